@@ -3,9 +3,10 @@
 
 use anyhow::Result;
 use base64::Engine;
-use clap::{Arg, Command};
+use clap::{Arg, ArgMatches, Command};
 use rust_embed::RustEmbed;
 use salvo::prelude::*;
+use salvo::websocket::{Message, WebSocket, WebSocketUpgrade};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -26,9 +27,27 @@ struct Assets;
 // 移除未使用的导入
 // Logger功能暂时禁用
 
+use rwkv_tts_rs::api_auth::{
+    parse_bearer_token, ApiKeyIdentity, ApiKeyStore, ApiScope, AuthConfig,
+};
+use rwkv_tts_rs::audio_encoder::{
+    encode_audio, encode_stream_chunk, normalize_samples, resample, streaming_wav_header,
+    validate_output_sample_rate, AudioEncodeOptions, AudioFormat, Resampler, MODEL_SAMPLE_RATE,
+};
+use rwkv_tts_rs::batch_types::{
+    CancelReason, CancelToken, QueueFull, RequestPriority, TtsCancelled,
+};
+use rwkv_tts_rs::job_webhook::{WebhookConfig, WebhookNotifier};
 use rwkv_tts_rs::lightweight_tts_pipeline::{LightweightTtsPipeline, LightweightTtsPipelineArgs};
+use rwkv_tts_rs::metrics::global_metrics;
+use rwkv_tts_rs::rate_limit::{RateLimitClient, RateLimitConfig, RateLimitExceeded, RateLimiter};
 use rwkv_tts_rs::ref_audio_utilities::RefAudioUtilities;
-use rwkv_tts_rs::voice_feature_manager::{VoiceFeatureManager, VoiceMetadata};
+use rwkv_tts_rs::sentence_buffer::SentenceBuffer;
+use rwkv_tts_rs::server_config::{OnnxConfig, ServerConfig};
+use rwkv_tts_rs::tts_job_manager::{JobOutput, JobStatus, TtsJob, TtsJobManager};
+use rwkv_tts_rs::voice_feature_manager::{
+    VoiceFeatureManager, VoiceImportResult, VoiceMetadata, VoicePackage, VoiceUpdate,
+};
 use web_rwkv::runtime::model::Quant;
 
 /// Web UI TTS请求参数（支持字符串类型的speed）
@@ -45,6 +64,27 @@ struct WebTtsRequest {
     emotion: Option<String>,
     pitch: Option<String>,
     prompt_text: Option<String>,
+    /// 输出格式。/api/tts："json"（默认，Base64）、"wav"、"wav_f32"、"mp3"、"opus"、"flac"、"pcm"，
+    /// 以及8kHz电话格式"ulaw"、"alaw"、"wav_ulaw"、"wav_alaw"；
+    /// 流式接口："wav"（默认）、"pcm"（16位小端裸数据）、"ulaw"或"alaw"（8kHz G.711裸帧）
+    response_format: Option<String>,
+    /// 有损格式（mp3、opus）的比特率，单位kbps
+    bitrate: Option<u32>,
+    /// 输出采样率（8000-48000Hz），默认为模型原生的16000Hz
+    sample_rate: Option<u32>,
+    /// 流式接口每块音频对应的semantic token数（50个约为1秒）
+    chunk_tokens: Option<usize>,
+    /// 临时参考音频（Base64编码），与prompt_text一起用于一次性zero-shot克隆，仅/api/tts支持
+    ref_audio_base64: Option<String>,
+    /// 参考音频格式："wav"（默认）或"mp3"
+    ref_audio_format: Option<String>,
+    /// 将临时参考音频保存为音色，值为音色名称（需要admin权限）
+    save_as: Option<String>,
+    /// 推理超时（毫秒），超时返回504；不能超过服务器的推理超时配置，超过时按服务器配置
+    timeout_ms: Option<u64>,
+    /// 调度优先级："interactive"、"normal"或"bulk"。默认值：流式接口和WebSocket为interactive，
+    /// /api/tts为normal，异步任务为bulk
+    priority: Option<RequestPriority>,
 }
 
 // VoiceExtractRequest结构体已移除，因为使用multipart表单处理
@@ -85,6 +125,10 @@ struct TtsResponse {
     audio_base64: Option<String>,
     duration_ms: Option<u64>,
     rtf: Option<f64>,
+    sample_rate: Option<u32>,
+    /// 通过save_as保存的音色ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    voice_id: Option<String>,
 }
 
 /// 错误响应
@@ -94,62 +138,136 @@ struct ErrorResponse {
     error: String,
 }
 
-/// 将f32音频样本转换为WAV格式的字节数据
-fn convert_samples_to_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
-    let mut wav_data = Vec::new();
+/// OpenAI兼容的语音合成请求（/v1/audio/speech）
+#[derive(Debug, Deserialize)]
+struct OaiSpeechRequest {
+    /// 模型名称，仅为兼容OpenAI客户端而接收，不参与推理
+    #[allow(dead_code)]
+    model: Option<String>,
+    input: String,
+    /// 音色ID或音色名称，未匹配到时使用默认属性合成
+    voice: Option<String>,
+    /// 输出格式：mp3（默认）、opus、flac、wav、pcm
+    response_format: Option<String>,
+    /// 语速倍率，范围0.25-4.0，1.0为正常语速
+    speed: Option<f32>,
+    /// 输出采样率（扩展字段，非OpenAI标准），默认为模型原生的16000Hz
+    sample_rate: Option<u32>,
+}
 
-    // 分析音频数据范围以确定合适的缩放因子
-    let max_abs = samples.iter().map(|x| x.abs()).fold(0.0f32, f32::max);
-    let scale_factor = if max_abs > 0.0 {
-        // 如果最大值超过1.0，需要归一化；如果小于1.0，需要放大
-        if max_abs > 1.0 {
-            1.0 / max_abs
-        } else {
-            // 对于小幅度信号，适度放大但不超过安全范围
-            (0.8 / max_abs).min(10.0)
-        }
-    } else {
-        1.0
-    };
+/// OpenAI风格的错误详情
+#[derive(Debug, Serialize)]
+struct OaiErrorDetail {
+    message: String,
+    #[serde(rename = "type")]
+    error_type: String,
+    param: Option<String>,
+    code: Option<String>,
+}
 
-    info!(
-        "音频数据分析: max_abs={:.6}, scale_factor={:.6}",
-        max_abs, scale_factor
-    );
+/// OpenAI风格的错误响应
+#[derive(Debug, Serialize)]
+struct OaiErrorResponse {
+    error: OaiErrorDetail,
+}
 
-    // WAV文件头
-    wav_data.extend_from_slice(b"RIFF");
-    let file_size = 36 + samples.len() * 2; // 16位音频
-    wav_data.extend_from_slice(&(file_size as u32).to_le_bytes());
-    wav_data.extend_from_slice(b"WAVE");
+/// 渲染OpenAI风格的错误响应
+fn render_oai_error(res: &mut Response, status: StatusCode, message: String, param: Option<&str>) {
+    let error_type = if status.is_server_error() {
+        "server_error"
+    } else {
+        "invalid_request_error"
+    };
+    res.status_code(status);
+    res.render(Json(OaiErrorResponse {
+        error: OaiErrorDetail {
+            message,
+            error_type: error_type.to_string(),
+            param: param.map(|p| p.to_string()),
+            code: None,
+        },
+    }));
+}
 
-    // fmt chunk
-    wav_data.extend_from_slice(b"fmt ");
-    wav_data.extend_from_slice(&16u32.to_le_bytes()); // chunk size
-    wav_data.extend_from_slice(&1u16.to_le_bytes()); // audio format (PCM)
-    wav_data.extend_from_slice(&1u16.to_le_bytes()); // num channels (mono)
-    wav_data.extend_from_slice(&sample_rate.to_le_bytes()); // sample rate
-    wav_data.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
-    wav_data.extend_from_slice(&2u16.to_le_bytes()); // block align
-    wav_data.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+/// 协商/api/tts的响应形式：返回None表示使用JSON（Base64）响应
+///
+/// response_format字段优先于Accept头；Accept头中第一个可识别的音频类型生效，
+/// 浏览器默认的`*/*`不会触发二进制响应，保证Web UI继续使用JSON模式
+fn negotiate_tts_output(
+    response_format: Option<&str>,
+    accept: Option<&str>,
+) -> Result<Option<AudioFormat>, String> {
+    if let Some(name) = response_format {
+        if name.eq_ignore_ascii_case("json") {
+            return Ok(None);
+        }
+        return AudioFormat::from_name(name).map(Some).ok_or_else(|| {
+            format!(
+                "不支持的输出格式: {}，支持: json, {}",
+                name,
+                AudioFormat::supported_names()
+            )
+        });
+    }
 
-    // data chunk
-    wav_data.extend_from_slice(b"data");
-    wav_data.extend_from_slice(&(samples.len() * 2).to_le_bytes() as &[u8]);
+    Ok(accept.and_then(|accept| {
+        accept
+            .split(',')
+            .filter_map(|item| item.split(';').next())
+            .find_map(|mime| AudioFormat::from_mime(mime.trim()))
+    }))
+}
 
-    // 音频数据 (转换f32到i16，应用动态缩放)
-    for sample in samples {
-        let scaled_sample = sample * scale_factor;
-        let sample_i16 = (scaled_sample.clamp(-1.0, 1.0) * 32767.0) as i16;
-        wav_data.extend_from_slice(&sample_i16.to_le_bytes());
+/// 解析并校验请求的输出采样率
+///
+/// 未指定时使用格式要求的采样率（G.711为8kHz），否则使用模型原生采样率；
+/// 指定时还须为该格式编码器支持的采样率（如Opus不支持44100Hz）
+fn resolve_output_sample_rate(
+    sample_rate: Option<u32>,
+    format: Option<AudioFormat>,
+) -> Result<u32, String> {
+    let required = format.and_then(|f| f.required_sample_rate());
+    let sample_rate = sample_rate.or(required).unwrap_or(MODEL_SAMPLE_RATE);
+    if let Some(required) = required {
+        if sample_rate != required {
+            return Err(format!(
+                "该输出格式要求采样率为{}Hz，当前为: {}",
+                required, sample_rate
+            ));
+        }
+    }
+    validate_output_sample_rate(sample_rate).map_err(|e| e.to_string())?;
+    if let Some(format) = format.filter(|f| !f.supports_sample_rate(sample_rate)) {
+        return Err(format!("{:?}格式不支持{}Hz采样率", format, sample_rate));
     }
+    Ok(sample_rate)
+}
+
+/// 校验请求的比特率是否适用于输出格式（仅MP3/Opus可设置）
+fn validate_output_bitrate(format: AudioFormat, bitrate_kbps: Option<u32>) -> Result<(), String> {
+    format
+        .validate_bitrate(bitrate_kbps)
+        .map_err(|e| e.to_string())
+}
 
-    wav_data
+/// 使用共享编码层将音频样本编码为指定格式
+fn encode_output_audio(
+    format: AudioFormat,
+    samples: &[f32],
+    sample_rate: u32,
+    bitrate_kbps: Option<u32>,
+) -> Result<Vec<u8>, String> {
+    let options = AudioEncodeOptions::new(format, sample_rate).with_bitrate(bitrate_kbps);
+    encode_audio(samples, &options).map_err(|e| format!("音频编码失败: {}", e))
 }
 
 /// 计算实时因子(RTF)
-fn calculate_rtf(audio_data: &[f32], processing_time: std::time::Duration) -> f64 {
-    let audio_duration = audio_data.len() as f64 / 16000.0; // 假设16kHz采样率
+fn calculate_rtf(
+    audio_data: &[f32],
+    sample_rate: u32,
+    processing_time: std::time::Duration,
+) -> f64 {
+    let audio_duration = audio_data.len() as f64 / sample_rate as f64;
     let processing_seconds = processing_time.as_secs_f64();
     if audio_duration > 0.0 {
         processing_seconds / audio_duration
@@ -158,17 +276,101 @@ fn calculate_rtf(audio_data: &[f32], processing_time: std::time::Duration) -> f6
     }
 }
 
+/// 预热合成状态
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum WarmupStatus {
+    /// 预热进行中
+    Pending,
+    /// 预热成功
+    Completed { elapsed_ms: u64 },
+    /// 预热失败，按指数退避重试中
+    Failed { error: String, attempts: u32 },
+}
+
 /// 应用状态
 #[derive(Debug, Clone)]
 struct AppState {
-    #[allow(dead_code)]
     start_time: std::time::Instant,
-    #[allow(dead_code)]
     model_path: String,
-    #[allow(dead_code)]
     vocab_path: String,
+    quant_layers: usize,
+    quant_type: String,
     tts_pipeline: Arc<LightweightTtsPipeline>,
     voice_manager: Arc<VoiceFeatureManager>,
+    warmup_status: Arc<std::sync::RwLock<WarmupStatus>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    job_manager: Arc<TtsJobManager>,
+    onnx_config: Arc<OnnxConfig>,
+    active_requests: Arc<ActiveRequests>,
+}
+
+/// 健康检查响应
+#[derive(Debug, Serialize)]
+struct HealthResponse {
+    status: &'static str,
+}
+
+/// 就绪检查响应
+#[derive(Debug, Serialize)]
+struct ReadyResponse {
+    ready: bool,
+    shutting_down: bool,
+    model_loaded: bool,
+    onnx_pools_ready: bool,
+    warmup: WarmupStatus,
+}
+
+/// ONNX会话池大小
+#[derive(Debug, Serialize)]
+struct OnnxPoolStatus {
+    bicodec_tokenize: usize,
+    wav2vec2: usize,
+    bicodec_detokenize: usize,
+}
+
+/// 请求队列状态
+#[derive(Debug, Serialize)]
+struct QueueStatus {
+    /// 等待进入槽位的请求数
+    depth: usize,
+    /// 队列容量（0表示不限制）
+    max_depth: usize,
+    /// 已提交、尚未返回结果的请求数
+    in_flight: usize,
+    /// 正在槽位中解码的请求数
+    active_slots: usize,
+    /// 状态槽位数
+    slots: usize,
+    /// 新请求预计开始推理前的等待时间
+    estimated_wait_ms: u64,
+    /// 因队列已满被拒绝的请求总数
+    rejected: u64,
+}
+
+/// 量化配置
+#[derive(Debug, Serialize)]
+struct QuantStatus {
+    layers: usize,
+    quant_type: String,
+}
+
+/// 服务状态响应
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    success: bool,
+    version: &'static str,
+    uptime_secs: u64,
+    ready: bool,
+    model_path: String,
+    vocab_path: String,
+    quantization: QuantStatus,
+    batch_config: Option<rwkv_tts_rs::batch_types::DynamicBatchConfig>,
+    queue: Option<QueueStatus>,
+    onnx_pools: Option<OnnxPoolStatus>,
+    voice_cache: rwkv_tts_rs::voice_feature_manager::CacheStats,
+    voice_cache_hit_rate: f64,
+    warmup: WarmupStatus,
 }
 
 /// 全局应用状态
@@ -184,11 +386,281 @@ fn get_global_app_state() -> AppState {
     GLOBAL_APP_STATE.get().expect("应用状态未初始化").clone()
 }
 
-/// 处理TTS请求
+/// 处理TTS请求（JSON，或附带参考音频文件的multipart表单）
 #[handler]
-async fn handle_tts(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
-    // 直接处理JSON请求，不再支持文件上传
-    handle_tts_json(req, res).await
+async fn handle_tts(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Result<(), StatusError> {
+    handle_tts_json(req, depot, res).await
+}
+
+/// 含Base64参考音频的TTS请求体大小上限
+const TTS_REQUEST_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// 一次性使用的参考音频临时文件，离开作用域时删除
+struct TempReferenceAudio {
+    path: PathBuf,
+}
+
+impl TempReferenceAudio {
+    /// 临时文件路径，格式仅支持wav和mp3
+    fn temp_path(app_state: &AppState, format: &str) -> Result<PathBuf, String> {
+        let format = format.to_ascii_lowercase();
+        if format != "wav" && format != "mp3" {
+            return Err(format!("不支持的参考音频格式: {}，可选: wav, mp3", format));
+        }
+        Ok(app_state
+            .voice_manager
+            .upload_temp_dir()
+            .join(format!("{}.{}", Uuid::new_v4(), format)))
+    }
+
+    /// 写入Base64编码的参考音频
+    async fn from_base64(app_state: &AppState, data: &str, format: &str) -> Result<Self, String> {
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(data.trim())
+            .map_err(|e| format!("参考音频Base64解码失败: {}", e))?;
+        if data.is_empty() {
+            return Err("参考音频为空".to_string());
+        }
+        let path = Self::temp_path(app_state, format)?;
+        fs::write(&path, data)
+            .await
+            .map_err(|e| format!("保存参考音频失败: {}", e))?;
+        Ok(Self { path })
+    }
+
+    /// 复制multipart上传的参考音频
+    async fn from_upload(
+        app_state: &AppState,
+        source: &Path,
+        format: &str,
+    ) -> Result<Self, String> {
+        let path = Self::temp_path(app_state, format)?;
+        fs::copy(source, &path)
+            .await
+            .map_err(|e| format!("保存参考音频失败: {}", e))?;
+        Ok(Self { path })
+    }
+
+    fn path_str(&self) -> &str {
+        self.path.to_str().unwrap_or_default()
+    }
+}
+
+impl Drop for TempReferenceAudio {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// 解析multipart表单形式的TTS请求，参考音频通过ref_audio文件字段上传
+async fn parse_tts_form(
+    req: &mut Request,
+    app_state: &AppState,
+) -> Result<(WebTtsRequest, Option<TempReferenceAudio>), String> {
+    let priority = match req.form::<String>("priority").await {
+        Some(name) => Some(
+            serde_json::from_value(serde_json::Value::String(name.clone())).map_err(|_| {
+                format!("无效的priority: {}，可选: interactive, normal, bulk", name)
+            })?,
+        ),
+        None => None,
+    };
+    let request = WebTtsRequest {
+        text: req.form::<String>("text").await.unwrap_or_default(),
+        temperature: req.form::<f32>("temperature").await,
+        top_p: req.form::<f32>("top_p").await,
+        speed: req.form::<String>("speed").await.map(|speed| {
+            speed
+                .parse::<f32>()
+                .map_or(serde_json::Value::String(speed), serde_json::Value::from)
+        }),
+        voice_id: req.form::<String>("voice_id").await,
+        seed: req.form::<u64>("seed").await,
+        age: req.form::<String>("age").await,
+        gender: req.form::<String>("gender").await,
+        emotion: req.form::<String>("emotion").await,
+        pitch: req.form::<String>("pitch").await,
+        prompt_text: req.form::<String>("prompt_text").await,
+        response_format: req.form::<String>("response_format").await,
+        bitrate: req.form::<u32>("bitrate").await,
+        sample_rate: req.form::<u32>("sample_rate").await,
+        chunk_tokens: None,
+        ref_audio_base64: None,
+        ref_audio_format: None,
+        save_as: req.form::<String>("save_as").await,
+        timeout_ms: req.form::<u64>("timeout_ms").await,
+        priority,
+    };
+
+    let reference = match req.file("ref_audio").await {
+        Some(file) => {
+            let format = file
+                .name()
+                .and_then(|name| Path::new(name).extension())
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("wav")
+                .to_string();
+            Some(TempReferenceAudio::from_upload(app_state, file.path(), &format).await?)
+        }
+        None => None,
+    };
+
+    Ok((request, reference))
+}
+
+/// 当前请求是否有admin权限（未启用认证时视为有）
+fn has_admin_scope(depot: &Depot) -> bool {
+    match depot.obtain::<ApiKeyIdentity>() {
+        Ok(identity) => identity.scope.allows(ApiScope::Admin),
+        Err(_) => true,
+    }
+}
+
+/// 访问请求/任务时用于过滤的密钥名称：非admin密钥只能访问自己提交的，admin或未启用鉴权时为None
+fn access_owner(depot: &Depot) -> Option<String> {
+    match depot.obtain::<ApiKeyIdentity>() {
+        Ok(identity) if !identity.scope.allows(ApiScope::Admin) => Some(identity.name.clone()),
+        _ => None,
+    }
+}
+
+/// 校验临时参考音频相关参数
+fn validate_reference_request(
+    request: &WebTtsRequest,
+    has_reference: bool,
+    depot: &Depot,
+) -> Result<(), (StatusCode, String)> {
+    if !has_reference {
+        if request.save_as.is_some() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "save_as需要同时提供参考音频".to_string(),
+            ));
+        }
+        return Ok(());
+    }
+
+    if request.voice_id.as_deref().is_some_and(|id| !id.is_empty()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "voice_id与参考音频不能同时使用".to_string(),
+        ));
+    }
+    if request
+        .prompt_text
+        .as_deref()
+        .is_none_or(|text| text.trim().is_empty())
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "使用参考音频时必须提供prompt_text（参考音频对应的文本）".to_string(),
+        ));
+    }
+    if let Some(name) = &request.save_as {
+        if name.trim().is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "save_as音色名称不能为空".to_string(),
+            ));
+        }
+        if !has_admin_scope(depot) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "保存音色（save_as）需要 admin 权限".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 将临时参考音频转换为tokens写入流水线参数
+async fn apply_reference_audio(
+    reference: &TempReferenceAudio,
+    pipeline_args: &mut LightweightTtsPipelineArgs,
+    app_state: &AppState,
+) -> Result<(), String> {
+    let (global_tokens, semantic_tokens) = app_state
+        .tts_pipeline
+        .process_reference_audio(reference.path_str())
+        .await
+        .map_err(|e| format!("参考音频处理失败: {}", e))?;
+    info!(
+        "🎙️ 使用临时参考音频: global_tokens {}, semantic_tokens {}",
+        global_tokens.len(),
+        semantic_tokens.len()
+    );
+
+    pipeline_args.zero_shot = true;
+    pipeline_args.voice_global_tokens = Some(global_tokens);
+    pipeline_args.voice_semantic_tokens = Some(semantic_tokens);
+    Ok(())
+}
+
+/// 将已转换为tokens的临时参考音频保存为音色（save_as），返回音色ID
+///
+/// 在合成成功后调用，避免请求被拒绝或合成失败时留下音色。
+async fn save_reference_voice(
+    reference: &TempReferenceAudio,
+    name: &str,
+    pipeline_args: &LightweightTtsPipelineArgs,
+    app_state: &AppState,
+) -> Result<String, String> {
+    let (Some(global_tokens), Some(semantic_tokens)) = (
+        pipeline_args.voice_global_tokens.clone(),
+        pipeline_args.voice_semantic_tokens.clone(),
+    ) else {
+        return Err("参考音频尚未转换为tokens".to_string());
+    };
+    let (audio_duration, sample_rate) = calculate_audio_info(reference.path_str())
+        .map_err(|e| format!("参考音频信息计算失败: {}", e))?;
+    let voice_id = app_state
+        .voice_manager
+        .save_voice_feature(
+            name.trim().to_string(),
+            pipeline_args.prompt_text.clone(),
+            String::new(),
+            global_tokens,
+            semantic_tokens,
+            audio_duration,
+            sample_rate,
+        )
+        .await
+        .map_err(|e| format!("保存音色失败: {}", e))?;
+    if let Err(e) = app_state
+        .voice_manager
+        .save_reference_audio(&voice_id, &reference.path)
+        .await
+    {
+        warn!("保存音色 {} 的参考音频失败: {}", voice_id, e);
+    }
+    info!("💾 临时参考音频已保存为音色 {}", voice_id);
+    Ok(voice_id)
+}
+
+/// 获取当前请求的限流器和限流对象（由rate_limit_handler注入），未启用限流时返回None
+fn quota_client(depot: &Depot) -> Option<(Arc<RateLimiter>, RateLimitClient)> {
+    let limiter = get_global_app_state().rate_limiter?;
+    let client = depot.obtain::<RateLimitClient>().ok()?.clone();
+    Some((limiter, client))
+}
+
+/// 按文本字符数扣除每日配额
+fn charge_text_quota(depot: &Depot, text: &str) -> Result<(), RateLimitExceeded> {
+    match quota_client(depot) {
+        Some((limiter, client)) => limiter.charge_characters(&client, text.chars().count() as u64),
+        None => Ok(()),
+    }
+}
+
+/// 累计本次合成的音频秒数
+fn record_audio_quota(depot: &Depot, samples: usize, sample_rate: u32) {
+    if let Some((limiter, client)) = quota_client(depot) {
+        limiter.record_audio_seconds(&client, samples as f64 / sample_rate as f64);
+    }
 }
 
 /// 提取音频特征
@@ -205,13 +677,8 @@ async fn extract_audio_features(
     }
 
     // 验证ONNX模型文件是否存在
-    let onnx_files = [
-        "assets/model/BiCodecTokenize.onnx",
-        "assets/model/wav2vec2-large-xlsr-53.onnx",
-        "assets/model/BiCodecDetokenize.onnx",
-    ];
-
-    for onnx_file in &onnx_files {
+    let onnx_config = get_global_app_state().onnx_config;
+    for onnx_file in &onnx_config.model_paths() {
         if !std::path::Path::new(onnx_file).exists() {
             let error_msg = format!("ONNX模型文件不存在: {}", onnx_file);
             error!("{}", error_msg);
@@ -221,11 +688,11 @@ async fn extract_audio_features(
 
     // 创建RefAudioUtilities实例
     let mut ref_audio_utils = match RefAudioUtilities::new(
-        "assets/model/BiCodecTokenize.onnx",
-        "assets/model/wav2vec2-large-xlsr-53.onnx",
+        &onnx_config.tokenize_path,
+        &onnx_config.wav2vec2_path,
         6.0, // ref_segment_duration
         320, // latent_hop_length
-        Some("assets/model/BiCodecDetokenize.onnx"),
+        Some(&onnx_config.detokenize_path),
     ) {
         Ok(utils) => {
             info!("RefAudioUtilities初始化成功");
@@ -446,38 +913,19 @@ fn calculate_audio_info(audio_path: &str) -> Result<(f32, u32), anyhow::Error> {
     }
 }
 
-/// 处理JSON格式的TTS请求（原有逻辑）
-async fn handle_tts_json(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
-    let total_start = std::time::Instant::now();
-
-    // 1. 解析JSON请求
-    let parse_start = std::time::Instant::now();
-    let web_tts_request: WebTtsRequest = match req.parse_json().await {
-        Ok(request) => request,
-        Err(e) => {
-            error!("JSON解析失败: {}", e);
-            res.status_code(StatusCode::BAD_REQUEST);
-            res.render(Json(ErrorResponse {
-                success: false,
-                error: format!("JSON解析失败: {}", e),
-            }));
-            return Ok(());
-        }
-    };
-    let parse_time = parse_start.elapsed();
-
-    info!(
-        "🎯 收到TTS请求: text='{}', voice_id='{:?}'",
-        web_tts_request.text, web_tts_request.voice_id
-    );
-    info!(
-        "  ⏱️  请求解析耗时: {:.2}ms",
-        parse_time.as_secs_f64() * 1000.0
-    );
-
-    // 2. 获取应用状态和创建参数
-    let setup_start = std::time::Instant::now();
-    let app_state = get_global_app_state();
+/// 将Web请求转换为流水线参数（加载音色特征、规范化语速和音调）
+async fn build_pipeline_args(
+    web_tts_request: WebTtsRequest,
+    default_priority: RequestPriority,
+    app_state: &AppState,
+) -> Result<LightweightTtsPipelineArgs, String> {
+    // 临时参考音频由/api/tts在调用前取出，其他接口不支持
+    if web_tts_request.ref_audio_base64.is_some() || web_tts_request.save_as.is_some() {
+        return Err("该接口不支持ref_audio_base64和save_as，请使用/api/tts".to_string());
+    }
+    if web_tts_request.timeout_ms == Some(0) {
+        return Err("timeout_ms必须大于0".to_string());
+    }
 
     // 处理音色ID参数
     let (_use_voice_clone, voice_feature, prompt_text_from_voice) =
@@ -497,12 +945,7 @@ async fn handle_tts_json(req: &mut Request, res: &mut Response) -> Result<(), St
                     }
                     Err(e) => {
                         error!("加载音色特征失败: {}", e);
-                        res.status_code(StatusCode::BAD_REQUEST);
-                        res.render(Json(ErrorResponse {
-                            success: false,
-                            error: format!("音色ID '{}' 不存在或加载失败: {}", voice_id, e),
-                        }));
-                        return Ok(());
+                        return Err(format!("音色ID '{}' 不存在或加载失败: {}", voice_id, e));
                     }
                 }
             } else {
@@ -553,7 +996,7 @@ async fn handle_tts_json(req: &mut Request, res: &mut Response) -> Result<(), St
         None => "medium".to_string(), // 默认语速
     };
 
-    let pipeline_args = LightweightTtsPipelineArgs {
+    Ok(LightweightTtsPipelineArgs {
         text: web_tts_request.text.clone(),
         ref_audio_path: String::new(), // 不再支持ref_audio_path
         zero_shot: zero_shot_mode,
@@ -580,45 +1023,368 @@ async fn handle_tts_json(req: &mut Request, res: &mut Response) -> Result<(), St
         // 如果有音色特征，传入tokens并转换为i64类型
         voice_global_tokens: voice_feature.as_ref().map(|vf| vf.global_tokens.clone()),
         voice_semantic_tokens: voice_feature.as_ref().map(|vf| vf.semantic_tokens.clone()),
+        timeout_ms: web_tts_request.timeout_ms,
+        priority: web_tts_request.priority.unwrap_or(default_priority),
         ..Default::default()
+    })
+}
+
+/// 进行中的合成请求，按请求ID索引，用于显式取消
+#[derive(Debug, Default)]
+struct ActiveRequests {
+    requests: std::sync::Mutex<HashMap<String, ActiveRequest>>,
+}
+
+#[derive(Debug)]
+struct ActiveRequest {
+    /// 提交请求的API密钥名称，未启用认证时为None
+    owner: Option<String>,
+    cancel: CancelToken,
+}
+
+/// 请求结束（守卫丢弃）时从登记表中移除
+struct ActiveRequestGuard {
+    registry: Arc<ActiveRequests>,
+    request_id: String,
+}
+
+impl Drop for ActiveRequestGuard {
+    fn drop(&mut self) {
+        self.registry
+            .requests
+            .lock()
+            .unwrap()
+            .remove(&self.request_id);
+    }
+}
+
+impl ActiveRequests {
+    /// 登记请求，同一ID的请求仍在进行时返回错误
+    fn register(
+        self: &Arc<Self>,
+        request_id: &str,
+        owner: Option<String>,
+        cancel: CancelToken,
+    ) -> Result<ActiveRequestGuard, String> {
+        let mut requests = self.requests.lock().unwrap();
+        if requests.contains_key(request_id) {
+            return Err(format!("请求ID {} 已有进行中的请求", request_id));
+        }
+        requests.insert(request_id.to_string(), ActiveRequest { owner, cancel });
+        Ok(ActiveRequestGuard {
+            registry: self.clone(),
+            request_id: request_id.to_string(),
+        })
+    }
+
+    /// 取消请求，`owner`为None时可取消任意请求，否则只能取消该密钥提交的请求；返回是否找到请求
+    fn cancel(&self, request_id: &str, owner: Option<&str>) -> bool {
+        match self.requests.lock().unwrap().get(request_id) {
+            Some(request) if owner.is_none() || request.owner.as_deref() == owner => {
+                request.cancel.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 请求ID只允许字母、数字、`-`、`_`和`.`
+fn is_valid_request_id(request_id: &str) -> bool {
+    !request_id.is_empty()
+        && request_id.len() <= 128
+        && request_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// 读取`X-Request-Id`（未提供时生成）并登记请求的取消令牌，响应头中返回该ID
+fn register_active_request(
+    req: &Request,
+    depot: &Depot,
+    res: &mut Response,
+    app_state: &AppState,
+    cancel: &CancelToken,
+) -> Result<ActiveRequestGuard, (StatusCode, String)> {
+    let request_id = match req.headers().get("x-request-id") {
+        Some(value) => {
+            let request_id = value.to_str().unwrap_or_default().trim();
+            if !is_valid_request_id(request_id) {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "X-Request-Id只能包含字母、数字、'-'、'_'和'.'，且不超过128个字符".to_string(),
+                ));
+            }
+            request_id.to_string()
+        }
+        None => Uuid::new_v4().to_string(),
     };
-    let setup_time = setup_start.elapsed();
-    info!(
-        "  ⏱️  参数设置耗时: {:.2}ms",
-        setup_time.as_secs_f64() * 1000.0
-    );
+    let owner = depot
+        .obtain::<ApiKeyIdentity>()
+        .ok()
+        .map(|identity| identity.name.clone());
+    let guard = app_state
+        .active_requests
+        .register(&request_id, owner, cancel.clone())
+        .map_err(|e| (StatusCode::CONFLICT, e))?;
+    res.add_header("x-request-id", request_id, true).unwrap();
+    Ok(guard)
+}
 
-    // 3. TTS生成（主要处理时间）
-    let tts_start = std::time::Instant::now();
-    let audio_data = match app_state.tts_pipeline.generate_speech(&pipeline_args).await {
-        Ok(data) => data,
+/// 合成失败时的状态码：被取消为499，超过截止时间为504，队列已满为503，其余为500
+fn synthesis_error_status(error: &anyhow::Error) -> StatusCode {
+    if error.downcast_ref::<QueueFull>().is_some() {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+    match error.downcast_ref::<TtsCancelled>() {
+        Some(TtsCancelled(CancelReason::Cancelled)) => {
+            StatusCode::from_u16(499).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        Some(TtsCancelled(CancelReason::DeadlineExceeded)) => StatusCode::GATEWAY_TIMEOUT,
+        None => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// 处理JSON格式的TTS请求（原有逻辑）
+async fn handle_tts_json(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Result<(), StatusError> {
+    let total_start = std::time::Instant::now();
+    let accept = req
+        .headers()
+        .get("accept")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    // 1. 解析请求（JSON，或附带参考音频文件的multipart表单）
+    let parse_start = std::time::Instant::now();
+    let app_state = get_global_app_state();
+    let is_multipart = req
+        .content_type()
+        .map(|ct| ct.type_() == "multipart")
+        .unwrap_or(false);
+    let (mut web_tts_request, uploaded_reference) = if is_multipart {
+        match parse_tts_form(req, &app_state).await {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                render_error(res, StatusCode::BAD_REQUEST, e);
+                return Ok(());
+            }
+        }
+    } else {
+        match req
+            .parse_json_with_max_size::<WebTtsRequest>(TTS_REQUEST_MAX_BYTES)
+            .await
+        {
+            Ok(request) => (request, None),
+            Err(e) => {
+                error!("JSON解析失败: {}", e);
+                res.status_code(StatusCode::BAD_REQUEST);
+                res.render(Json(ErrorResponse {
+                    success: false,
+                    error: format!("JSON解析失败: {}", e),
+                }));
+                return Ok(());
+            }
+        }
+    };
+
+    // 临时参考音频：用于本次合成，指定save_as时保存为音色
+    let has_reference = uploaded_reference.is_some() || web_tts_request.ref_audio_base64.is_some();
+    if let Err((status, e)) = validate_reference_request(&web_tts_request, has_reference, depot) {
+        render_error(res, status, e);
+        return Ok(());
+    }
+    let save_as = web_tts_request.save_as.take();
+    let reference = match (uploaded_reference, web_tts_request.ref_audio_base64.take()) {
+        (Some(reference), _) => Some(reference),
+        (None, Some(data)) => {
+            let format = web_tts_request.ref_audio_format.as_deref().unwrap_or("wav");
+            match TempReferenceAudio::from_base64(&app_state, &data, format).await {
+                Ok(reference) => Some(reference),
+                Err(e) => {
+                    render_error(res, StatusCode::BAD_REQUEST, e);
+                    return Ok(());
+                }
+            }
+        }
+        (None, None) => None,
+    };
+    let parse_time = parse_start.elapsed();
+
+    let (binary_format, output_sample_rate) = match negotiate_tts_output(
+        web_tts_request.response_format.as_deref(),
+        accept.as_deref(),
+    )
+    .and_then(|format| {
+        validate_output_bitrate(format.unwrap_or(AudioFormat::Wav), web_tts_request.bitrate)?;
+        resolve_output_sample_rate(web_tts_request.sample_rate, format).map(|rate| (format, rate))
+    }) {
+        Ok(negotiated) => negotiated,
         Err(e) => {
-            error!("生成TTS音频失败: {}", e);
-            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.status_code(StatusCode::BAD_REQUEST);
             res.render(Json(ErrorResponse {
                 success: false,
-                error: format!("生成TTS音频失败: {}", e),
+                error: e,
             }));
             return Ok(());
         }
     };
-    let tts_time = tts_start.elapsed();
+
     info!(
-        "  ⏱️  TTS生成耗时: {:.2}ms",
-        tts_time.as_secs_f64() * 1000.0
+        "🎯 收到TTS请求: text='{}', voice_id='{:?}', 输出: {}",
+        web_tts_request.text,
+        web_tts_request.voice_id,
+        binary_format.map_or("json", |f| f.content_type())
     );
-
-    // 4. 音频格式转换
-    let convert_start = std::time::Instant::now();
-    let wav_data = convert_samples_to_wav(&audio_data, 16000);
-    let convert_time = convert_start.elapsed();
     info!(
-        "  ⏱️  WAV转换耗时: {:.2}ms",
-        convert_time.as_secs_f64() * 1000.0
+        "  ⏱️  请求解析耗时: {:.2}ms",
+        parse_time.as_secs_f64() * 1000.0
     );
 
-    // 5. Base64编码
-    let encode_start = std::time::Instant::now();
+    // 2. 创建参数
+    let setup_start = std::time::Instant::now();
+    let bitrate_kbps = web_tts_request.bitrate;
+    let mut pipeline_args =
+        match build_pipeline_args(web_tts_request, RequestPriority::Normal, &app_state).await {
+            Ok(args) => args,
+            Err(e) => {
+                res.status_code(StatusCode::BAD_REQUEST);
+                res.render(Json(ErrorResponse {
+                    success: false,
+                    error: e,
+                }));
+                return Ok(());
+            }
+        };
+    if let Some(reference) = &reference {
+        if let Err(e) = apply_reference_audio(reference, &mut pipeline_args, &app_state).await {
+            error!("{}", e);
+            render_error(res, StatusCode::BAD_REQUEST, e);
+            return Ok(());
+        }
+    }
+    let _active_request =
+        match register_active_request(req, depot, res, &app_state, &pipeline_args.cancel) {
+            Ok(guard) => guard,
+            Err((status, e)) => {
+                render_error(res, status, e);
+                return Ok(());
+            }
+        };
+    // 参数校验通过、即将开始合成时才扣除配额
+    if let Err(e) = charge_text_quota(depot, &pipeline_args.text) {
+        render_rate_limit_error(req, res, &e);
+        return Ok(());
+    }
+    let setup_time = setup_start.elapsed();
+    info!(
+        "  ⏱️  参数设置耗时: {:.2}ms",
+        setup_time.as_secs_f64() * 1000.0
+    );
+
+    // 3. TTS生成（主要处理时间）
+    let tts_start = std::time::Instant::now();
+    let audio_data = match app_state.tts_pipeline.generate_speech(&pipeline_args).await {
+        Ok(data) => data,
+        Err(e) => {
+            if let Some(full) = e.downcast_ref::<QueueFull>() {
+                render_queue_full(req, res, full);
+                return Ok(());
+            }
+            error!("生成TTS音频失败: {}", e);
+            res.status_code(synthesis_error_status(&e));
+            res.render(Json(ErrorResponse {
+                success: false,
+                error: format!("生成TTS音频失败: {}", e),
+            }));
+            return Ok(());
+        }
+    };
+    let tts_time = tts_start.elapsed();
+    info!(
+        "  ⏱️  TTS生成耗时: {:.2}ms",
+        tts_time.as_secs_f64() * 1000.0
+    );
+    record_audio_quota(depot, audio_data.len(), MODEL_SAMPLE_RATE);
+
+    // 合成成功后才按save_as保存音色，随后删除临时参考音频
+    let saved_voice_id = match (&reference, &save_as) {
+        (Some(reference), Some(name)) => {
+            match save_reference_voice(reference, name, &pipeline_args, &app_state).await {
+                Ok(voice_id) => Some(voice_id),
+                Err(e) => {
+                    error!("{}", e);
+                    render_error(res, StatusCode::INTERNAL_SERVER_ERROR, e);
+                    return Ok(());
+                }
+            }
+        }
+        _ => None,
+    };
+    drop(reference);
+
+    // 4. 重采样及音频格式转换
+    let convert_start = std::time::Instant::now();
+    let audio_data = resample(&audio_data, MODEL_SAMPLE_RATE, output_sample_rate);
+    let wav_data = match encode_output_audio(
+        binary_format.unwrap_or(AudioFormat::Wav),
+        &audio_data,
+        output_sample_rate,
+        bitrate_kbps,
+    ) {
+        Ok(data) => data,
+        Err(e) => {
+            error!("{}", e);
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render(Json(ErrorResponse {
+                success: false,
+                error: e,
+            }));
+            return Ok(());
+        }
+    };
+    let convert_time = convert_start.elapsed();
+    info!(
+        "  ⏱️  音频编码耗时: {:.2}ms",
+        convert_time.as_secs_f64() * 1000.0
+    );
+
+    // 二进制模式：直接返回音频字节，计时信息放入响应头
+    if let Some(format) = binary_format {
+        let total_time = total_start.elapsed();
+        let rtf = calculate_rtf(&audio_data, output_sample_rate, total_time);
+        let audio_duration_ms = audio_data.len() as u64 * 1000 / output_sample_rate as u64;
+        info!(
+            "📊 TTS请求完成: 音频时长 {}ms, 总耗时 {:.2}ms, RTF {:.3}, {} {} bytes",
+            audio_duration_ms,
+            total_time.as_secs_f64() * 1000.0,
+            rtf,
+            format.content_type(),
+            wav_data.len()
+        );
+
+        res.add_header("content-type", format.content_type(), true)
+            .unwrap();
+        res.add_header("x-sample-rate", output_sample_rate.to_string(), true)
+            .unwrap();
+        res.add_header("x-duration-ms", total_time.as_millis().to_string(), true)
+            .unwrap();
+        res.add_header("x-audio-duration-ms", audio_duration_ms.to_string(), true)
+            .unwrap();
+        res.add_header("x-rtf", format!("{:.4}", rtf), true)
+            .unwrap();
+        if let Some(voice_id) = &saved_voice_id {
+            res.add_header("x-voice-id", voice_id, true).unwrap();
+        }
+        res.write_body(wav_data).ok();
+        return Ok(());
+    }
+
+    // 5. Base64编码
+    let encode_start = std::time::Instant::now();
     let base64_audio = base64::engine::general_purpose::STANDARD.encode(&wav_data);
     let encode_time = encode_start.elapsed();
     info!(
@@ -628,8 +1394,8 @@ async fn handle_tts_json(req: &mut Request, res: &mut Response) -> Result<(), St
 
     // 6. 计算总体性能指标
     let total_time = total_start.elapsed();
-    let rtf = calculate_rtf(&audio_data, total_time);
-    let audio_duration = audio_data.len() as f64 / 16000.0;
+    let rtf = calculate_rtf(&audio_data, output_sample_rate, total_time);
+    let audio_duration = audio_data.len() as f64 / output_sample_rate as f64;
 
     info!("📊 TTS请求完成统计:");
     info!("  ⏱️  总耗时: {:.2}ms", total_time.as_secs_f64() * 1000.0);
@@ -683,6 +1449,8 @@ async fn handle_tts_json(req: &mut Request, res: &mut Response) -> Result<(), St
         audio_base64: Some(base64_audio),
         duration_ms: Some(total_time.as_millis() as u64),
         rtf: Some(rtf),
+        sample_rate: Some(output_sample_rate),
+        voice_id: saved_voice_id,
     }));
     let response_time = response_start.elapsed();
     info!(
@@ -693,155 +1461,1304 @@ async fn handle_tts_json(req: &mut Request, res: &mut Response) -> Result<(), St
     Ok(())
 }
 
-/// 提供Web UI界面
+/// 处理流式TTS请求：边生成边以chunked方式返回音频
 #[handler]
-async fn handle_web_ui(_req: &mut Request, res: &mut Response) {
-    match Assets::get("index.html") {
-        Some(content) => {
-            let html = std::str::from_utf8(content.data.as_ref())
-                .unwrap_or("<h1>Error reading embedded HTML</h1>");
-            res.render(Text::Html(html.to_string()));
-        }
-        None => {
-            res.render(Text::Html("<h1>Web UI not found</h1>".to_string()));
+async fn handle_tts_stream(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Result<(), StatusError> {
+    let web_tts_request: WebTtsRequest = match req.parse_json().await {
+        Ok(request) => request,
+        Err(e) => {
+            error!("JSON解析失败: {}", e);
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render(Json(ErrorResponse {
+                success: false,
+                error: format!("JSON解析失败: {}", e),
+            }));
+            return Ok(());
         }
-    }
-}
-
-/// 处理嵌入的静态文件
-#[handler]
-async fn handle_static_files(req: &mut Request, res: &mut Response) {
-    let path = req.param::<String>("path").unwrap_or_default();
-
-    // 处理根路径请求，返回index.html
-    let file_path = if path.is_empty() || path == "/" {
-        "index.html".to_string()
-    } else {
-        // 移除开头的斜杠（如果有的话）
-        path.trim_start_matches('/').to_string()
     };
 
-    // 调试：打印请求信息
-    debug!(
-        "请求路径: {}, 参数path: {}, 文件路径: {}",
-        req.uri().path(),
-        path,
-        file_path
+    info!(
+        "🎯 收到流式TTS请求: text='{}', voice_id='{:?}'",
+        web_tts_request.text, web_tts_request.voice_id
     );
 
-    // 首先尝试直接查找文件
-    if let Some(content) = Assets::get(&file_path) {
-        debug!("找到文件: {}", file_path);
-        // 根据文件扩展名设置Content-Type
-        let content_type = match file_path.split('.').next_back() {
-            Some("html") => "text/html; charset=utf-8",
-            Some("css") => "text/css",
-            Some("js") => "application/javascript",
-            Some("json") => "application/json",
-            Some("png") => "image/png",
-            Some("jpg") | Some("jpeg") => "image/jpeg",
-            Some("gif") => "image/gif",
-            Some("svg") => "image/svg+xml",
-            Some("ico") => "image/x-icon",
-            _ => "application/octet-stream",
-        };
-        res.add_header("content-type", content_type, true).unwrap();
-        // 复制数据以避免生命周期问题
-        let data = content.data.to_vec();
-        res.write_body(data).unwrap();
-        debug!("成功返回文件: {}", file_path);
-        return;
-    }
-
-    // 如果找不到文件，且是根路径，返回index.html
-    if path.is_empty() || path == "/" {
-        if let Some(content) = Assets::get("index.html") {
-            res.add_header("content-type", "text/html; charset=utf-8", true)
-                .unwrap();
-            // 复制数据以避免生命周期问题
-            let data = content.data.to_vec();
-            res.write_body(data).unwrap();
-            debug!("返回默认index.html");
-            return;
+    let stream_format = match web_tts_request.response_format.as_deref() {
+        None => AudioFormat::Wav,
+        Some(name) => match AudioFormat::from_name(name).filter(|f| f.supports_streaming()) {
+            Some(format) => format,
+            None => {
+                res.status_code(StatusCode::BAD_REQUEST);
+                res.render(Json(ErrorResponse {
+                    success: false,
+                    error: format!("不支持的流式输出格式: {}，支持: wav, pcm, ulaw, alaw", name),
+                }));
+                return Ok(());
+            }
+        },
+    };
+    let chunk_tokens = web_tts_request.chunk_tokens.unwrap_or(50);
+    let output_sample_rate = match validate_output_bitrate(stream_format, web_tts_request.bitrate)
+        .and_then(|_| resolve_output_sample_rate(web_tts_request.sample_rate, Some(stream_format)))
+    {
+        Ok(rate) => rate,
+        Err(e) => {
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render(Json(ErrorResponse {
+                success: false,
+                error: e,
+            }));
+            return Ok(());
         }
-    }
-
-    // 文件未找到，返回404
-    res.status_code(StatusCode::NOT_FOUND);
-    res.render(Text::Plain("File not found"));
-    debug!("文件未找到: {}", file_path);
-}
+    };
 
-/// 处理音色特征提取请求
-#[handler]
-async fn handle_voice_extract(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
-    // 检查是否是multipart请求（文件上传）
-    if !req
-        .content_type()
-        .map(|ct| ct.type_() == "multipart")
-        .unwrap_or(false)
+    let app_state = get_global_app_state();
+    let pipeline_args = match build_pipeline_args(
+        web_tts_request,
+        RequestPriority::Interactive,
+        &app_state,
+    )
+    .await
     {
-        let error_response = VoiceExtractResponse {
-            success: false,
-            message: "需要上传音频文件".to_string(),
-            voice_id: None,
-        };
-        res.render(Json(error_response));
-        return Ok(());
-    }
-
-    // multipart数据会自动解析，无需手动调用parse_form
-
-    // 提取参数
-    let voice_name: String = req.form("voice_name").await.unwrap_or_default();
-    let prompt_text: String = req.form("prompt_text").await.unwrap_or_default();
-    let _description: Option<String> = req.form("description").await;
+        Ok(args) => args,
+        Err(e) => {
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render(Json(ErrorResponse {
+                success: false,
+                error: e,
+            }));
+            return Ok(());
+        }
+    };
 
-    if voice_name.is_empty() {
-        let error_response = VoiceExtractResponse {
-            success: false,
-            message: "音色名称不能为空".to_string(),
-            voice_id: None,
-        };
-        res.render(Json(error_response));
+    // 响应头发出后无法再返回状态码，队列已满时提前拒绝
+    if reject_if_queue_full(req, res) {
         return Ok(());
     }
-
-    if prompt_text.is_empty() {
-        let error_response = VoiceExtractResponse {
-            success: false,
-            message: "提示词不能为空".to_string(),
-            voice_id: None,
+    let active_request =
+        match register_active_request(req, depot, res, &app_state, &pipeline_args.cancel) {
+            Ok(guard) => guard,
+            Err((status, e)) => {
+                render_error(res, status, e);
+                return Ok(());
+            }
         };
-        res.render(Json(error_response));
+    if let Err(e) = charge_text_quota(depot, &pipeline_args.text) {
+        render_rate_limit_error(req, res, &e);
         return Ok(());
     }
+    res.add_header("content-type", stream_format.content_type(), true)
+        .unwrap();
+    res.add_header("x-sample-rate", output_sample_rate.to_string(), true)
+        .unwrap();
+
+    let mut body = res.channel();
+    let (audio_tx, audio_rx) = flume::unbounded::<Vec<f32>>();
+    let tts_pipeline = app_state.tts_pipeline.clone();
+    let quota = quota_client(depot);
+    let cancel = pipeline_args.cancel.clone();
+    let producer = tokio::spawn(async move {
+        tts_pipeline
+            .generate_speech_stream(&pipeline_args, chunk_tokens, audio_tx)
+            .await
+    });
+
+    tokio::spawn(async move {
+        let _active_request = active_request;
+        // 客户端断开时任务提前返回，取消推理释放运行时；正常结束时推理已完成，取消无副作用
+        let _cancel_on_disconnect = cancel.cancel_on_drop();
+        let stream_start = std::time::Instant::now();
+        if stream_format == AudioFormat::Wav
+            && body
+                .send_data(streaming_wav_header(output_sample_rate))
+                .await
+                .is_err()
+        {
+            return;
+        }
 
-    // 获取上传的音频文件
-    let file = match req.file("audio_file").await {
-        Some(file) => file,
-        None => {
-            let error_response = VoiceExtractResponse {
-                success: false,
-                message: "未找到音频文件".to_string(),
-                voice_id: None,
-            };
-            res.render(Json(error_response));
-            return Ok(());
+        // 逐块重采样，跨块保留卷积上下文避免块边界失真
+        let mut resampler = Resampler::new(MODEL_SAMPLE_RATE, output_sample_rate);
+        let mut total_samples = 0usize;
+        while let Ok(chunk) = audio_rx.recv_async().await {
+            let chunk = resampler.process(&chunk);
+            if chunk.is_empty() {
+                continue;
+            }
+            if total_samples == 0 {
+                info!(
+                    "  ⏱️  首包音频延迟: {:.2}ms",
+                    stream_start.elapsed().as_secs_f64() * 1000.0
+                );
+            }
+            total_samples += chunk.len();
+            let data = encode_stream_chunk(stream_format, &chunk).unwrap_or_default();
+            if body.send_data(data).await.is_err() {
+                warn!("客户端已断开流式TTS连接");
+                return;
+            }
         }
-    };
 
-    // 保存上传的文件到临时目录，保持原始扩展名
-    let original_filename = file.name().unwrap_or("audio");
-    let extension = std::path::Path::new(original_filename)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("wav"); // 默认为wav
+        match producer.await {
+            Ok(Ok(())) => {
+                let tail = resampler.flush();
+                total_samples += tail.len();
+                if let Some((limiter, client)) = &quota {
+                    limiter.record_audio_seconds(
+                        client,
+                        total_samples as f64 / output_sample_rate as f64,
+                    );
+                }
+                let data = encode_stream_chunk(stream_format, &tail).unwrap_or_default();
+                if !tail.is_empty() && body.send_data(data).await.is_err() {
+                    warn!("客户端已断开流式TTS连接");
+                    return;
+                }
+                info!(
+                    "📊 流式TTS完成: 音频时长 {:.2}s, 总耗时 {:.2}ms",
+                    total_samples as f64 / output_sample_rate as f64,
+                    stream_start.elapsed().as_secs_f64() * 1000.0
+                );
+            }
+            Ok(Err(e)) => {
+                error!("流式TTS生成失败: {}", e);
+                body.send_error(std::io::Error::other(e.to_string()));
+            }
+            Err(e) => {
+                error!("流式TTS任务异常: {}", e);
+                body.send_error(std::io::Error::other(e.to_string()));
+            }
+        }
+    });
 
-    let temp_dir = PathBuf::from("assets/raf/temp/upload_temp_files");
-    let temp_file_path = temp_dir.join(format!("{}.{}", Uuid::new_v4(), extension));
+    Ok(())
+}
 
-    if let Err(e) = tokio::fs::copy(file.path(), &temp_file_path).await {
+/// SSE完成事件
+#[derive(Serialize)]
+struct SseCompleteEvent {
+    audio_base64: String,
+    format: String,
+    sample_rate: u32,
+    audio_duration_ms: u64,
+    duration_ms: u64,
+    rtf: f64,
+}
+
+/// 格式化一条SSE事件
+fn sse_event<T: Serialize>(event: &str, data: &T) -> String {
+    let data = serde_json::to_string(data).unwrap_or_else(|_| "{}".to_string());
+    format!("event: {}\ndata: {}\n\n", event, data)
+}
+
+/// 处理带进度的TTS请求：以Server-Sent Events推送各阶段进度，最后的complete事件携带Base64音频
+///
+/// 事件依次为queued、prefill_done、global_tokens_done、semantic_tokens（多次）、decoding，
+/// 最后为complete或error。
+#[handler]
+async fn handle_tts_sse(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Result<(), StatusError> {
+    let web_tts_request: WebTtsRequest = match req.parse_json().await {
+        Ok(request) => request,
+        Err(e) => {
+            error!("JSON解析失败: {}", e);
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render(Json(ErrorResponse {
+                success: false,
+                error: format!("JSON解析失败: {}", e),
+            }));
+            return Ok(());
+        }
+    };
+
+    // 音频随complete事件以Base64返回，json/未指定时使用WAV
+    let (format, output_sample_rate) =
+        match negotiate_tts_output(web_tts_request.response_format.as_deref(), None).and_then(
+            |format| {
+                let format = format.unwrap_or(AudioFormat::Wav);
+                validate_output_bitrate(format, web_tts_request.bitrate)?;
+                resolve_output_sample_rate(web_tts_request.sample_rate, Some(format))
+                    .map(|rate| (format, rate))
+            },
+        ) {
+            Ok(negotiated) => negotiated,
+            Err(e) => {
+                res.status_code(StatusCode::BAD_REQUEST);
+                res.render(Json(ErrorResponse {
+                    success: false,
+                    error: e,
+                }));
+                return Ok(());
+            }
+        };
+
+    info!(
+        "🎯 收到SSE TTS请求: text='{}', voice_id='{:?}'",
+        web_tts_request.text, web_tts_request.voice_id
+    );
+
+    let bitrate_kbps = web_tts_request.bitrate;
+    let app_state = get_global_app_state();
+    let pipeline_args = match build_pipeline_args(
+        web_tts_request,
+        RequestPriority::Interactive,
+        &app_state,
+    )
+    .await
+    {
+        Ok(args) => args,
+        Err(e) => {
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render(Json(ErrorResponse {
+                success: false,
+                error: e,
+            }));
+            return Ok(());
+        }
+    };
+
+    // 响应头发出后无法再返回状态码，队列已满时提前拒绝
+    if reject_if_queue_full(req, res) {
+        return Ok(());
+    }
+    let active_request =
+        match register_active_request(req, depot, res, &app_state, &pipeline_args.cancel) {
+            Ok(guard) => guard,
+            Err((status, e)) => {
+                render_error(res, status, e);
+                return Ok(());
+            }
+        };
+    if let Err(e) = charge_text_quota(depot, &pipeline_args.text) {
+        render_rate_limit_error(req, res, &e);
+        return Ok(());
+    }
+    res.add_header("content-type", "text/event-stream", true)
+        .unwrap();
+    res.add_header("cache-control", "no-cache", true).unwrap();
+    // 禁止反向代理缓冲，保证事件及时送达
+    res.add_header("x-accel-buffering", "no", true).unwrap();
+
+    let mut body = res.channel();
+    let (progress_tx, progress_rx) = flume::unbounded();
+    let tts_pipeline = app_state.tts_pipeline.clone();
+    let quota = quota_client(depot);
+    let total_start = std::time::Instant::now();
+    let cancel = pipeline_args.cancel.clone();
+    let producer = tokio::spawn(async move {
+        tts_pipeline
+            .generate_speech_with_progress(&pipeline_args, progress_tx)
+            .await
+    });
+
+    tokio::spawn(async move {
+        let _active_request = active_request;
+        // 客户端断开时任务提前返回，取消推理释放运行时
+        let _cancel_on_disconnect = cancel.cancel_on_drop();
+        while let Ok(progress) = progress_rx.recv_async().await {
+            if body
+                .send_data(sse_event(progress.name(), &progress))
+                .await
+                .is_err()
+            {
+                warn!("客户端已断开SSE连接");
+                return;
+            }
+        }
+
+        let result = match producer.await {
+            Ok(Ok(audio)) => {
+                let audio = resample(&audio, MODEL_SAMPLE_RATE, output_sample_rate);
+                encode_output_audio(format, &audio, output_sample_rate, bitrate_kbps)
+                    .map(|data| (audio, data))
+            }
+            Ok(Err(e)) => Err(format!("语音生成失败: {}", e)),
+            Err(e) => Err(format!("TTS任务异常: {}", e)),
+        };
+
+        let event = match result {
+            Ok((audio, data)) => {
+                let total_time = total_start.elapsed();
+                if let Some((limiter, client)) = &quota {
+                    limiter.record_audio_seconds(
+                        client,
+                        audio.len() as f64 / output_sample_rate as f64,
+                    );
+                }
+                sse_event(
+                    "complete",
+                    &SseCompleteEvent {
+                        audio_base64: base64::engine::general_purpose::STANDARD.encode(&data),
+                        format: format.extension().to_string(),
+                        sample_rate: output_sample_rate,
+                        audio_duration_ms: audio.len() as u64 * 1000 / output_sample_rate as u64,
+                        duration_ms: total_time.as_millis() as u64,
+                        rtf: calculate_rtf(&audio, output_sample_rate, total_time),
+                    },
+                )
+            }
+            Err(e) => {
+                error!("SSE TTS失败: {}", e);
+                sse_event(
+                    "error",
+                    &ErrorResponse {
+                        success: false,
+                        error: e,
+                    },
+                )
+            }
+        };
+        let _ = body.send_data(event).await;
+    });
+
+    Ok(())
+}
+
+/// WebSocket会话中单句的最大字符数，LLM长时间不输出句末标点时按此切分
+const WS_SENTENCE_MAX_CHARS: usize = 150;
+
+/// 空闲的WebSocket会话检查是否已被取消的间隔
+const WS_CANCEL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// WebSocket客户端消息
+///
+/// - `{"type":"start", ...}`：开始会话，其余字段同`/api/tts`（不含text），
+///   response_format可选pcm（默认）、ulaw、alaw；未指定voice_id时按start中的属性合成第一句，
+///   并以第一句的tokens作为后续句子的zero-shot参考，保证整个会话音色一致
+/// - `{"type":"text","text":"..."}`：追加文本片段，遇到句末标点时开始合成
+/// - `{"type":"flush"}`：立即合成缓冲区中剩余的文本
+/// - `{"type":"cancel"}`：丢弃缓冲区和尚未返回的句子
+/// - `{"type":"done"}`：输入结束，合成剩余文本后服务端发送done并关闭连接
+enum WsClientMessage {
+    Start(Box<WebTtsRequest>),
+    Text(String),
+    Flush,
+    Cancel,
+    Done,
+}
+
+impl WsClientMessage {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut value: serde_json::Value =
+            serde_json::from_str(text).map_err(|e| format!("JSON解析失败: {}", e))?;
+        let kind = value
+            .get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        match kind.as_str() {
+            "start" => {
+                if let Some(object) = value.as_object_mut() {
+                    object
+                        .entry("text")
+                        .or_insert_with(|| serde_json::Value::String(String::new()));
+                }
+                serde_json::from_value(value)
+                    .map(|request| Self::Start(Box::new(request)))
+                    .map_err(|e| format!("start消息格式错误: {}", e))
+            }
+            "text" => value
+                .get("text")
+                .and_then(|v| v.as_str())
+                .map(|text| Self::Text(text.to_string()))
+                .ok_or_else(|| "text消息缺少text字段".to_string()),
+            "flush" => Ok(Self::Flush),
+            "cancel" => Ok(Self::Cancel),
+            "done" => Ok(Self::Done),
+            other => Err(format!("未知的消息类型: {}", other)),
+        }
+    }
+}
+
+/// WebSocket服务端控制消息，每条audio消息之后紧跟一帧对应的二进制音频
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WsServerMessage {
+    Started {
+        format: String,
+        sample_rate: u32,
+    },
+    Audio {
+        index: usize,
+        text: String,
+        bytes: usize,
+        audio_duration_ms: u64,
+    },
+    Cancelled,
+    Done {
+        sentences: usize,
+        audio_duration_ms: u64,
+    },
+    Error {
+        index: Option<usize>,
+        error: String,
+    },
+}
+
+/// 单句合成结果
+enum WsSynthesisResult {
+    Audio {
+        index: usize,
+        text: String,
+        data: Vec<u8>,
+        samples: usize,
+    },
+    Failed {
+        index: usize,
+        error: String,
+    },
+}
+
+/// WebSocket双向流式TTS：客户端逐段发送文本，服务端按句合成并推送音频帧
+///
+/// 会话按`X-Request-Id`登记，可通过取消接口结束整个会话。
+#[handler]
+async fn handle_tts_websocket(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Result<(), StatusError> {
+    let app_state = get_global_app_state();
+    let cancel = CancelToken::new();
+    let active_request = match register_active_request(req, depot, res, &app_state, &cancel) {
+        Ok(guard) => guard,
+        Err((status, e)) => {
+            render_error(res, status, e);
+            return Ok(());
+        }
+    };
+    let quota = quota_client(depot);
+    WebSocketUpgrade::new()
+        .upgrade(req, res, move |ws| {
+            run_tts_websocket(ws, quota, cancel, active_request)
+        })
+        .await
+}
+
+async fn ws_send_json(ws: &mut WebSocket, message: &WsServerMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(text) => ws.send(Message::text(text)).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// 等待客户端的start消息，返回合成参数、输出格式和采样率
+async fn ws_wait_start(
+    ws: &mut WebSocket,
+) -> Option<(LightweightTtsPipelineArgs, AudioFormat, u32)> {
+    loop {
+        let message = match ws.recv().await {
+            Some(Ok(message)) => message,
+            _ => return None,
+        };
+        if message.is_close() {
+            return None;
+        }
+        if !message.is_text() {
+            continue;
+        }
+
+        let text = std::str::from_utf8(message.as_bytes()).unwrap_or_default();
+        let request = match WsClientMessage::parse(text) {
+            Ok(WsClientMessage::Start(request)) => *request,
+            Ok(_) => Err("请先发送start消息".to_string()),
+            Err(e) => Err(e),
+        }
+        .and_then(|request| {
+            let format = match request.response_format.as_deref() {
+                None => AudioFormat::Pcm,
+                Some(name) => AudioFormat::from_name(name)
+                    .filter(|f| {
+                        matches!(f, AudioFormat::Pcm | AudioFormat::Mulaw | AudioFormat::Alaw)
+                    })
+                    .ok_or_else(|| {
+                        format!("不支持的WebSocket输出格式: {}，支持: pcm, ulaw, alaw", name)
+                    })?,
+            };
+            let sample_rate = resolve_output_sample_rate(request.sample_rate, Some(format))?;
+            Ok((request, format, sample_rate))
+        });
+
+        let (request, format, sample_rate) = match request {
+            Ok(start) => start,
+            Err(error) => {
+                ws_send_json(ws, &WsServerMessage::Error { index: None, error }).await;
+                continue;
+            }
+        };
+
+        let app_state = get_global_app_state();
+        match build_pipeline_args(request, RequestPriority::Interactive, &app_state).await {
+            Ok(args) => return Some((args, format, sample_rate)),
+            Err(error) => {
+                ws_send_json(ws, &WsServerMessage::Error { index: None, error }).await;
+            }
+        }
+    }
+}
+
+/// 启动单句合成工作任务，按顺序合成队列中的句子
+///
+/// 会话未指定音色时，第一句生成的tokens写回`args`作为后续句子的zero-shot参考；
+/// `args`在cancel后重建的工作任务间共享，已确定的音色不会丢失
+fn spawn_ws_synthesis_worker(
+    args: Arc<std::sync::Mutex<LightweightTtsPipelineArgs>>,
+    format: AudioFormat,
+    sample_rate: u32,
+    sentence_rx: flume::Receiver<(usize, String)>,
+    result_tx: flume::Sender<(u64, WsSynthesisResult)>,
+    generation: u64,
+) -> tokio::task::JoinHandle<()> {
+    let tts_pipeline = get_global_app_state().tts_pipeline;
+    tokio::spawn(async move {
+        while let Ok((index, text)) = sentence_rx.recv_async().await {
+            let mut sentence_args = args.lock().unwrap().clone();
+            sentence_args.text = text.clone();
+            let inherit_voice = sentence_args.voice_global_tokens.is_none()
+                || sentence_args.voice_semantic_tokens.is_none();
+
+            let result = match tts_pipeline
+                .generate_speech_with_tokens(&sentence_args)
+                .await
+            {
+                Ok((audio, global_tokens, semantic_tokens)) => {
+                    if inherit_voice && !semantic_tokens.is_empty() {
+                        let mut args = args.lock().unwrap();
+                        args.zero_shot = true;
+                        args.prompt_text = text.clone();
+                        args.voice_global_tokens = Some(global_tokens);
+                        args.voice_semantic_tokens = Some(semantic_tokens);
+                    }
+                    // 整句合成完毕，按句归一化，响度与/api/tts一致
+                    let samples =
+                        normalize_samples(&resample(&audio, MODEL_SAMPLE_RATE, sample_rate));
+                    match encode_stream_chunk(format, &samples) {
+                        Ok(data) => WsSynthesisResult::Audio {
+                            index,
+                            text,
+                            data,
+                            samples: samples.len(),
+                        },
+                        Err(e) => WsSynthesisResult::Failed {
+                            index,
+                            error: format!("音频编码失败: {}", e),
+                        },
+                    }
+                }
+                Err(e) => WsSynthesisResult::Failed {
+                    index,
+                    error: format!("语音生成失败: {}", e),
+                },
+            };
+
+            if result_tx.send((generation, result)).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// WebSocket会话主循环，`_active_request`在会话结束时注销
+async fn run_tts_websocket(
+    mut ws: WebSocket,
+    quota: Option<(Arc<RateLimiter>, RateLimitClient)>,
+    cancel: CancelToken,
+    _active_request: ActiveRequestGuard,
+) {
+    let Some((mut args, format, sample_rate)) = ws_wait_start(&mut ws).await else {
+        return;
+    };
+    // 每句的推理令牌都是会话令牌的子令牌，取消会话即终止正在合成的句子
+    args.cancel = cancel.clone();
+    info!(
+        "🔌 WebSocket TTS会话开始: voice_id='{:?}', 输出: {} {}Hz",
+        args.voice_id,
+        format.content_type(),
+        sample_rate
+    );
+    if !ws_send_json(
+        &mut ws,
+        &WsServerMessage::Started {
+            format: format.extension().to_string(),
+            sample_rate,
+        },
+    )
+    .await
+    {
+        return;
+    }
+
+    let args = Arc::new(std::sync::Mutex::new(args));
+    let (sentence_tx, sentence_rx) = flume::unbounded::<(usize, String)>();
+    let (result_tx, result_rx) = flume::unbounded::<(u64, WsSynthesisResult)>();
+    // 每次cancel后递增，丢弃旧工作任务残留的结果
+    let mut generation = 0u64;
+    let mut worker = spawn_ws_synthesis_worker(
+        args.clone(),
+        format,
+        sample_rate,
+        sentence_rx.clone(),
+        result_tx.clone(),
+        generation,
+    );
+
+    let mut buffer = SentenceBuffer::new(WS_SENTENCE_MAX_CHARS);
+    let mut next_index = 0usize;
+    let mut pending = 0usize;
+    let mut total_samples = 0usize;
+    let mut closing = false;
+
+    loop {
+        if cancel.is_cancelled() {
+            info!("🔌 WebSocket TTS会话已被取消");
+            ws_send_json(&mut ws, &WsServerMessage::Cancelled).await;
+            let _ = ws.send(Message::close()).await;
+            break;
+        }
+        if closing && pending == 0 {
+            ws_send_json(
+                &mut ws,
+                &WsServerMessage::Done {
+                    sentences: next_index,
+                    audio_duration_ms: total_samples as u64 * 1000 / sample_rate as u64,
+                },
+            )
+            .await;
+            let _ = ws.send(Message::close()).await;
+            break;
+        }
+
+        tokio::select! {
+            message = ws.recv() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    _ => break,
+                };
+                if message.is_close() {
+                    break;
+                }
+                if !message.is_text() {
+                    continue;
+                }
+
+                let text = std::str::from_utf8(message.as_bytes()).unwrap_or_default();
+                let mut sentences = Vec::new();
+                match WsClientMessage::parse(text) {
+                    Ok(WsClientMessage::Text(_) | WsClientMessage::Flush) if closing => {
+                        ws_send_json(&mut ws, &WsServerMessage::Error {
+                            index: None,
+                            error: "会话已结束输入".to_string(),
+                        })
+                        .await;
+                    }
+                    Ok(WsClientMessage::Text(fragment)) => sentences = buffer.push(&fragment),
+                    Ok(WsClientMessage::Flush) => sentences.extend(buffer.flush()),
+                    Ok(WsClientMessage::Done) => {
+                        sentences.extend(buffer.flush());
+                        closing = true;
+                    }
+                    Ok(WsClientMessage::Cancel) => {
+                        buffer.clear();
+                        sentence_rx.drain();
+                        worker.abort();
+                        generation += 1;
+                        pending = 0;
+                        worker = spawn_ws_synthesis_worker(
+                            args.clone(),
+                            format,
+                            sample_rate,
+                            sentence_rx.clone(),
+                            result_tx.clone(),
+                            generation,
+                        );
+                        if !ws_send_json(&mut ws, &WsServerMessage::Cancelled).await {
+                            break;
+                        }
+                    }
+                    Ok(WsClientMessage::Start(_)) => {
+                        ws_send_json(&mut ws, &WsServerMessage::Error {
+                            index: None,
+                            error: "会话已开始，音色与参数在会话内不可更改".to_string(),
+                        })
+                        .await;
+                    }
+                    Err(error) => {
+                        ws_send_json(&mut ws, &WsServerMessage::Error { index: None, error }).await;
+                    }
+                }
+
+                for sentence in sentences {
+                    // 队列已满时跳过该句，不扣除配额
+                    if let Err(full) = check_queue_capacity() {
+                        let sent = ws_send_json(&mut ws, &WsServerMessage::Error {
+                            index: Some(next_index),
+                            error: full.to_string(),
+                        })
+                        .await;
+                        next_index += 1;
+                        if !sent {
+                            break;
+                        }
+                        continue;
+                    }
+                    if let Some((limiter, client)) = &quota {
+                        if let Err(e) = limiter.charge_characters(client, sentence.chars().count() as u64) {
+                            ws_send_json(&mut ws, &WsServerMessage::Error {
+                                index: None,
+                                error: e.to_string(),
+                            })
+                            .await;
+                            closing = true;
+                            break;
+                        }
+                    }
+                    if sentence_tx.send((next_index, sentence)).is_err() {
+                        break;
+                    }
+                    next_index += 1;
+                    pending += 1;
+                }
+            }
+            result = result_rx.recv_async() => {
+                let Ok((result_generation, result)) = result else {
+                    break;
+                };
+                if result_generation != generation {
+                    continue;
+                }
+                pending = pending.saturating_sub(1);
+
+                let sent = match result {
+                    WsSynthesisResult::Audio { index, text, data, samples } => {
+                        total_samples += samples;
+                        if let Some((limiter, client)) = &quota {
+                            limiter.record_audio_seconds(client, samples as f64 / sample_rate as f64);
+                        }
+                        ws_send_json(&mut ws, &WsServerMessage::Audio {
+                            index,
+                            text,
+                            bytes: data.len(),
+                            audio_duration_ms: samples as u64 * 1000 / sample_rate as u64,
+                        })
+                        .await
+                            && ws.send(Message::binary(data)).await.is_ok()
+                    }
+                    WsSynthesisResult::Failed { index, error } => {
+                        error!("WebSocket句子 {} 合成失败: {}", index, error);
+                        ws_send_json(&mut ws, &WsServerMessage::Error { index: Some(index), error }).await
+                    }
+                };
+                if !sent {
+                    break;
+                }
+            }
+            // 空闲时定期检查会话是否已通过取消接口终止
+            _ = tokio::time::sleep(WS_CANCEL_POLL_INTERVAL) => {}
+        }
+    }
+
+    worker.abort();
+    info!(
+        "🔌 WebSocket TTS会话结束: {} 句, 音频时长 {:.2}s",
+        next_index,
+        total_samples as f64 / sample_rate as f64
+    );
+}
+
+/// 根据音色ID或音色名称（不区分大小写）查找已保存的音色特征
+async fn resolve_voice_feature(
+    voice_manager: &VoiceFeatureManager,
+    voice: &str,
+) -> Option<rwkv_tts_rs::voice_feature_manager::VoiceFeature> {
+    if let Ok(voice_feature) = voice_manager.load_voice_feature(voice).await {
+        return Some(voice_feature);
+    }
+
+    let voices = voice_manager.list_voices().await.ok()?;
+    let metadata = voices.iter().find(|v| v.name.eq_ignore_ascii_case(voice))?;
+    voice_manager.load_voice_feature(&metadata.id).await.ok()
+}
+
+/// 处理OpenAI兼容的语音合成请求，直接返回二进制音频
+#[handler]
+async fn handle_oai_speech(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Result<(), StatusError> {
+    let total_start = std::time::Instant::now();
+
+    let speech_request: OaiSpeechRequest = match req.parse_json().await {
+        Ok(request) => request,
+        Err(e) => {
+            error!("JSON解析失败: {}", e);
+            render_oai_error(
+                res,
+                StatusCode::BAD_REQUEST,
+                format!("JSON解析失败: {}", e),
+                None,
+            );
+            return Ok(());
+        }
+    };
+
+    if speech_request.input.trim().is_empty() {
+        render_oai_error(
+            res,
+            StatusCode::BAD_REQUEST,
+            "input不能为空".to_string(),
+            Some("input"),
+        );
+        return Ok(());
+    }
+
+    let response_format = speech_request.response_format.as_deref().unwrap_or("mp3");
+    let Some(audio_format) = AudioFormat::from_name(response_format) else {
+        render_oai_error(
+            res,
+            StatusCode::BAD_REQUEST,
+            format!(
+                "不支持的输出格式: {}，支持: mp3, opus, flac, wav, pcm",
+                response_format
+            ),
+            Some("response_format"),
+        );
+        return Ok(());
+    };
+
+    let speed_ratio = speech_request.speed.unwrap_or(1.0);
+    if !(0.25..=4.0).contains(&speed_ratio) {
+        render_oai_error(
+            res,
+            StatusCode::BAD_REQUEST,
+            format!("speed必须在0.25到4.0之间，当前为: {}", speed_ratio),
+            Some("speed"),
+        );
+        return Ok(());
+    }
+
+    let output_sample_rate =
+        match resolve_output_sample_rate(speech_request.sample_rate, Some(audio_format)) {
+            Ok(rate) => rate,
+            Err(e) => {
+                render_oai_error(res, StatusCode::BAD_REQUEST, e, Some("sample_rate"));
+                return Ok(());
+            }
+        };
+
+    info!(
+        "🎯 收到OpenAI兼容TTS请求: input='{}', voice='{:?}', format={}, speed={}",
+        speech_request.input, speech_request.voice, response_format, speed_ratio
+    );
+
+    let app_state = get_global_app_state();
+
+    // 音色可以是音色ID或名称；OpenAI内置音色（如alloy）等未知名称回退到默认属性合成
+    let voice_feature = match speech_request.voice.as_deref() {
+        Some(voice) if !voice.is_empty() => {
+            let found = resolve_voice_feature(&app_state.voice_manager, voice).await;
+            match &found {
+                Some(vf) => info!("🎭 使用音色: {} ({})", vf.name, vf.id),
+                None => warn!("未找到音色 '{}'，使用默认音色合成", voice),
+            }
+            found
+        }
+        _ => None,
+    };
+
+    // 音色克隆（zero-shot）不使用属性token，语速由参考音频决定，无法按speed调整
+    let speed = rwkv_tts_rs::properties_util::classify_speed_ratio(speed_ratio);
+    if voice_feature.is_some() && speed != rwkv_tts_rs::properties_util::classify_speed_ratio(1.0) {
+        render_oai_error(
+            res,
+            StatusCode::BAD_REQUEST,
+            format!("使用已保存的音色时不支持调整speed，当前为: {}", speed_ratio),
+            Some("speed"),
+        );
+        return Ok(());
+    }
+
+    let pipeline_args = LightweightTtsPipelineArgs {
+        text: speech_request.input.clone(),
+        zero_shot: voice_feature.is_some(),
+        top_k: 100,
+        speed: speed.to_string(),
+        prompt_text: voice_feature
+            .as_ref()
+            .map(|vf| vf.prompt_text.clone())
+            .unwrap_or_default(),
+        voice_global_tokens: voice_feature.as_ref().map(|vf| vf.global_tokens.clone()),
+        voice_semantic_tokens: voice_feature.as_ref().map(|vf| vf.semantic_tokens.clone()),
+        ..Default::default()
+    };
+    let _active_request =
+        match register_active_request(req, depot, res, &app_state, &pipeline_args.cancel) {
+            Ok(guard) => guard,
+            Err((status, e)) => {
+                render_oai_error(res, status, e, None);
+                return Ok(());
+            }
+        };
+    if let Err(e) = charge_text_quota(depot, &pipeline_args.text) {
+        render_rate_limit_error(req, res, &e);
+        return Ok(());
+    }
+
+    let audio_data = match app_state.tts_pipeline.generate_speech(&pipeline_args).await {
+        Ok(data) => data,
+        Err(e) => {
+            if let Some(full) = e.downcast_ref::<QueueFull>() {
+                render_queue_full(req, res, full);
+                return Ok(());
+            }
+            error!("生成TTS音频失败: {}", e);
+            render_oai_error(
+                res,
+                synthesis_error_status(&e),
+                format!("生成TTS音频失败: {}", e),
+                None,
+            );
+            return Ok(());
+        }
+    };
+
+    record_audio_quota(depot, audio_data.len(), MODEL_SAMPLE_RATE);
+
+    let audio_data = resample(&audio_data, MODEL_SAMPLE_RATE, output_sample_rate);
+    let audio_bytes = match encode_output_audio(audio_format, &audio_data, output_sample_rate, None)
+    {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("{}", e);
+            render_oai_error(res, StatusCode::INTERNAL_SERVER_ERROR, e, None);
+            return Ok(());
+        }
+    };
+
+    let total_time = total_start.elapsed();
+    info!(
+        "📊 OpenAI兼容TTS完成: 音频时长 {:.2}s, 总耗时 {:.2}ms, RTF {:.3}, 输出 {} bytes",
+        audio_data.len() as f64 / output_sample_rate as f64,
+        total_time.as_secs_f64() * 1000.0,
+        calculate_rtf(&audio_data, output_sample_rate, total_time),
+        audio_bytes.len()
+    );
+
+    res.add_header("content-type", audio_format.content_type(), true)
+        .unwrap();
+    res.add_header("x-sample-rate", output_sample_rate.to_string(), true)
+        .unwrap();
+    res.write_body(audio_bytes).ok();
+    Ok(())
+}
+
+/// 存活检查：进程能响应即返回ok
+#[handler]
+async fn handle_health(_req: &mut Request, res: &mut Response) {
+    res.render(Json(HealthResponse { status: "ok" }));
+}
+
+/// 检查服务是否就绪：模型与ONNX会话池已加载、预热合成成功且未进入停机流程
+fn check_readiness(app_state: &AppState) -> ReadyResponse {
+    let batch_manager = rwkv_tts_rs::dynamic_batch_manager::get_global_dynamic_batch_manager();
+    let model_loaded = batch_manager.is_ok();
+    let shutting_down = batch_manager
+        .map(|manager| manager.is_shutting_down())
+        .unwrap_or(false);
+    let onnx_pools_ready = rwkv_tts_rs::onnx_session_pool::get_global_onnx_manager().is_ok();
+    let warmup = app_state.warmup_status.read().unwrap().clone();
+    let ready = model_loaded
+        && !shutting_down
+        && onnx_pools_ready
+        && matches!(warmup, WarmupStatus::Completed { .. });
+
+    ReadyResponse {
+        ready,
+        shutting_down,
+        model_loaded,
+        onnx_pools_ready,
+        warmup,
+    }
+}
+
+/// 就绪检查：模型、分词器、ONNX会话池加载完成且预热合成成功后才返回200
+#[handler]
+async fn handle_ready(_req: &mut Request, res: &mut Response) {
+    let readiness = check_readiness(&get_global_app_state());
+    if !readiness.ready {
+        res.status_code(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    res.render(Json(readiness));
+}
+
+/// 服务状态：运行时长、模型与量化配置、批处理配置、ONNX会话池和音色缓存统计
+#[handler]
+async fn handle_status(_req: &mut Request, res: &mut Response) {
+    let app_state = get_global_app_state();
+    let manager = rwkv_tts_rs::dynamic_batch_manager::get_global_dynamic_batch_manager().ok();
+    let batch_config = manager.as_ref().map(|manager| manager.config().clone());
+    let queue = manager.as_ref().map(|manager| QueueStatus {
+        depth: manager.queue_depth(),
+        max_depth: manager.max_queue_depth(),
+        in_flight: manager.in_flight(),
+        active_slots: manager.active_slots(),
+        slots: manager.num_slots(),
+        estimated_wait_ms: manager.estimated_wait().as_millis() as u64,
+        rejected: manager.rejected_requests(),
+    });
+    let onnx_pools = rwkv_tts_rs::onnx_session_pool::get_global_onnx_manager()
+        .ok()
+        .map(|manager| {
+            let (bicodec_tokenize, wav2vec2, bicodec_detokenize) = manager.get_pool_stats();
+            OnnxPoolStatus {
+                bicodec_tokenize,
+                wav2vec2,
+                bicodec_detokenize,
+            }
+        });
+    let warmup = app_state.warmup_status.read().unwrap().clone();
+
+    res.render(Json(StatusResponse {
+        success: true,
+        version: env!("CARGO_PKG_VERSION"),
+        uptime_secs: app_state.start_time.elapsed().as_secs(),
+        ready: check_readiness(&app_state).ready,
+        model_path: app_state.model_path.clone(),
+        vocab_path: app_state.vocab_path.clone(),
+        quantization: QuantStatus {
+            layers: app_state.quant_layers,
+            quant_type: app_state.quant_type.clone(),
+        },
+        batch_config,
+        queue,
+        onnx_pools,
+        voice_cache: app_state.voice_manager.get_cache_stats(),
+        voice_cache_hit_rate: app_state.voice_manager.get_cache_hit_rate(),
+        warmup,
+    }));
+}
+
+/// Prometheus指标导出
+#[handler]
+async fn handle_metrics(_req: &mut Request, res: &mut Response) {
+    let app_state = get_global_app_state();
+    let cache_stats = app_state.voice_manager.get_cache_stats();
+    let body = global_metrics().render(Some(&cache_stats));
+
+    res.add_header(
+        "content-type",
+        "text/plain; version=0.0.4; charset=utf-8",
+        true,
+    )
+    .unwrap();
+    res.write_body(body).ok();
+}
+
+/// 预热失败后首次重试前的等待时间，之后每次翻倍
+const WARMUP_RETRY_INITIAL_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// 预热重试的最长等待时间
+const WARMUP_RETRY_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// 执行短文本预热合成，失败时按指数退避重试，成功后服务才报告就绪
+async fn run_warmup_synthesis(app_state: AppState) {
+    let args = LightweightTtsPipelineArgs {
+        text: "你好".to_string(),
+        seed: Some(0),
+        ..Default::default()
+    };
+    let mut delay = WARMUP_RETRY_INITIAL_DELAY;
+
+    for attempts in 1u32.. {
+        info!("开始预热合成（第{}次）...", attempts);
+        let warmup_start = std::time::Instant::now();
+        let error = match app_state.tts_pipeline.generate_speech(&args).await {
+            Ok(audio) if !audio.is_empty() => {
+                let elapsed_ms = warmup_start.elapsed().as_millis() as u64;
+                info!("✅ 预热合成完成，耗时 {}ms，服务已就绪", elapsed_ms);
+                *app_state.warmup_status.write().unwrap() = WarmupStatus::Completed { elapsed_ms };
+                return;
+            }
+            Ok(_) => "预热合成未生成音频".to_string(),
+            Err(e) => e.to_string(),
+        };
+        error!(
+            "预热合成失败（第{}次），{}秒后重试: {}",
+            attempts,
+            delay.as_secs(),
+            error
+        );
+        *app_state.warmup_status.write().unwrap() = WarmupStatus::Failed { error, attempts };
+
+        tokio::time::sleep(delay).await;
+        let shutting_down = rwkv_tts_rs::dynamic_batch_manager::get_global_dynamic_batch_manager()
+            .map(|manager| manager.is_shutting_down())
+            .unwrap_or(false);
+        if shutting_down {
+            return;
+        }
+        delay = (delay * 2).min(WARMUP_RETRY_MAX_DELAY);
+    }
+}
+
+/// 提供Web UI界面
+#[handler]
+async fn handle_web_ui(_req: &mut Request, res: &mut Response) {
+    match Assets::get("index.html") {
+        Some(content) => {
+            let html = std::str::from_utf8(content.data.as_ref())
+                .unwrap_or("<h1>Error reading embedded HTML</h1>");
+            res.render(Text::Html(html.to_string()));
+        }
+        None => {
+            res.render(Text::Html("<h1>Web UI not found</h1>".to_string()));
+        }
+    }
+}
+
+/// 处理嵌入的静态文件
+#[handler]
+async fn handle_static_files(req: &mut Request, res: &mut Response) {
+    let path = req.param::<String>("path").unwrap_or_default();
+
+    // 处理根路径请求，返回index.html
+    let file_path = if path.is_empty() || path == "/" {
+        "index.html".to_string()
+    } else {
+        // 移除开头的斜杠（如果有的话）
+        path.trim_start_matches('/').to_string()
+    };
+
+    // 调试：打印请求信息
+    debug!(
+        "请求路径: {}, 参数path: {}, 文件路径: {}",
+        req.uri().path(),
+        path,
+        file_path
+    );
+
+    // 首先尝试直接查找文件
+    if let Some(content) = Assets::get(&file_path) {
+        debug!("找到文件: {}", file_path);
+        // 根据文件扩展名设置Content-Type
+        let content_type = match file_path.split('.').next_back() {
+            Some("html") => "text/html; charset=utf-8",
+            Some("css") => "text/css",
+            Some("js") => "application/javascript",
+            Some("json") => "application/json",
+            Some("png") => "image/png",
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("svg") => "image/svg+xml",
+            Some("ico") => "image/x-icon",
+            _ => "application/octet-stream",
+        };
+        res.add_header("content-type", content_type, true).unwrap();
+        // 复制数据以避免生命周期问题
+        let data = content.data.to_vec();
+        res.write_body(data).unwrap();
+        debug!("成功返回文件: {}", file_path);
+        return;
+    }
+
+    // 如果找不到文件，且是根路径，返回index.html
+    if path.is_empty() || path == "/" {
+        if let Some(content) = Assets::get("index.html") {
+            res.add_header("content-type", "text/html; charset=utf-8", true)
+                .unwrap();
+            // 复制数据以避免生命周期问题
+            let data = content.data.to_vec();
+            res.write_body(data).unwrap();
+            debug!("返回默认index.html");
+            return;
+        }
+    }
+
+    // 文件未找到，返回404
+    res.status_code(StatusCode::NOT_FOUND);
+    res.render(Text::Plain("File not found"));
+    debug!("文件未找到: {}", file_path);
+}
+
+/// 处理音色特征提取请求
+#[handler]
+async fn handle_voice_extract(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
+    // 检查是否是multipart请求（文件上传）
+    if !req
+        .content_type()
+        .map(|ct| ct.type_() == "multipart")
+        .unwrap_or(false)
+    {
+        let error_response = VoiceExtractResponse {
+            success: false,
+            message: "需要上传音频文件".to_string(),
+            voice_id: None,
+        };
+        res.render(Json(error_response));
+        return Ok(());
+    }
+
+    // multipart数据会自动解析，无需手动调用parse_form
+
+    // 提取参数
+    let voice_name: String = req.form("voice_name").await.unwrap_or_default();
+    let prompt_text: String = req.form("prompt_text").await.unwrap_or_default();
+    let description: String = req.form("description").await.unwrap_or_default();
+
+    if voice_name.is_empty() {
+        let error_response = VoiceExtractResponse {
+            success: false,
+            message: "音色名称不能为空".to_string(),
+            voice_id: None,
+        };
+        res.render(Json(error_response));
+        return Ok(());
+    }
+
+    if prompt_text.is_empty() {
+        let error_response = VoiceExtractResponse {
+            success: false,
+            message: "提示词不能为空".to_string(),
+            voice_id: None,
+        };
+        res.render(Json(error_response));
+        return Ok(());
+    }
+
+    // 获取上传的音频文件
+    let file = match req.file("audio_file").await {
+        Some(file) => file,
+        None => {
+            let error_response = VoiceExtractResponse {
+                success: false,
+                message: "未找到音频文件".to_string(),
+                voice_id: None,
+            };
+            res.render(Json(error_response));
+            return Ok(());
+        }
+    };
+
+    // 保存上传的文件到临时目录，保持原始扩展名
+    let original_filename = file.name().unwrap_or("audio");
+    let extension = std::path::Path::new(original_filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("wav"); // 默认为wav
+
+    // 获取应用状态
+    let app_state = get_global_app_state();
+
+    let temp_dir = app_state.voice_manager.upload_temp_dir();
+    let temp_file_path = temp_dir.join(format!("{}.{}", Uuid::new_v4(), extension));
+
+    if let Err(e) = tokio::fs::copy(file.path(), &temp_file_path).await {
         error!("保存临时文件失败: {}", e);
         let error_response = VoiceExtractResponse {
             success: false,
@@ -852,157 +2769,1076 @@ async fn handle_voice_extract(req: &mut Request, res: &mut Response) -> Result<(
         return Ok(());
     }
 
-    // 获取应用状态
-    let app_state = get_global_app_state();
+    // 实际的音频特征提取逻辑
+    let (global_tokens, semantic_tokens, audio_duration, sample_rate) =
+        match extract_audio_features(temp_file_path.to_str().unwrap()).await {
+            Ok(features) => features,
+            Err(e) => {
+                error!("音频特征提取失败: {}", e);
+                // 清理临时文件
+                let _ = fs::remove_file(&temp_file_path).await;
+
+                let error_response = VoiceExtractResponse {
+                    success: false,
+                    message: format!("音频特征提取失败: {}", e),
+                    voice_id: None,
+                };
+                res.render(Json(error_response));
+                return Ok(());
+            }
+        };
+
+    // 使用音色特征管理器提取并保存音色特征
+    match app_state
+        .voice_manager
+        .save_voice_feature(
+            voice_name,
+            prompt_text,
+            description,
+            global_tokens,
+            semantic_tokens,
+            audio_duration,
+            sample_rate,
+        )
+        .await
+    {
+        Ok(voice_id) => {
+            // 保留原始参考音频，导出音色包时可一并带上
+            if let Err(e) = app_state
+                .voice_manager
+                .save_reference_audio(&voice_id, &temp_file_path)
+                .await
+            {
+                warn!("保存音色 {} 的参考音频失败: {}", voice_id, e);
+            }
+
+            // 清理临时文件
+            let _ = fs::remove_file(&temp_file_path).await;
+
+            let response = VoiceExtractResponse {
+                success: true,
+                message: "音色特征提取成功".to_string(),
+                voice_id: Some(voice_id),
+            };
+            res.render(Json(response));
+        }
+        Err(e) => {
+            error!("音色特征提取失败: {}", e);
+            // 清理临时文件
+            let _ = fs::remove_file(&temp_file_path).await;
+
+            let error_response = VoiceExtractResponse {
+                success: false,
+                message: format!("音色特征提取失败: {}", e),
+                voice_id: None,
+            };
+            res.render(Json(error_response));
+        }
+    }
+
+    Ok(())
+}
+
+/// 处理音色列表请求
+#[handler]
+async fn handle_voice_list(_req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
+    let app_state = get_global_app_state();
+
+    match app_state.voice_manager.list_voices().await {
+        Ok(voices) => {
+            let response = VoiceListResponse {
+                success: true,
+                voices,
+            };
+            res.render(Json(response));
+        }
+        Err(e) => {
+            error!("获取音色列表失败: {}", e);
+            let error_response = VoiceListResponse {
+                success: false,
+                voices: vec![],
+            };
+            res.render(Json(error_response));
+        }
+    }
+
+    Ok(())
+}
+
+/// 处理音色删除请求
+#[handler]
+async fn handle_voice_delete(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
+    let delete_request: VoiceDeleteRequest = match req.parse_json().await {
+        Ok(req) => req,
+        Err(e) => {
+            error!("解析删除请求失败: {}", e);
+            let error_response = VoiceDeleteResponse {
+                success: false,
+                message: "请求格式错误".to_string(),
+            };
+            res.render(Json(error_response));
+            return Ok(());
+        }
+    };
+
+    let app_state = get_global_app_state();
+
+    match app_state
+        .voice_manager
+        .delete_voice(&delete_request.voice_id)
+        .await
+    {
+        Ok(()) => {
+            let response = VoiceDeleteResponse {
+                success: true,
+                message: "音色删除成功".to_string(),
+            };
+            res.render(Json(response));
+        }
+        Err(e) => {
+            error!("删除音色失败: {}", e);
+            let error_response = VoiceDeleteResponse {
+                success: false,
+                message: format!("删除音色失败: {}", e),
+            };
+            res.render(Json(error_response));
+        }
+    }
+
+    Ok(())
+}
 
-    // 实际的音频特征提取逻辑
-    let (global_tokens, semantic_tokens, audio_duration, sample_rate) =
-        match extract_audio_features(temp_file_path.to_str().unwrap()).await {
-            Ok(features) => features,
-            Err(e) => {
-                error!("音频特征提取失败: {}", e);
-                // 清理临时文件
-                let _ = fs::remove_file(&temp_file_path).await;
+/// 异步任务提交请求
+#[derive(Deserialize)]
+struct JobSubmitRequest {
+    #[serde(flatten)]
+    tts: WebTtsRequest,
+    /// 任务结束后接收签名通知的地址
+    callback_url: Option<String>,
+}
 
-                let error_response = VoiceExtractResponse {
-                    success: false,
-                    message: format!("音频特征提取失败: {}", e),
-                    voice_id: None,
-                };
-                res.render(Json(error_response));
+/// 异步任务提交响应
+#[derive(Serialize)]
+struct JobSubmitResponse {
+    success: bool,
+    job_id: String,
+    status: JobStatus,
+    status_url: String,
+    download_url: String,
+}
+
+/// 任务状态（不含合成参数）
+#[derive(Serialize)]
+struct JobInfo {
+    id: String,
+    status: JobStatus,
+    progress: f64,
+    segments_total: usize,
+    segments_done: usize,
+    text_chars: usize,
+    format: String,
+    sample_rate: u32,
+    created_at: chrono::DateTime<chrono::Utc>,
+    started_at: Option<chrono::DateTime<chrono::Utc>>,
+    finished_at: Option<chrono::DateTime<chrono::Utc>>,
+    audio_duration_secs: Option<f64>,
+    rtf: Option<f64>,
+    error: Option<String>,
+    download_url: Option<String>,
+    callback_delivered: Option<bool>,
+}
+
+impl From<&TtsJob> for JobInfo {
+    fn from(job: &TtsJob) -> Self {
+        Self {
+            id: job.id.clone(),
+            status: job.status,
+            progress: job.progress(),
+            segments_total: job.segments_total,
+            segments_done: job.segments_done,
+            text_chars: job.text_chars,
+            format: job.output.format.clone(),
+            sample_rate: job.output.sample_rate,
+            created_at: job.created_at,
+            started_at: job.started_at,
+            finished_at: job.finished_at,
+            audio_duration_secs: job.audio_duration_secs,
+            rtf: job.rtf,
+            error: job.error.clone(),
+            download_url: (job.status == JobStatus::Completed)
+                .then(|| format!("/api/jobs/{}/audio", job.id)),
+            callback_delivered: job.callback_delivered,
+        }
+    }
+}
+
+/// 单个任务响应
+#[derive(Serialize)]
+struct JobResponse {
+    success: bool,
+    job: JobInfo,
+}
+
+/// 任务列表响应
+#[derive(Serialize)]
+struct JobListResponse {
+    success: bool,
+    jobs: Vec<JobInfo>,
+}
+
+fn render_error(res: &mut Response, status: StatusCode, error: String) {
+    res.status_code(status);
+    res.render(Json(ErrorResponse {
+        success: false,
+        error,
+    }));
+}
+
+/// 取消请求响应
+#[derive(Debug, Serialize)]
+struct TtsCancelResponse {
+    success: bool,
+    request_id: String,
+}
+
+/// 取消进行中的同步合成请求（ID来自`X-Request-Id`），推理在下一个解码步骤中止
+///
+/// 非admin密钥只能取消自己提交的请求。
+#[handler]
+async fn handle_tts_cancel(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let request_id = req.param::<String>("id").unwrap_or_default();
+    let owner = access_owner(depot);
+
+    if !get_global_app_state()
+        .active_requests
+        .cancel(&request_id, owner.as_deref())
+    {
+        render_error(
+            res,
+            StatusCode::NOT_FOUND,
+            format!("请求不存在或已结束: {}", request_id),
+        );
+        return;
+    }
+    info!("🛑 合成请求 {} 已取消", request_id);
+    res.render(Json(TtsCancelResponse {
+        success: true,
+        request_id,
+    }));
+}
+
+/// 提交异步合成任务，立即返回任务ID（适合超出单次请求时长的长文本）
+#[handler]
+async fn handle_job_submit(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Result<(), StatusError> {
+    let JobSubmitRequest {
+        tts: web_tts_request,
+        callback_url,
+    } = match req.parse_json().await {
+        Ok(request) => request,
+        Err(e) => {
+            render_error(res, StatusCode::BAD_REQUEST, format!("JSON解析失败: {}", e));
+            return Ok(());
+        }
+    };
+
+    // 任务输出为音频文件，默认WAV
+    let format_name = web_tts_request
+        .response_format
+        .clone()
+        .unwrap_or_else(|| "wav".to_string())
+        .to_ascii_lowercase();
+    let output = match AudioFormat::from_name(&format_name)
+        .ok_or_else(|| {
+            format!(
+                "不支持的response_format: {}，可选: {}",
+                format_name,
+                AudioFormat::supported_names()
+            )
+        })
+        .and_then(|format| {
+            validate_output_bitrate(format, web_tts_request.bitrate)?;
+            resolve_output_sample_rate(web_tts_request.sample_rate, Some(format))
+        }) {
+        Ok(sample_rate) => JobOutput {
+            format: format_name,
+            sample_rate,
+            bitrate_kbps: web_tts_request.bitrate,
+        },
+        Err(e) => {
+            render_error(res, StatusCode::BAD_REQUEST, e);
+            return Ok(());
+        }
+    };
+
+    let app_state = get_global_app_state();
+    let pipeline_args =
+        match build_pipeline_args(web_tts_request, RequestPriority::Bulk, &app_state).await {
+            Ok(args) => args,
+            Err(e) => {
+                render_error(res, StatusCode::BAD_REQUEST, e);
                 return Ok(());
             }
         };
 
-    // 使用音色特征管理器提取并保存音色特征
+    if let Err(e) = app_state
+        .job_manager
+        .validate(&pipeline_args, &output, callback_url.as_deref())
+        .await
+    {
+        render_error(res, StatusCode::BAD_REQUEST, e.to_string());
+        return Ok(());
+    }
+    // 参数校验通过、即将入队时才扣除配额
+    if let Err(e) = charge_text_quota(depot, &pipeline_args.text) {
+        render_rate_limit_error(req, res, &e);
+        return Ok(());
+    }
+
     match app_state
-        .voice_manager
-        .save_voice_feature(
-            voice_name,
-            prompt_text,
-            global_tokens,
-            semantic_tokens,
-            audio_duration,
-            sample_rate,
+        .job_manager
+        .submit(
+            pipeline_args,
+            output,
+            callback_url,
+            depot
+                .obtain::<ApiKeyIdentity>()
+                .ok()
+                .map(|i| i.name.clone()),
         )
         .await
     {
-        Ok(voice_id) => {
-            // 清理临时文件
-            let _ = fs::remove_file(&temp_file_path).await;
+        Ok(job) => {
+            res.status_code(StatusCode::ACCEPTED);
+            res.render(Json(JobSubmitResponse {
+                success: true,
+                status: job.status,
+                status_url: format!("/api/jobs/{}", job.id),
+                download_url: format!("/api/jobs/{}/audio", job.id),
+                job_id: job.id,
+            }));
+        }
+        Err(e) => render_error(res, StatusCode::BAD_REQUEST, e.to_string()),
+    }
 
-            let response = VoiceExtractResponse {
+    Ok(())
+}
+
+/// 列出异步任务（非admin密钥只能看到自己提交的任务）
+#[handler]
+async fn handle_job_list(depot: &mut Depot, res: &mut Response) {
+    let app_state = get_global_app_state();
+    let owner = access_owner(depot);
+    let jobs = app_state.job_manager.list();
+    res.render(Json(JobListResponse {
+        success: true,
+        jobs: jobs
+            .iter()
+            .filter(|job| job.is_visible_to(owner.as_deref()))
+            .map(JobInfo::from)
+            .collect(),
+    }));
+}
+
+/// 获取当前密钥可访问的任务，其他密钥的任务视为不存在
+fn find_visible_job(depot: &Depot, job_id: &str) -> Option<TtsJob> {
+    let owner = access_owner(depot);
+    get_global_app_state()
+        .job_manager
+        .get(job_id)
+        .filter(|job| job.is_visible_to(owner.as_deref()))
+}
+
+/// 查询任务状态与进度
+#[handler]
+async fn handle_job_status(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let job_id = req.param::<String>("id").unwrap_or_default();
+    match find_visible_job(depot, &job_id) {
+        Some(job) => res.render(Json(JobResponse {
+            success: true,
+            job: JobInfo::from(&job),
+        })),
+        None => render_error(
+            res,
+            StatusCode::NOT_FOUND,
+            format!("任务不存在: {}", job_id),
+        ),
+    }
+}
+
+/// 取消任务
+#[handler]
+async fn handle_job_cancel(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let job_id = req.param::<String>("id").unwrap_or_default();
+    let job_manager = get_global_app_state().job_manager;
+    if find_visible_job(depot, &job_id).is_none() {
+        render_error(
+            res,
+            StatusCode::NOT_FOUND,
+            format!("任务不存在: {}", job_id),
+        );
+        return;
+    }
+
+    match job_manager.cancel(&job_id).await {
+        Ok(job) => res.render(Json(JobResponse {
+            success: true,
+            job: JobInfo::from(&job),
+        })),
+        Err(e) => render_error(res, StatusCode::CONFLICT, e.to_string()),
+    }
+}
+
+/// 下载已完成任务的音频
+#[handler]
+async fn handle_job_audio(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let job_id = req.param::<String>("id").unwrap_or_default();
+    let job_manager = get_global_app_state().job_manager;
+    let Some(job) = find_visible_job(depot, &job_id) else {
+        render_error(
+            res,
+            StatusCode::NOT_FOUND,
+            format!("任务不存在: {}", job_id),
+        );
+        return;
+    };
+
+    let output_path = match job_manager.output_path(&job) {
+        Some(path) if job.status == JobStatus::Completed => path,
+        _ => {
+            render_error(
+                res,
+                StatusCode::CONFLICT,
+                format!("任务尚未完成，当前状态: {:?}", job.status),
+            );
+            return;
+        }
+    };
+
+    match fs::read(&output_path).await {
+        Ok(data) => {
+            let content_type = job
+                .output
+                .audio_format()
+                .map_or("application/octet-stream", |f| f.content_type());
+            let file_name = output_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("output");
+            res.add_header("content-type", content_type, true).unwrap();
+            res.add_header(
+                "content-disposition",
+                format!("attachment; filename=\"{}_{}\"", job.id, file_name),
+                true,
+            )
+            .unwrap();
+            res.add_header("x-sample-rate", job.output.sample_rate.to_string(), true)
+                .unwrap();
+            res.write_body(data).ok();
+        }
+        Err(e) => {
+            error!("读取任务 {} 输出失败: {}", job_id, e);
+            render_error(
+                res,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("读取任务输出失败: {}", e),
+            );
+        }
+    }
+}
+
+/// 音色详情（不含tokens本身）
+#[derive(Serialize)]
+struct VoiceDetail {
+    id: String,
+    name: String,
+    prompt_text: String,
+    description: String,
+    tags: Vec<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    audio_duration: f32,
+    sample_rate: u32,
+    global_token_count: usize,
+    semantic_token_count: usize,
+    checksum: String,
+}
+
+impl From<rwkv_tts_rs::voice_feature_manager::VoiceFeature> for VoiceDetail {
+    fn from(voice: rwkv_tts_rs::voice_feature_manager::VoiceFeature) -> Self {
+        Self {
+            global_token_count: voice.global_tokens.len(),
+            semantic_token_count: voice.semantic_tokens.len(),
+            id: voice.id,
+            name: voice.name,
+            prompt_text: voice.prompt_text,
+            description: voice.description,
+            tags: voice.tags,
+            created_at: voice.created_at,
+            audio_duration: voice.audio_duration,
+            sample_rate: voice.sample_rate,
+            checksum: voice.checksum,
+        }
+    }
+}
+
+/// 音色详情响应
+#[derive(Serialize)]
+struct VoiceDetailResponse {
+    success: bool,
+    voice: VoiceDetail,
+}
+
+/// 音色修改请求，未提供的字段保持不变
+#[derive(Deserialize)]
+struct VoiceUpdateRequest {
+    name: Option<String>,
+    prompt_text: Option<String>,
+    description: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+/// 音色是否存在（只查元数据，不加载特征文件）
+async fn voice_exists(voice_manager: &VoiceFeatureManager, voice_id: &str) -> bool {
+    voice_manager
+        .list_voices()
+        .await
+        .map(|voices| voices.iter().any(|v| v.id == voice_id))
+        .unwrap_or(false)
+}
+
+/// 获取音色详情
+#[handler]
+async fn handle_voice_get(req: &mut Request, res: &mut Response) {
+    let voice_id = req.param::<String>("id").unwrap_or_default();
+    let app_state = get_global_app_state();
+
+    if !voice_exists(&app_state.voice_manager, &voice_id).await {
+        render_error(
+            res,
+            StatusCode::NOT_FOUND,
+            format!("音色不存在: {}", voice_id),
+        );
+        return;
+    }
+
+    match app_state.voice_manager.load_voice_feature(&voice_id).await {
+        Ok(voice) => res.render(Json(VoiceDetailResponse {
+            success: true,
+            voice: voice.into(),
+        })),
+        Err(e) => {
+            error!("加载音色 {} 失败: {}", voice_id, e);
+            render_error(
+                res,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("加载音色失败: {}", e),
+            );
+        }
+    }
+}
+
+/// 修改音色名称、提示词、描述和标签
+#[handler]
+async fn handle_voice_update(req: &mut Request, res: &mut Response) {
+    let voice_id = req.param::<String>("id").unwrap_or_default();
+    let update_request: VoiceUpdateRequest = match req.parse_json().await {
+        Ok(request) => request,
+        Err(e) => {
+            render_error(res, StatusCode::BAD_REQUEST, format!("JSON解析失败: {}", e));
+            return;
+        }
+    };
+
+    let app_state = get_global_app_state();
+    if !voice_exists(&app_state.voice_manager, &voice_id).await {
+        render_error(
+            res,
+            StatusCode::NOT_FOUND,
+            format!("音色不存在: {}", voice_id),
+        );
+        return;
+    }
+
+    let update = VoiceUpdate {
+        name: update_request.name,
+        prompt_text: update_request.prompt_text,
+        description: update_request.description,
+        tags: update_request.tags,
+    };
+    match app_state
+        .voice_manager
+        .update_voice(&voice_id, update)
+        .await
+    {
+        Ok(voice) => {
+            info!("✏️  音色 {} 已更新", voice_id);
+            res.render(Json(VoiceDetailResponse {
                 success: true,
-                message: "音色特征提取成功".to_string(),
-                voice_id: Some(voice_id),
-            };
-            res.render(Json(response));
+                voice: voice.into(),
+            }));
         }
+        Err(e) => render_error(res, StatusCode::BAD_REQUEST, format!("修改音色失败: {}", e)),
+    }
+}
+
+/// 删除音色（RESTful）
+#[handler]
+async fn handle_voice_delete_by_id(req: &mut Request, res: &mut Response) {
+    let voice_id = req.param::<String>("id").unwrap_or_default();
+    let app_state = get_global_app_state();
+
+    if !voice_exists(&app_state.voice_manager, &voice_id).await {
+        render_error(
+            res,
+            StatusCode::NOT_FOUND,
+            format!("音色不存在: {}", voice_id),
+        );
+        return;
+    }
+
+    match app_state.voice_manager.delete_voice(&voice_id).await {
+        Ok(()) => res.render(Json(VoiceDeleteResponse {
+            success: true,
+            message: "音色删除成功".to_string(),
+        })),
         Err(e) => {
-            error!("音色特征提取失败: {}", e);
-            // 清理临时文件
-            let _ = fs::remove_file(&temp_file_path).await;
+            error!("删除音色失败: {}", e);
+            render_error(
+                res,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("删除音色失败: {}", e),
+            );
+        }
+    }
+}
 
-            let error_response = VoiceExtractResponse {
-                success: false,
-                message: format!("音色特征提取失败: {}", e),
-                voice_id: None,
-            };
-            res.render(Json(error_response));
+/// 音色包上传大小上限（含Base64编码的参考音频）
+const VOICE_PACKAGE_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// 音色导入响应
+#[derive(Serialize)]
+struct VoiceImportResponse {
+    success: bool,
+    #[serde(flatten)]
+    result: VoiceImportResult,
+}
+
+/// 导出音色包（`?include_audio=true`时附带原始参考音频）
+#[handler]
+async fn handle_voice_export(req: &mut Request, res: &mut Response) {
+    let voice_id = req.param::<String>("id").unwrap_or_default();
+    let include_audio = req.query::<bool>("include_audio").unwrap_or(false);
+    let app_state = get_global_app_state();
+
+    if !voice_exists(&app_state.voice_manager, &voice_id).await {
+        render_error(
+            res,
+            StatusCode::NOT_FOUND,
+            format!("音色不存在: {}", voice_id),
+        );
+        return;
+    }
+
+    let package = match app_state
+        .voice_manager
+        .export_voice(&voice_id, include_audio)
+        .await
+    {
+        Ok(package) => package,
+        Err(e) => {
+            error!("导出音色 {} 失败: {}", voice_id, e);
+            render_error(
+                res,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("导出音色失败: {}", e),
+            );
+            return;
+        }
+    };
+
+    match serde_json::to_vec_pretty(&package) {
+        Ok(data) => {
+            info!(
+                "📦 导出音色 {}（参考音频: {}）",
+                voice_id,
+                package.reference_audio.is_some()
+            );
+            res.add_header("content-type", "application/json", true)
+                .unwrap();
+            res.add_header(
+                "content-disposition",
+                format!("attachment; filename=\"{}.voice.json\"", voice_id),
+                true,
+            )
+            .unwrap();
+            res.write_body(data).ok();
+        }
+        Err(e) => render_error(
+            res,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("序列化音色包失败: {}", e),
+        ),
+    }
+}
+
+/// 导入音色包，ID与已有音色冲突时分配新ID
+#[handler]
+async fn handle_voice_import(req: &mut Request, res: &mut Response) {
+    let package: VoicePackage = match req.payload_with_max_size(VOICE_PACKAGE_MAX_BYTES).await {
+        Ok(body) => match serde_json::from_slice(body) {
+            Ok(package) => package,
+            Err(e) => {
+                render_error(
+                    res,
+                    StatusCode::BAD_REQUEST,
+                    format!("音色包解析失败: {}", e),
+                );
+                return;
+            }
+        },
+        Err(e) => {
+            render_error(
+                res,
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "读取音色包失败（上限{}MB）: {}",
+                    VOICE_PACKAGE_MAX_BYTES / 1024 / 1024,
+                    e
+                ),
+            );
+            return;
+        }
+    };
+
+    let app_state = get_global_app_state();
+    match app_state.voice_manager.import_voice(package).await {
+        Ok(result) => {
+            if result.already_exists {
+                info!("📦 音色 {} 已存在，跳过导入", result.voice_id);
+            } else {
+                info!(
+                    "📦 导入音色 {}（原ID: {}）",
+                    result.voice_id, result.original_id
+                );
+                res.status_code(StatusCode::CREATED);
+            }
+            res.render(Json(VoiceImportResponse {
+                success: true,
+                result,
+            }));
         }
+        Err(e) => render_error(res, StatusCode::BAD_REQUEST, format!("导入音色失败: {}", e)),
     }
+}
 
-    Ok(())
+/// CORS中间件
+///
+/// 允许列表包含"*"时对任意来源返回`*`；否则只回显列表中的Origin，其余来源不返回CORS头。
+struct Cors {
+    allow_any: bool,
+    origins: Vec<String>,
 }
 
-/// 处理音色列表请求
-#[handler]
-async fn handle_voice_list(_req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
-    let app_state = get_global_app_state();
+impl Cors {
+    fn new(origins: &[String]) -> Self {
+        Self {
+            allow_any: origins.iter().any(|origin| origin == "*"),
+            origins: origins.to_vec(),
+        }
+    }
+}
 
-    match app_state.voice_manager.list_voices().await {
-        Ok(voices) => {
-            let response = VoiceListResponse {
-                success: true,
-                voices,
-            };
-            res.render(Json(response));
+#[async_trait]
+impl Handler for Cors {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        if self.allow_any {
+            res.headers_mut()
+                .insert("Access-Control-Allow-Origin", "*".parse().unwrap());
+        } else {
+            let origin = req
+                .headers()
+                .get("origin")
+                .filter(|origin| {
+                    origin
+                        .to_str()
+                        .is_ok_and(|origin| self.origins.iter().any(|o| o == origin))
+                })
+                .cloned();
+            if let Some(origin) = origin {
+                res.headers_mut()
+                    .insert("Access-Control-Allow-Origin", origin);
+            }
+            res.headers_mut().append("Vary", "Origin".parse().unwrap());
         }
-        Err(e) => {
-            error!("获取音色列表失败: {}", e);
-            let error_response = VoiceListResponse {
-                success: false,
-                voices: vec![],
-            };
-            res.render(Json(error_response));
+        res.headers_mut().insert(
+            "Access-Control-Allow-Methods",
+            "GET, POST, PATCH, DELETE, OPTIONS".parse().unwrap(),
+        );
+        res.headers_mut().insert(
+            "Access-Control-Allow-Headers",
+            "Content-Type, Authorization, X-Request-Id".parse().unwrap(),
+        );
+        res.headers_mut().insert(
+            "Access-Control-Expose-Headers",
+            "X-Sample-Rate, X-Duration-Ms, X-Audio-Duration-Ms, X-Rtf, X-Voice-Id, X-Request-Id, Retry-After, X-Queue-Depth"
+                .parse()
+                .unwrap(),
+        );
+        ctrl.call_next(req, depot, res).await;
+    }
+}
+
+/// 中间件：API密钥认证
+///
+/// 令牌从`Authorization: Bearer <key>`头读取，也接受`api_key`查询参数（便于浏览器直接打开页面）。
+/// 未配置密钥文件时不做任何校验；认证通过后把调用方身份注入Depot供后续处理使用。
+struct ApiKeyAuth {
+    store: Option<Arc<ApiKeyStore>>,
+    scope: ApiScope,
+    /// 是否为静态Web UI路由（受allow_anonymous_ui控制）
+    static_ui: bool,
+}
+
+impl ApiKeyAuth {
+    fn new(store: Option<Arc<ApiKeyStore>>, scope: ApiScope) -> Self {
+        Self {
+            store,
+            scope,
+            static_ui: false,
         }
     }
 
-    Ok(())
+    fn static_ui(store: Option<Arc<ApiKeyStore>>) -> Self {
+        Self {
+            store,
+            scope: ApiScope::Read,
+            static_ui: true,
+        }
+    }
 }
 
-/// 处理音色删除请求
-#[handler]
-async fn handle_voice_delete(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
-    let delete_request: VoiceDeleteRequest = match req.parse_json().await {
-        Ok(req) => req,
-        Err(e) => {
-            error!("解析删除请求失败: {}", e);
-            let error_response = VoiceDeleteResponse {
-                success: false,
-                message: "请求格式错误".to_string(),
-            };
-            res.render(Json(error_response));
-            return Ok(());
+#[async_trait]
+impl Handler for ApiKeyAuth {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        if req.method() == salvo::http::Method::OPTIONS
+            || (self.static_ui && store.allow_anonymous_ui())
+        {
+            return;
         }
-    };
 
-    let app_state = get_global_app_state();
+        let token = req
+            .headers()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_bearer_token)
+            .map(|t| t.to_string())
+            .or_else(|| req.query::<String>("api_key"));
+
+        let Some(token) = token else {
+            render_auth_error(
+                req,
+                res,
+                StatusCode::UNAUTHORIZED,
+                "缺少API密钥，请使用 Authorization: Bearer <key>".to_string(),
+            );
+            ctrl.skip_rest();
+            return;
+        };
 
-    match app_state
-        .voice_manager
-        .delete_voice(&delete_request.voice_id)
-        .await
-    {
-        Ok(()) => {
-            let response = VoiceDeleteResponse {
-                success: true,
-                message: "音色删除成功".to_string(),
-            };
-            res.render(Json(response));
+        match store.authenticate(&token) {
+            Some(identity) if identity.scope.allows(self.scope) => {
+                depot.inject(identity.clone());
+            }
+            Some(identity) => {
+                warn!(
+                    "API密钥 {} 权限不足: 需要 {}，实际 {}",
+                    identity.name,
+                    self.scope.as_str(),
+                    identity.scope.as_str()
+                );
+                render_auth_error(
+                    req,
+                    res,
+                    StatusCode::FORBIDDEN,
+                    format!("该操作需要 {} 权限", self.scope.as_str()),
+                );
+                ctrl.skip_rest();
+            }
+            None => {
+                render_auth_error(
+                    req,
+                    res,
+                    StatusCode::UNAUTHORIZED,
+                    "API密钥无效".to_string(),
+                );
+                ctrl.skip_rest();
+            }
         }
-        Err(e) => {
-            error!("删除音色失败: {}", e);
-            let error_response = VoiceDeleteResponse {
-                success: false,
-                message: format!("删除音色失败: {}", e),
-            };
-            res.render(Json(error_response));
+    }
+}
+
+/// 返回认证错误
+fn render_auth_error(req: &Request, res: &mut Response, status: StatusCode, message: String) {
+    if status == StatusCode::UNAUTHORIZED {
+        res.add_header("www-authenticate", "Bearer", true).ok();
+    }
+    render_access_error(
+        req,
+        res,
+        status,
+        message,
+        "invalid_request_error",
+        "invalid_api_key",
+    );
+}
+
+/// 返回429限流错误并设置Retry-After
+fn render_rate_limit_error(req: &Request, res: &mut Response, exceeded: &RateLimitExceeded) {
+    res.add_header("retry-after", exceeded.retry_after_secs.to_string(), true)
+        .ok();
+    render_access_error(
+        req,
+        res,
+        StatusCode::TOO_MANY_REQUESTS,
+        exceeded.to_string(),
+        "rate_limit_error",
+        "rate_limit_exceeded",
+    );
+}
+
+/// 请求队列已满，返回503并通过Retry-After告知预计等待时间
+fn render_queue_full(req: &Request, res: &mut Response, full: &QueueFull) {
+    res.add_header("retry-after", full.retry_after_secs().to_string(), true)
+        .ok();
+    res.add_header("x-queue-depth", full.queue_depth.to_string(), true)
+        .ok();
+    render_access_error(
+        req,
+        res,
+        StatusCode::SERVICE_UNAVAILABLE,
+        full.to_string(),
+        "server_error",
+        "queue_full",
+    );
+}
+
+/// 检查请求队列容量，批处理管理器尚未初始化时视为有空位
+fn check_queue_capacity() -> Result<(), QueueFull> {
+    match rwkv_tts_rs::dynamic_batch_manager::get_global_dynamic_batch_manager() {
+        Ok(manager) => manager.check_capacity(),
+        Err(_) => Ok(()),
+    }
+}
+
+/// 检查请求队列容量，已满时返回503并返回true
+fn reject_if_queue_full(req: &Request, res: &mut Response) -> bool {
+    match check_queue_capacity() {
+        Ok(()) => false,
+        Err(full) => {
+            render_queue_full(req, res, &full);
+            true
         }
     }
+}
 
-    Ok(())
+/// 返回访问控制类错误，OpenAI兼容路由使用OpenAI风格的错误体
+fn render_access_error(
+    req: &Request,
+    res: &mut Response,
+    status: StatusCode,
+    message: String,
+    oai_type: &str,
+    oai_code: &str,
+) {
+    let path = req.uri().path();
+    res.status_code(status);
+    if path.starts_with("/v1/") || path.starts_with("/api/oai/") {
+        res.render(Json(OaiErrorResponse {
+            error: OaiErrorDetail {
+                message,
+                error_type: oai_type.to_string(),
+                param: None,
+                code: Some(oai_code.to_string()),
+            },
+        }));
+    } else {
+        res.render(Json(ErrorResponse {
+            success: false,
+            error: message,
+        }));
+    }
 }
 
-/// CORS中间件
+/// 中间件：限流
+///
+/// 已认证的请求按密钥名称限流，否则按客户端IP；通过后将限流对象注入Depot，
+/// 供处理函数扣除字符配额和累计音频时长。
 #[handler]
-async fn cors_handler(
+async fn rate_limit_handler(
     req: &mut Request,
     depot: &mut Depot,
     res: &mut Response,
     ctrl: &mut FlowCtrl,
 ) {
-    res.headers_mut()
-        .insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-    res.headers_mut().insert(
-        "Access-Control-Allow-Methods",
-        "GET, POST, OPTIONS".parse().unwrap(),
-    );
-    res.headers_mut().insert(
-        "Access-Control-Allow-Headers",
-        "Content-Type, Authorization".parse().unwrap(),
-    );
-    ctrl.call_next(req, depot, res).await;
+    let Some(limiter) = get_global_app_state().rate_limiter else {
+        return;
+    };
+
+    let client = match depot.obtain::<ApiKeyIdentity>() {
+        Ok(identity) => RateLimitClient::ApiKey(identity.name.clone()),
+        Err(_) => RateLimitClient::Ip(client_ip(req, limiter.trust_forwarded_for())),
+    };
+
+    if let Err(e) = limiter.check_request(&client) {
+        warn!("{} 触发限流: {}", client, e);
+        render_rate_limit_error(req, res, &e);
+        ctrl.skip_rest();
+        return;
+    }
+    depot.inject(client);
+}
+
+/// 获取客户端IP，可选信任X-Forwarded-For中的第一个地址
+fn client_ip(req: &Request, trust_forwarded_for: bool) -> String {
+    if trust_forwarded_for {
+        let forwarded = req
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+
+    let addr = req.remote_addr();
+    if let Some(addr) = addr.as_ipv4() {
+        addr.ip().to_string()
+    } else if let Some(addr) = addr.as_ipv6() {
+        addr.ip().to_string()
+    } else {
+        "unknown".to_string()
+    }
 }
 
 /// 中间件：请求日志
@@ -1022,9 +3858,59 @@ async fn request_logger(
     let duration = start.elapsed();
     let status = res.status_code.unwrap_or(StatusCode::OK);
 
+    global_metrics().record_request(method.as_str(), metrics_route_label(&path), status.as_u16());
     info!("{} {} {} - {:?}", method, path, status.as_u16(), duration);
 }
 
+/// 指标中使用的路由模式，需与`main`中注册的API路由保持一致（`{id}`匹配任意单段路径）
+const METRICS_ROUTES: &[&str] = &[
+    "/api/health",
+    "/api/ready",
+    "/api/status",
+    "/metrics",
+    "/api/tts",
+    "/api/tts/stream",
+    "/api/tts/sse",
+    "/api/tts/ws",
+    "/api/tts/requests/{id}/cancel",
+    "/api/jobs",
+    "/api/jobs/{id}",
+    "/api/jobs/{id}/cancel",
+    "/api/jobs/{id}/audio",
+    "/v1/audio/speech",
+    "/api/oai/audio/speech",
+    "/api/oai/v1/audio/speech",
+    "/api/voice-clone/list",
+    "/api/voice-clone/extract",
+    "/api/voice-clone/delete",
+    "/api/voice-clone/import",
+    "/api/voice-clone/{id}",
+    "/api/voice-clone/{id}/export",
+];
+
+/// 将请求路径归一为指标路由标签，避免ID、静态文件和未匹配路径导致标签基数膨胀
+///
+/// 已注册的API路由使用路由模式，其余API路径统一记为`unmatched`，非API路径记为`static`
+fn metrics_route_label(path: &str) -> &'static str {
+    if !(path.starts_with("/api/") || path.starts_with("/v1/") || path == "/metrics") {
+        return "static";
+    }
+
+    let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    METRICS_ROUTES
+        .iter()
+        .find(|route| {
+            let pattern: Vec<&str> = route.split('/').collect();
+            pattern.len() == segments.len()
+                && pattern
+                    .iter()
+                    .zip(&segments)
+                    .all(|(p, s)| (*p == "{id}" && !s.is_empty()) || p == s)
+        })
+        .copied()
+        .unwrap_or("unmatched")
+}
+
 /// 解析量化类型字符串
 fn parse_quant_type(s: &str) -> Result<Quant> {
     let quant_type = match s.to_lowercase().as_str() {
@@ -1070,6 +3956,99 @@ fn create_quant_config(quant_layers: usize, quant_type: Quant) -> Option<HashMap
     Some(config)
 }
 
+/// 读取配置文件（未指定时使用默认配置），再用命令行参数覆盖并校验
+fn load_server_config(matches: &ArgMatches) -> Result<ServerConfig> {
+    let mut config = match matches.get_one::<String>("config") {
+        Some(path) => ServerConfig::load(path)?,
+        None => ServerConfig::default(),
+    };
+
+    if let Some(host) = parse_arg(matches, "host")? {
+        config.listen.host = host;
+    }
+    if let Some(port) = parse_arg(matches, "port")? {
+        config.listen.port = port;
+    }
+    if let Some(grace) = parse_arg(matches, "shutdown-grace")? {
+        config.listen.shutdown_grace_secs = grace;
+    }
+    if let Some(path) = matches.get_one::<String>("model-path") {
+        config.model.model_path = path.clone();
+    }
+    if let Some(path) = matches.get_one::<String>("vocab-path") {
+        config.model.vocab_path = path.clone();
+    }
+    if let Some(layers) = parse_arg(matches, "quant-layers")? {
+        config.model.quant_layers = layers;
+    }
+    if let Some(quant_type) = matches.get_one::<String>("quant-type") {
+        config.model.quant_type = quant_type.clone();
+    }
+    if let Some(pool_size) = parse_arg(matches, "onnx-pool-size")? {
+        config.onnx.pool_size = pool_size;
+    }
+    if let Some(batch_size) = parse_arg(matches, "batch-size")? {
+        config.batch.max_batch_size = batch_size;
+    }
+    if let Some(timeout) = parse_arg(matches, "batch-timeout")? {
+        config.batch.collect_timeout_ms = timeout;
+    }
+    if let Some(timeout) = parse_arg(matches, "inference-timeout")? {
+        config.batch.inference_timeout_ms = timeout;
+    }
+    if let Some(depth) = parse_arg(matches, "max-queue-depth")? {
+        config.batch.max_queue_depth = depth;
+    }
+    if let Some(chunk_size) = parse_arg(matches, "token-chunk-size")? {
+        config.batch.token_chunk_size = chunk_size;
+    }
+    if let Some(dir) = matches.get_one::<String>("raf-dir") {
+        config.storage.raf_dir = dir.clone();
+    }
+    if let Some(dir) = matches.get_one::<String>("jobs-dir") {
+        config.storage.jobs_dir = dir.clone();
+    }
+    if let Some(workers) = parse_arg(matches, "job-workers")? {
+        config.jobs.workers = workers;
+    }
+    if let Some(secret) = matches.get_one::<String>("webhook-secret") {
+        config.jobs.webhook_secret = Some(secret.clone());
+    }
+    if let Some(url) = matches.get_one::<String>("public-url") {
+        config.jobs.public_url = Some(url.clone());
+    }
+    if let Some(level) = matches.get_one::<String>("log-level") {
+        config.log.level = level.clone();
+    }
+    if let Some(path) = matches.get_one::<String>("auth-config") {
+        config.auth = Some(AuthConfig::load(path)?);
+    }
+    if let Some(path) = matches.get_one::<String>("rate-limit-config") {
+        config.rate_limit = Some(RateLimitConfig::load(path)?);
+    }
+
+    config
+        .validate()
+        .map_err(|e| anyhow::anyhow!("配置无效: {}", e))?;
+    Ok(config)
+}
+
+/// 解析命令行参数，未指定时返回None
+fn parse_arg<T>(matches: &ArgMatches, name: &str) -> Result<Option<T>>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    matches
+        .get_one::<String>(name)
+        .map(|value| {
+            value
+                .parse::<T>()
+                .map_err(|e| anyhow::anyhow!("无效的参数 --{} {}: {}", name, value, e))
+        })
+        .transpose()
+}
+
 /// 从Hugging Face下载模型文件
 async fn download_models_from_hf() -> Result<()> {
     info!("开始从Hugging Face下载模型文件...");
@@ -1199,99 +4178,200 @@ async fn download_models_from_hf() -> Result<()> {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // 解析命令行参数
+    let start_time = std::time::Instant::now();
+
+    // 解析命令行参数（未指定的项使用配置文件或默认值）
     let matches = Command::new("RWKV TTS Server")
         .version(env!("CARGO_PKG_VERSION"))
         .about("基于RWKV的高性能TTS服务器")
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .help("服务器配置文件（TOML），命令行参数优先于配置文件"),
+        )
         .arg(
             Arg::new("quant-layers")
                 .long("quant-layers")
                 .value_name("NUMBER")
-                .help("指定量化层数")
-                .default_value("0"),
+                .help("指定量化层数（默认0）"),
         )
         .arg(
             Arg::new("quant-type")
                 .long("quant-type")
                 .value_name("TYPE")
-                .help("指定量化类型 (none, int8, nf4, sf4)。推荐使用 int8 以获得最佳稳定性")
-                .default_value("none"),
+                .help("指定量化类型 (none, int8, nf4, sf4)。推荐使用 int8 以获得最佳稳定性"),
         )
         .arg(
             Arg::new("model-path")
                 .long("model-path")
                 .value_name("PATH")
-                .help("模型文件路径")
-                .default_value("assets/model/webrwkv.safetensors"),
+                .help("模型文件路径（默认assets/model/webrwkv.safetensors）"),
         )
         .arg(
             Arg::new("vocab-path")
                 .long("vocab-path")
                 .value_name("PATH")
-                .help("词汇表文件路径")
-                .default_value("assets/model/tokenizer.json"),
+                .help("词汇表文件路径（默认assets/model/tokenizer.json）"),
         )
         .arg(
             Arg::new("batch-size")
                 .long("batch-size")
                 .value_name("NUMBER")
-                .help("批处理最大大小")
-                .default_value("10"),
+                .help("批处理最大大小（默认10）"),
         )
         .arg(
             Arg::new("batch-timeout")
                 .long("batch-timeout")
                 .value_name("MS")
-                .help("批处理超时时间（毫秒）")
-                .default_value("20"),
+                .help("批处理超时时间（毫秒，默认20）"),
         )
         .arg(
             Arg::new("inference-timeout")
                 .long("inference-timeout")
                 .value_name("MS")
-                .help("推理超时时间（毫秒）")
-                .default_value("120000"),
+                .help("推理超时时间（毫秒，默认120000）"),
+        )
+        .arg(
+            Arg::new("max-queue-depth")
+                .long("max-queue-depth")
+                .value_name("NUMBER")
+                .help("排队请求上限，队列满时返回503（默认256，0表示不限制）"),
+        )
+        .arg(
+            Arg::new("host")
+                .long("host")
+                .value_name("IP")
+                .help("服务器监听地址（默认0.0.0.0）"),
         )
         .arg(
             Arg::new("port")
                 .long("port")
                 .value_name("PORT")
-                .help("服务器监听端口")
-                .default_value("3000"),
+                .help("服务器监听端口（默认3000）"),
+        )
+        .arg(
+            Arg::new("shutdown-grace")
+                .long("shutdown-grace")
+                .value_name("SECS")
+                .help("优雅关闭宽限期（秒，默认30），超时后未完成的请求将被终止"),
         )
         .arg(
             Arg::new("token-chunk-size")
                 .long("token-chunk-size")
                 .value_name("SIZE")
-                .help("Prefill阶段每次送入的token块大小")
-                .default_value("256"),
+                .help("Prefill阶段每次送入的token块大小（默认256）"),
+        )
+        .arg(
+            Arg::new("onnx-pool-size")
+                .long("onnx-pool-size")
+                .value_name("SIZE")
+                .help("ONNX会话池大小（默认4）"),
+        )
+        .arg(
+            Arg::new("raf-dir")
+                .long("raf-dir")
+                .value_name("DIR")
+                .help("音色特征目录（默认assets/raf）"),
+        )
+        .arg(
+            Arg::new("log-level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .help("日志级别 (trace, debug, info, warn, error)，默认debug"),
+        )
+        .arg(
+            Arg::new("auth-config")
+                .long("auth-config")
+                .value_name("FILE")
+                .help("API密钥配置文件（TOML），不指定则不启用认证"),
+        )
+        .arg(
+            Arg::new("rate-limit-config")
+                .long("rate-limit-config")
+                .value_name("FILE")
+                .help("限流与配额配置文件（TOML），不指定则不限流"),
+        )
+        .arg(
+            Arg::new("jobs-dir")
+                .long("jobs-dir")
+                .value_name("DIR")
+                .help("异步任务状态与输出的存储目录（默认jobs）"),
+        )
+        .arg(
+            Arg::new("job-workers")
+                .long("job-workers")
+                .value_name("COUNT")
+                .help("并行执行的异步任务数（默认1）"),
+        )
+        .arg(
+            Arg::new("webhook-secret")
+                .long("webhook-secret")
+                .value_name("SECRET")
+                .help("任务回调的HMAC-SHA256签名密钥，不指定则不支持callback_url"),
+        )
+        .arg(
+            Arg::new("public-url")
+                .long("public-url")
+                .value_name("URL")
+                .help("服务对外地址，用于生成回调通知中的下载链接"),
         )
         .get_matches();
 
-    // 初始化日志，过滤掉ort和web-rwkv的调试输出
-    let filter = EnvFilter::new("debug")
-        .add_directive("ort=warn".parse().unwrap())
-        .add_directive("web_rwkv=warn".parse().unwrap())
-        .add_directive("naga=warn".parse().unwrap())
-        .add_directive("wgpu=warn".parse().unwrap());
+    // 加载配置（在加载模型之前校验，配置错误时尽早退出）
+    let config = load_server_config(&matches)?;
+
+    // 初始化日志，默认过滤掉ort和web-rwkv的调试输出
+    let mut filter = EnvFilter::new(&config.log.level);
+    for directive in &config.log.directives {
+        let directive: tracing_subscriber::filter::Directive = directive.parse()?;
+        filter = filter.add_directive(directive);
+    }
 
     tracing_subscriber::fmt().with_env_filter(filter).init();
 
     info!("启动RWKV TTS HTTP服务器...");
+    if let Some(path) = matches.get_one::<String>("config") {
+        info!("📄 已加载配置文件: {}", path);
+    }
+
+    // API密钥认证
+    let api_key_store = match &config.auth {
+        Some(auth_config) => {
+            let store = ApiKeyStore::new(auth_config);
+            info!(
+                "🔐 已启用API密钥认证: {} 个密钥，匿名访问Web UI: {}",
+                store.len(),
+                store.allow_anonymous_ui()
+            );
+            Some(Arc::new(store))
+        }
+        None => {
+            warn!("未配置API密钥（--auth-config或配置文件[auth]），API未启用认证");
+            None
+        }
+    };
+
+    // 限流
+    let rate_limiter = match &config.rate_limit {
+        Some(rate_limit_config) => {
+            info!(
+                "🚦 已启用限流: 默认 {:?}，按密钥覆盖 {} 项",
+                rate_limit_config.default,
+                rate_limit_config.keys.len()
+            );
+            Some(Arc::new(RateLimiter::new(rate_limit_config.clone())))
+        }
+        None => None,
+    };
 
-    // 获取命令行参数
-    let model_path = matches.get_one::<String>("model-path").unwrap();
-    let vocab_path = matches.get_one::<String>("vocab-path").unwrap();
-    let quant_layers: usize = matches
-        .get_one::<String>("quant-layers")
-        .unwrap()
-        .parse()
-        .map_err(|e| anyhow::anyhow!("无效的量化层数: {}", e))?;
-    let quant_type_str = matches.get_one::<String>("quant-type").unwrap();
-    let quant_type = parse_quant_type(quant_type_str)?;
+    let model_path = config.model.model_path.as_str();
+    let vocab_path = config.model.vocab_path.as_str();
+    let quant_type = parse_quant_type(&config.model.quant_type)?;
 
     // 创建量化配置
-    let quant_config = create_quant_config(quant_layers, quant_type);
+    let quant_config = create_quant_config(config.model.quant_layers, quant_type);
+    let quantized_layers = quant_config.as_ref().map_or(0, |config| config.len());
 
     // 打印量化配置信息
     match &quant_config {
@@ -1306,11 +4386,7 @@ async fn main() -> Result<()> {
     // 验证模型文件路径，如果不存在则尝试下载
     let model_missing = !Path::new(model_path).exists();
     let vocab_missing = !Path::new(vocab_path).exists();
-    let onnx_files = [
-        "assets/model/BiCodecTokenize.onnx",
-        "assets/model/wav2vec2-large-xlsr-53.onnx",
-        "assets/model/BiCodecDetokenize.onnx",
-    ];
+    let onnx_files = config.onnx.model_paths();
     let onnx_missing = onnx_files.iter().any(|path| !Path::new(path).exists());
 
     if model_missing || vocab_missing || onnx_missing {
@@ -1356,64 +4432,27 @@ async fn main() -> Result<()> {
     // 架构优化：移除全局RwkvSampler管理器，避免与动态批处理管理器的重复初始化
     // 动态批处理管理器已经内置了共享Runtime架构，无需额外的全局管理器
 
-    // 从命令行参数获取批处理配置
-    let batch_size: usize = matches
-        .get_one::<String>("batch-size")
-        .unwrap()
-        .parse()
-        .expect("无效的批处理大小");
-
-    info!("初始化ONNX会话池（使用原始BiCodec模型）...");
+    info!(
+        "初始化ONNX会话池（使用原始BiCodec模型，大小: {}）...",
+        config.onnx.pool_size
+    );
     rwkv_tts_rs::onnx_session_pool::init_global_onnx_manager(
-        "assets/model/BiCodecTokenize.onnx",
-        "assets/model/wav2vec2-large-xlsr-53.onnx",
-        "assets/model/BiCodecDetokenize.onnx",
-        Some(4),
+        &config.onnx.tokenize_path,
+        &config.onnx.wav2vec2_path,
+        &config.onnx.detokenize_path,
+        Some(config.onnx.pool_size),
     )
     .map_err(|e| anyhow::anyhow!("初始化ONNX管理器失败: {}", e))?;
 
     info!("初始化动态批处理管理器...");
-    // 获取批处理超时配置
-    let batch_timeout: u64 = matches
-        .get_one::<String>("batch-timeout")
-        .unwrap()
-        .parse()
-        .expect("无效的批处理超时时间");
-
-    // 获取推理超时配置
-    let inference_timeout: u64 = matches
-        .get_one::<String>("inference-timeout")
-        .unwrap()
-        .parse()
-        .expect("无效的推理超时时间");
-
-    // 获取token chunk size配置
-    let token_chunk_size: usize = matches
-        .get_one::<String>("token-chunk-size")
-        .unwrap()
-        .parse()
-        .expect("无效的token chunk size");
-
-    // 自动计算最大并发批次数
-    let max_concurrent_batches: usize = if batch_size <= 10 {
-        10
-    } else {
-        std::cmp::max(8, batch_size / 10)
-    };
-
-    // 创建动态批处理配置
-    let dynamic_batch_config = rwkv_tts_rs::batch_types::DynamicBatchConfig {
-        min_batch_size: 1,
-        max_batch_size: batch_size,              // 可配置的批处理大小
-        collect_timeout_ms: batch_timeout,       // 可配置的超时时间
-        inference_timeout_ms: inference_timeout, // 可配置的推理超时时间
-        max_concurrent_batches,                  // 可配置的并发批次数
-        semaphore_permits: (max_concurrent_batches * 3 / 4).clamp(1, 8), // 信号量许可数量略小于并发数
-        token_chunk_size,                                                // 可配置的token chunk size
-    };
+    let dynamic_batch_config = config.batch.to_dynamic_batch_config();
     info!(
-        "动态批处理配置: 最大大小={}, 收集超时={}ms, 推理超时={}ms, 最大并发批次={}（自动计算）",
-        batch_size, batch_timeout, inference_timeout, max_concurrent_batches
+        "动态批处理配置: 最大大小={}, 收集超时={}ms, 推理超时={}ms, 最大并发批次={}, 队列上限={}",
+        dynamic_batch_config.max_batch_size,
+        dynamic_batch_config.collect_timeout_ms,
+        dynamic_batch_config.inference_timeout_ms,
+        dynamic_batch_config.max_concurrent_batches,
+        dynamic_batch_config.max_queue_depth
     );
     rwkv_tts_rs::dynamic_batch_manager::init_global_dynamic_batch_manager(
         model_path,
@@ -1428,46 +4467,200 @@ async fn main() -> Result<()> {
     let tts_pipeline = Arc::new(LightweightTtsPipeline::new());
 
     // 初始化音色特征管理器
-    let voice_manager = Arc::new(VoiceFeatureManager::new("assets/raf")?);
+    let voice_manager = Arc::new(VoiceFeatureManager::new(&config.storage.raf_dir)?);
+
+    // 初始化异步任务管理器（恢复上次未完成的任务）
+    let jobs_dir = &config.storage.jobs_dir;
+    let job_workers = config.jobs.workers;
+    let webhook_notifier = match &config.jobs.webhook_secret {
+        Some(secret) => {
+            let webhook_config = WebhookConfig::new(secret.clone())
+                .with_public_base_url(config.jobs.public_url.clone());
+            Some(Arc::new(WebhookNotifier::new(webhook_config)?))
+        }
+        None => None,
+    };
+    let job_manager = TtsJobManager::new(
+        jobs_dir,
+        tts_pipeline.clone(),
+        job_workers,
+        webhook_notifier,
+    )
+    .await?;
+    info!("异步任务目录: {}, 并发数: {}", jobs_dir, job_workers);
 
     let app_state = AppState {
-        start_time: std::time::Instant::now(),
+        start_time,
         model_path: model_path.to_string(),
         vocab_path: vocab_path.to_string(),
+        quant_layers: quantized_layers,
+        quant_type: format!("{:?}", quant_type),
         tts_pipeline,
         voice_manager,
+        warmup_status: Arc::new(std::sync::RwLock::new(WarmupStatus::Pending)),
+        rate_limiter,
+        job_manager,
+        onnx_config: Arc::new(config.onnx.clone()),
+        active_requests: Arc::new(ActiveRequests::default()),
     };
 
     // 初始化全局应用状态
-    init_global_app_state(app_state);
+    init_global_app_state(app_state.clone());
+
+    // 后台预热，完成前/api/ready返回503
+    tokio::spawn(run_warmup_synthesis(app_state));
 
     // 创建路由
-    let router = Router::new()
-        .hoop(cors_handler)
+    // 存活与就绪探针始终无需认证
+    let public_router = Router::new()
+        .push(Router::with_path("/api/health").get(handle_health))
+        .push(Router::with_path("/api/ready").get(handle_ready));
+
+    // 合成接口受限流与配额约束
+    let synthesis_router = Router::new()
+        .hoop(rate_limit_handler)
         .push(Router::with_path("/api/tts").post(handle_tts))
-        .push(Router::with_path("/api/voice-clone/extract").post(handle_voice_extract))
+        .push(Router::with_path("/api/tts/stream").post(handle_tts_stream))
+        .push(Router::with_path("/api/tts/sse").post(handle_tts_sse))
+        .push(Router::with_path("/api/tts/ws").get(handle_tts_websocket))
+        .push(Router::with_path("/api/jobs").post(handle_job_submit))
+        // OpenAI兼容接口
+        .push(Router::with_path("/v1/audio/speech").post(handle_oai_speech))
+        .push(
+            Router::with_path("/api/oai")
+                .push(Router::with_path("audio/speech").post(handle_oai_speech))
+                .push(Router::with_path("v1/audio/speech").post(handle_oai_speech)),
+        );
+
+    let read_router = Router::new()
+        .hoop(ApiKeyAuth::new(api_key_store.clone(), ApiScope::Read))
+        .push(Router::with_path("/api/status").get(handle_status))
+        .push(Router::with_path("/metrics").get(handle_metrics))
         .push(Router::with_path("/api/voice-clone/list").get(handle_voice_list))
+        .push(Router::with_path("/api/voice-clone/{id}").get(handle_voice_get))
+        .push(Router::with_path("/api/jobs").get(handle_job_list))
+        .push(
+            Router::with_path("/api/jobs/{id}")
+                .get(handle_job_status)
+                .push(Router::with_path("cancel").post(handle_job_cancel))
+                .push(Router::with_path("audio").get(handle_job_audio)),
+        )
+        .push(Router::with_path("/api/tts/requests/{id}/cancel").post(handle_tts_cancel))
+        .push(synthesis_router);
+
+    // 修改音色库的操作需要admin权限
+    let admin_router = Router::new()
+        .hoop(ApiKeyAuth::new(api_key_store.clone(), ApiScope::Admin))
+        .push(Router::with_path("/api/voice-clone/extract").post(handle_voice_extract))
         .push(Router::with_path("/api/voice-clone/delete").post(handle_voice_delete))
-        .push(Router::with_path("{*path}").get(handle_static_files));
+        .push(Router::with_path("/api/voice-clone/import").post(handle_voice_import))
+        .push(
+            Router::with_path("/api/voice-clone/{id}")
+                .patch(handle_voice_update)
+                .delete(handle_voice_delete_by_id)
+                .push(Router::with_path("export").get(handle_voice_export)),
+        );
+
+    let router = Router::new()
+        .hoop(Cors::new(&config.listen.cors_origins))
+        .push(public_router)
+        .push(read_router)
+        .push(admin_router)
+        .push(
+            Router::with_path("{*path}")
+                .hoop(ApiKeyAuth::static_ui(api_key_store))
+                .get(handle_static_files),
+        );
 
     // 注意：现在静态文件已嵌入到二进制文件中，不再依赖外部static目录
 
     // 创建服务
     let service = Service::new(router).hoop(request_logger);
 
-    let port: u16 = matches
-        .get_one::<String>("port")
-        .and_then(|s| s.parse::<u16>().ok())
-        .unwrap_or(3000);
-
-    let acceptor = TcpListener::new(format!("0.0.0.0:{port}")).bind().await;
+    let port = config.listen.port;
+    let bind_addr = std::net::SocketAddr::new(config.listen.host, port);
+    let acceptor = TcpListener::new(bind_addr).bind().await;
 
-    info!("服务器启动成功，监听端口: http://0.0.0.0:{}", port);
+    info!("服务器启动成功，监听地址: http://{}", bind_addr);
     info!("Web UI: http://localhost:{}", port);
-    info!("API文档: http://localhost:{}/api/status", port);
+    info!("服务状态: http://localhost:{}/api/status", port);
+    info!("Prometheus指标: http://localhost:{}/metrics", port);
+    info!(
+        "健康检查: http://localhost:{}/api/health, 就绪检查: /api/ready",
+        port
+    );
     info!("TTS服务已就绪，使用预加载的全局模型实例，支持高并发访问");
 
-    Server::new(acceptor).serve(service).await;
+    let server = Server::new(acceptor);
+    let server_handle = server.handle();
+    let grace = std::time::Duration::from_secs(config.listen.shutdown_grace_secs);
+    let shutdown_task = tokio::spawn(async move {
+        shutdown_signal().await;
+        graceful_shutdown(server_handle, grace).await;
+    });
+
+    server.serve(service).await;
+
+    // serve只在停止信号后返回，等待批处理收尾后清理临时文件
+    let _ = shutdown_task.await;
+    match get_global_app_state()
+        .voice_manager
+        .clean_upload_temp_files()
+    {
+        Ok(0) => {}
+        Ok(removed) => info!("已清理 {} 个上传临时文件", removed),
+        Err(e) => warn!("清理上传临时文件失败: {}", e),
+    }
+    info!("服务器已退出");
 
     Ok(())
 }
+
+/// 宽限期结束后留给连接发送错误响应的时间
+const SHUTDOWN_RESPONSE_MARGIN: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// 等待SIGINT（Ctrl+C）或SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("监听Ctrl+C信号失败: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("监听SIGTERM信号失败: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("收到SIGINT"),
+        _ = terminate => info!("收到SIGTERM"),
+    }
+}
+
+/// 优雅关闭：停止接受新连接和新任务，等待排队与推理中的请求在宽限期内完成，
+/// 超时后让剩余请求以错误返回
+async fn graceful_shutdown(server_handle: salvo::server::ServerHandle, grace: std::time::Duration) {
+    info!(
+        "🛑 开始优雅关闭，宽限期 {} 秒，不再接受新连接",
+        grace.as_secs()
+    );
+    get_global_app_state().job_manager.begin_shutdown();
+    // 额外留出时间把被终止请求的错误响应发回客户端
+    server_handle.stop_graceful(Some(grace + SHUTDOWN_RESPONSE_MARGIN));
+
+    if let Ok(manager) = rwkv_tts_rs::dynamic_batch_manager::get_global_dynamic_batch_manager() {
+        manager.shutdown(grace).await;
+    }
+}
@@ -0,0 +1,280 @@
+//! API密钥认证
+//!
+//! 从TOML配置文件加载Bearer令牌，区分只读（read）与管理（admin）两种权限。
+//! 内存中只保存密钥的SHA-256摘要，按摘要查找，避免逐字节比较泄露时序信息。
+
+use anyhow::Result;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 密钥权限，admin包含read的全部权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    /// 合成、查询音色等常规调用
+    Read,
+    /// 额外允许提取、删除音色等管理操作
+    Admin,
+}
+
+impl ApiScope {
+    /// 当前权限是否满足`required`
+    pub fn allows(self, required: ApiScope) -> bool {
+        self >= required
+    }
+
+    /// 权限名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::Admin => "admin",
+        }
+    }
+}
+
+fn default_scope() -> ApiScope {
+    ApiScope::Read
+}
+
+fn default_allow_anonymous_ui() -> bool {
+    true
+}
+
+/// 单个API密钥配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// 密钥名称，用于日志和限流统计，不参与认证
+    pub name: String,
+    /// Bearer令牌
+    pub key: String,
+    /// 权限，默认read
+    #[serde(default = "default_scope")]
+    pub scope: ApiScope,
+}
+
+/// 认证配置文件
+///
+/// ```toml
+/// allow_anonymous_ui = true
+///
+/// [[keys]]
+/// name = "web"
+/// key = "sk-read-xxxxxxxxxxxxxxxx"
+/// scope = "read"
+///
+/// [[keys]]
+/// name = "ops"
+/// key = "sk-admin-xxxxxxxxxxxxxxxx"
+/// scope = "admin"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    /// 未携带密钥时是否允许访问静态Web UI
+    #[serde(default = "default_allow_anonymous_ui")]
+    pub allow_anonymous_ui: bool,
+    /// 密钥列表
+    #[serde(default)]
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+/// 密钥最小长度
+const MIN_KEY_LENGTH: usize = 16;
+
+impl AuthConfig {
+    /// 从TOML文件加载并校验配置
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("读取认证配置 {} 失败: {}", path.display(), e))?;
+        Self::from_toml(&content)
+    }
+
+    /// 从TOML字符串解析并校验配置
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let config: AuthConfig =
+            toml::from_str(content).map_err(|e| anyhow::anyhow!("解析认证配置失败: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 校验密钥列表：至少一个密钥，名称与密钥均不可重复，密钥长度不少于16
+    pub fn validate(&self) -> Result<()> {
+        if self.keys.is_empty() {
+            return Err(anyhow::anyhow!("认证配置中没有任何密钥"));
+        }
+
+        let mut names = std::collections::HashSet::new();
+        let mut keys = std::collections::HashSet::new();
+        for entry in &self.keys {
+            if entry.name.trim().is_empty() {
+                return Err(anyhow::anyhow!("密钥名称不能为空"));
+            }
+            if entry.key.len() < MIN_KEY_LENGTH {
+                return Err(anyhow::anyhow!(
+                    "密钥 {} 过短，至少需要{}个字符",
+                    entry.name,
+                    MIN_KEY_LENGTH
+                ));
+            }
+            if !names.insert(entry.name.as_str()) {
+                return Err(anyhow::anyhow!("密钥名称重复: {}", entry.name));
+            }
+            if !keys.insert(entry.key.as_str()) {
+                return Err(anyhow::anyhow!("密钥 {} 与其他密钥重复", entry.name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 认证通过的调用方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyIdentity {
+    pub name: String,
+    pub scope: ApiScope,
+}
+
+/// 密钥存储
+#[derive(Debug)]
+pub struct ApiKeyStore {
+    keys: HashMap<[u8; 32], ApiKeyIdentity>,
+    allow_anonymous_ui: bool,
+}
+
+impl ApiKeyStore {
+    /// 根据配置构建密钥存储
+    pub fn new(config: &AuthConfig) -> Self {
+        let keys = config
+            .keys
+            .iter()
+            .map(|entry| {
+                (
+                    digest_key(&entry.key),
+                    ApiKeyIdentity {
+                        name: entry.name.clone(),
+                        scope: entry.scope,
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            keys,
+            allow_anonymous_ui: config.allow_anonymous_ui,
+        }
+    }
+
+    /// 校验令牌，返回对应的调用方
+    pub fn authenticate(&self, token: &str) -> Option<&ApiKeyIdentity> {
+        self.keys.get(&digest_key(token))
+    }
+
+    /// 未认证时是否允许访问静态Web UI
+    pub fn allow_anonymous_ui(&self) -> bool {
+        self.allow_anonymous_ui
+    }
+
+    /// 已配置的密钥数量
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// 是否没有配置任何密钥
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+fn digest_key(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+/// 从Authorization头中提取Bearer令牌
+pub fn parse_bearer_token(header: &str) -> Option<&str> {
+    let (scheme, token) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let token = token.trim();
+    if token.is_empty() {
+        None
+    } else {
+        Some(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+allow_anonymous_ui = false
+
+[[keys]]
+name = "web"
+key = "sk-read-0123456789abcdef"
+
+[[keys]]
+name = "ops"
+key = "sk-admin-0123456789abcdef"
+scope = "admin"
+"#;
+
+    #[test]
+    fn test_load_and_authenticate() {
+        let config = AuthConfig::from_toml(CONFIG).unwrap();
+        let store = ApiKeyStore::new(&config);
+
+        assert_eq!(store.len(), 2);
+        assert!(!store.allow_anonymous_ui());
+
+        let web = store.authenticate("sk-read-0123456789abcdef").unwrap();
+        assert_eq!(web.name, "web");
+        assert_eq!(web.scope, ApiScope::Read);
+        assert!(!web.scope.allows(ApiScope::Admin));
+
+        let ops = store.authenticate("sk-admin-0123456789abcdef").unwrap();
+        assert!(ops.scope.allows(ApiScope::Read));
+        assert!(ops.scope.allows(ApiScope::Admin));
+
+        assert!(store.authenticate("sk-unknown-0123456789").is_none());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(AuthConfig::from_toml("allow_anonymous_ui = true").is_err());
+        assert!(AuthConfig::from_toml("[[keys]]\nname = \"a\"\nkey = \"short\"\n").is_err());
+        assert!(AuthConfig::from_toml(
+            "[[keys]]\nname = \"a\"\nkey = \"sk-0123456789abcdef\"\n\
+             [[keys]]\nname = \"b\"\nkey = \"sk-0123456789abcdef\"\n"
+        )
+        .is_err());
+        assert!(AuthConfig::from_toml(
+            "[[keys]]\nname = \"a\"\nkey = \"sk-0123456789abcdef\"\nscope = \"root\"\n"
+        )
+        .is_err());
+        // 拼错的字段名不会被静默忽略
+        assert!(AuthConfig::from_toml(
+            "allow_anonymous_web = false\n[[keys]]\nname = \"a\"\nkey = \"sk-0123456789abcdef\"\n"
+        )
+        .is_err());
+        assert!(AuthConfig::from_toml(
+            "[[keys]]\nname = \"a\"\nkey = \"sk-0123456789abcdef\"\nscopes = \"admin\"\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_bearer_token() {
+        assert_eq!(parse_bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(parse_bearer_token("bearer  abc "), Some("abc"));
+        assert_eq!(parse_bearer_token("Basic abc"), None);
+        assert_eq!(parse_bearer_token("Bearer "), None);
+        assert_eq!(parse_bearer_token("abc"), None);
+    }
+}
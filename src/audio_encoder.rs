@@ -0,0 +1,1232 @@
+//! 音频编码模块
+//! 所有输出入口（HTTP接口、文件保存）共用的音频编码层，支持WAV（PCM16/float32）、MP3、Ogg/Opus、FLAC和无头PCM
+
+use anyhow::{anyhow, Result};
+use std::borrow::Cow;
+use std::path::Path;
+
+/// BiCodec解码输出的采样率
+pub const MODEL_SAMPLE_RATE: u32 = 16000;
+/// 支持的最低输出采样率（电话）
+pub const MIN_OUTPUT_SAMPLE_RATE: u32 = 8000;
+/// 支持的最高输出采样率（视频）
+pub const MAX_OUTPUT_SAMPLE_RATE: u32 = 48000;
+/// G.711电话编码的采样率
+pub const G711_SAMPLE_RATE: u32 = 8000;
+/// Opus编码器支持的采样率
+pub const OPUS_SAMPLE_RATES: [u32; 5] = [8000, 12000, 16000, 24000, 48000];
+/// LAME支持的MP3采样率（MPEG-1/2/2.5）
+pub const MP3_SAMPLE_RATES: [u32; 9] =
+    [8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000];
+/// LAME支持的MP3比特率档位（kbps）
+pub const MP3_BITRATES_KBPS: [u32; 16] = [
+    8, 16, 24, 32, 40, 48, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+/// Opus编码器接受的最小比特率（kbps）
+pub const MIN_OPUS_BITRATE_KBPS: u32 = 6;
+/// Opus编码器接受的最大比特率（kbps，单声道）
+pub const MAX_OPUS_BITRATE_KBPS: u32 = 510;
+
+/// 输出音频格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    /// 16位PCM WAV
+    Wav,
+    /// 32位浮点WAV
+    WavFloat,
+    /// MP3（mp3lame）
+    Mp3,
+    /// Ogg封装的Opus
+    Opus,
+    /// FLAC无损压缩
+    Flac,
+    /// 16位小端无头PCM
+    Pcm,
+    /// G.711 μ-law裸帧（8kHz，可直接用于RTP PCMU）
+    Mulaw,
+    /// G.711 A-law裸帧（8kHz，可直接用于RTP PCMA）
+    Alaw,
+    /// WAV封装的G.711 μ-law（格式标签7）
+    WavMulaw,
+    /// WAV封装的G.711 A-law（格式标签6）
+    WavAlaw,
+}
+
+impl AudioFormat {
+    /// 根据格式名称解析（用于response_format等请求字段）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "wav" | "wav_pcm16" => Some(Self::Wav),
+            "wav_f32" | "wav_float" | "wav_float32" => Some(Self::WavFloat),
+            "mp3" => Some(Self::Mp3),
+            "opus" | "ogg" => Some(Self::Opus),
+            "flac" => Some(Self::Flac),
+            "pcm" | "pcm16" => Some(Self::Pcm),
+            "ulaw" | "mulaw" | "pcmu" => Some(Self::Mulaw),
+            "alaw" | "pcma" => Some(Self::Alaw),
+            "wav_ulaw" | "wav_mulaw" => Some(Self::WavMulaw),
+            "wav_alaw" => Some(Self::WavAlaw),
+            _ => None,
+        }
+    }
+
+    /// 根据MIME类型解析（用于Accept头内容协商）
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        match mime.to_ascii_lowercase().as_str() {
+            "audio/wav" | "audio/wave" | "audio/x-wav" | "audio/vnd.wave" => Some(Self::Wav),
+            "audio/mpeg" | "audio/mp3" => Some(Self::Mp3),
+            "audio/ogg" | "audio/opus" => Some(Self::Opus),
+            "audio/flac" | "audio/x-flac" => Some(Self::Flac),
+            "audio/pcm" | "audio/l16" => Some(Self::Pcm),
+            "audio/basic" | "audio/pcmu" => Some(Self::Mulaw),
+            "audio/pcma" => Some(Self::Alaw),
+            _ => None,
+        }
+    }
+
+    /// 支持的格式名称列表（用于错误提示）
+    pub fn supported_names() -> &'static str {
+        "wav, wav_f32, mp3, opus, flac, pcm, ulaw, alaw, wav_ulaw, wav_alaw"
+    }
+
+    /// HTTP响应的Content-Type
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Wav | Self::WavFloat | Self::WavMulaw | Self::WavAlaw => "audio/wav",
+            Self::Mp3 => "audio/mpeg",
+            Self::Opus => "audio/ogg",
+            Self::Flac => "audio/flac",
+            Self::Pcm => "audio/pcm",
+            Self::Mulaw => "audio/PCMU",
+            Self::Alaw => "audio/PCMA",
+        }
+    }
+
+    /// 文件扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wav | Self::WavFloat | Self::WavMulaw | Self::WavAlaw => "wav",
+            Self::Mp3 => "mp3",
+            Self::Opus => "ogg",
+            Self::Flac => "flac",
+            Self::Pcm => "pcm",
+            Self::Mulaw => "ulaw",
+            Self::Alaw => "alaw",
+        }
+    }
+
+    /// 是否为有损压缩格式（仅有损格式支持比特率设置）
+    pub fn is_lossy(self) -> bool {
+        matches!(self, Self::Mp3 | Self::Opus)
+    }
+
+    /// 校验比特率是否适用于该格式
+    ///
+    /// 仅有损格式可设置比特率：MP3须为LAME支持的标准档位，Opus为6-510kbps
+    pub fn validate_bitrate(self, bitrate_kbps: Option<u32>) -> Result<()> {
+        let Some(kbps) = bitrate_kbps else {
+            return Ok(());
+        };
+        if !self.is_lossy() {
+            return Err(anyhow!("{:?}格式不支持设置比特率", self));
+        }
+        let valid = match self {
+            Self::Mp3 => MP3_BITRATES_KBPS.contains(&kbps),
+            _ => (MIN_OPUS_BITRATE_KBPS..=MAX_OPUS_BITRATE_KBPS).contains(&kbps),
+        };
+        if valid {
+            Ok(())
+        } else if self == Self::Mp3 {
+            Err(anyhow!(
+                "不支持的MP3比特率: {}kbps，支持: {:?}",
+                kbps,
+                MP3_BITRATES_KBPS
+            ))
+        } else {
+            Err(anyhow!(
+                "不支持的Opus比特率: {}kbps，支持范围: {}-{}kbps",
+                kbps,
+                MIN_OPUS_BITRATE_KBPS,
+                MAX_OPUS_BITRATE_KBPS
+            ))
+        }
+    }
+
+    /// 是否支持逐块流式输出（WAV流式头 + PCM16，或无头的PCM16/G.711）
+    pub fn supports_streaming(self) -> bool {
+        matches!(self, Self::Wav | Self::Pcm | Self::Mulaw | Self::Alaw)
+    }
+
+    /// 编码器是否支持该采样率（Opus和MP3只接受固定的几档，其余格式覆盖整个输出范围）
+    pub fn supports_sample_rate(self, sample_rate: u32) -> bool {
+        if let Some(required) = self.required_sample_rate() {
+            return sample_rate == required;
+        }
+        match self {
+            Self::Opus => OPUS_SAMPLE_RATES.contains(&sample_rate),
+            Self::Mp3 => MP3_SAMPLE_RATES.contains(&sample_rate),
+            _ => (MIN_OUTPUT_SAMPLE_RATE..=MAX_OUTPUT_SAMPLE_RATE).contains(&sample_rate),
+        }
+    }
+
+    /// 格式强制要求的采样率（G.711固定为8kHz）
+    pub fn required_sample_rate(self) -> Option<u32> {
+        match self {
+            Self::Mulaw | Self::Alaw | Self::WavMulaw | Self::WavAlaw => Some(G711_SAMPLE_RATE),
+            _ => None,
+        }
+    }
+}
+
+/// 音频编码参数
+#[derive(Debug, Clone, Copy)]
+pub struct AudioEncodeOptions {
+    /// 输出格式
+    pub format: AudioFormat,
+    /// 采样率（Hz）
+    pub sample_rate: u32,
+    /// 比特率（kbps），仅MP3和Opus可设置，None时使用默认值（MP3 128kbps，Opus 32kbps）
+    pub bitrate_kbps: Option<u32>,
+    /// 编码前是否做峰值归一化（HTTP输出默认开启，保存文件时关闭以保留原始幅度）
+    pub normalize: bool,
+}
+
+impl AudioEncodeOptions {
+    pub fn new(format: AudioFormat, sample_rate: u32) -> Self {
+        Self {
+            format,
+            sample_rate,
+            bitrate_kbps: None,
+            normalize: true,
+        }
+    }
+
+    pub fn with_bitrate(mut self, bitrate_kbps: Option<u32>) -> Self {
+        self.bitrate_kbps = bitrate_kbps;
+        self
+    }
+
+    pub fn without_normalization(mut self) -> Self {
+        self.normalize = false;
+        self
+    }
+}
+
+/// 按选项将f32音频样本编码为目标格式的字节数据
+///
+/// 默认编码前做峰值归一化（见[`normalize_samples`]），保证各格式输出响度一致
+pub fn encode_audio(samples: &[f32], options: &AudioEncodeOptions) -> Result<Vec<u8>> {
+    if options.sample_rate == 0 {
+        return Err(anyhow!("采样率不能为0"));
+    }
+    if let Some(required) = options.format.required_sample_rate() {
+        if options.sample_rate != required {
+            return Err(anyhow!(
+                "{:?}格式要求采样率为{}Hz，当前为: {}",
+                options.format,
+                required,
+                options.sample_rate
+            ));
+        }
+    }
+
+    options.format.validate_bitrate(options.bitrate_kbps)?;
+
+    let normalized = if options.normalize {
+        Cow::Owned(normalize_samples(samples))
+    } else {
+        Cow::Borrowed(samples)
+    };
+    match options.format {
+        AudioFormat::Wav => Ok(encode_wav_pcm16(&normalized, options.sample_rate)),
+        AudioFormat::WavFloat => Ok(encode_wav_f32(&normalized, options.sample_rate)),
+        AudioFormat::Pcm => Ok(samples_to_pcm16(&normalized)),
+        AudioFormat::Mulaw => Ok(encode_g711(&normalized, linear_to_ulaw)),
+        AudioFormat::Alaw => Ok(encode_g711(&normalized, linear_to_alaw)),
+        AudioFormat::WavMulaw => Ok(encode_wav_g711(&normalized, 7, linear_to_ulaw)),
+        AudioFormat::WavAlaw => Ok(encode_wav_g711(&normalized, 6, linear_to_alaw)),
+        AudioFormat::Flac => Ok(encode_flac(&normalized, options.sample_rate)),
+        AudioFormat::Mp3 => encode_mp3(
+            &normalized,
+            options.sample_rate,
+            options.bitrate_kbps.unwrap_or(128),
+        ),
+        AudioFormat::Opus => encode_ogg_opus(
+            &normalized,
+            options.sample_rate,
+            options.bitrate_kbps.unwrap_or(32),
+        ),
+    }
+}
+
+/// 保存音频到文件，按扩展名选择格式（wav、mp3、ogg/opus、flac、pcm，未知扩展名按WAV保存）
+///
+/// 保存文件时不做归一化，保留模型输出的原始幅度；WAV沿用32位浮点格式
+pub fn save_audio_file(samples: &[f32], output_path: &str, sample_rate: u32) -> Result<()> {
+    let extension = Path::new(output_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("wav")
+        .to_lowercase();
+    let format = match AudioFormat::from_name(&extension) {
+        Some(AudioFormat::Wav) | None => AudioFormat::WavFloat,
+        Some(format) => format,
+    };
+
+    let options = AudioEncodeOptions::new(format, sample_rate).without_normalization();
+    let encoded_data = encode_audio(samples, &options)?;
+    std::fs::write(output_path, encoded_data).map_err(|e| anyhow!("写入音频文件失败: {}", e))
+}
+
+/// 峰值归一化：超过1.0时压回满幅，幅度过小时适度放大（最多10倍）
+pub fn normalize_samples(samples: &[f32]) -> Vec<f32> {
+    let max_abs = samples.iter().map(|x| x.abs()).fold(0.0f32, f32::max);
+    let scale_factor = if max_abs > 0.0 {
+        if max_abs > 1.0 {
+            1.0 / max_abs
+        } else {
+            (0.8 / max_abs).min(10.0)
+        }
+    } else {
+        1.0
+    };
+
+    samples.iter().map(|s| s * scale_factor).collect()
+}
+
+/// 将音频从`from_rate`重采样到`to_rate`（一次性处理整段音频）
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let mut resampler = Resampler::new(from_rate, to_rate);
+    let mut output = resampler.process(samples);
+    output.extend(resampler.flush());
+    output
+}
+
+/// 校验输出采样率是否在支持范围内
+pub fn validate_output_sample_rate(sample_rate: u32) -> Result<()> {
+    if (MIN_OUTPUT_SAMPLE_RATE..=MAX_OUTPUT_SAMPLE_RATE).contains(&sample_rate) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "不支持的采样率: {}，支持范围: {}-{}Hz",
+            sample_rate,
+            MIN_OUTPUT_SAMPLE_RATE,
+            MAX_OUTPUT_SAMPLE_RATE
+        ))
+    }
+}
+
+/// 窗函数sinc重采样器，可逐块输入，适用于流式输出
+///
+/// 降采样时按目标奈奎斯特频率设置截止频率以抑制混叠（8kHz电话输出需要）
+pub struct Resampler {
+    /// 输入/输出采样率之比
+    ratio: f64,
+    /// 归一化截止频率（相对输入奈奎斯特频率）
+    cutoff: f64,
+    /// 卷积核半宽（输入样本数）
+    half_width: isize,
+    /// 尚需参与卷积的输入样本
+    buffer: Vec<f32>,
+    /// buffer[0]对应的绝对输入下标
+    buffer_start: u64,
+    /// 已接收的输入样本总数
+    input_len: u64,
+    /// 下一个待输出样本的下标
+    next_output: u64,
+}
+
+impl Resampler {
+    /// 每侧保留的sinc过零点数
+    const ZERO_CROSSINGS: f64 = 16.0;
+
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        let ratio = from_rate as f64 / to_rate as f64;
+        let cutoff = (1.0 / ratio).min(1.0);
+        Self {
+            ratio,
+            cutoff,
+            half_width: (Self::ZERO_CROSSINGS / cutoff).ceil() as isize,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            input_len: 0,
+            next_output: 0,
+        }
+    }
+
+    /// 输入一块音频，返回当前已可确定的输出样本
+    pub fn process(&mut self, chunk: &[f32]) -> Vec<f32> {
+        self.buffer.extend_from_slice(chunk);
+        self.input_len += chunk.len() as u64;
+        self.drain(false)
+    }
+
+    /// 输入结束，输出剩余样本（缺失的右侧上下文按静音处理）
+    pub fn flush(&mut self) -> Vec<f32> {
+        self.drain(true)
+    }
+
+    fn drain(&mut self, finished: bool) -> Vec<f32> {
+        let total_output = (self.input_len as f64 / self.ratio).round() as u64;
+        let mut output = Vec::new();
+
+        loop {
+            if finished && self.next_output >= total_output {
+                break;
+            }
+            let position = self.next_output as f64 * self.ratio;
+            let center = position.floor() as isize;
+            if !finished && center + self.half_width >= self.input_len as isize {
+                break;
+            }
+            output.push(self.interpolate(position, center));
+            self.next_output += 1;
+        }
+
+        // 丢弃之后不再需要的输入样本
+        let keep_from = (self.next_output as f64 * self.ratio).floor() as isize - self.half_width;
+        if keep_from > self.buffer_start as isize {
+            let drop = (keep_from as u64 - self.buffer_start).min(self.buffer.len() as u64);
+            self.buffer.drain(..drop as usize);
+            self.buffer_start += drop;
+        }
+
+        output
+    }
+
+    fn interpolate(&self, position: f64, center: isize) -> f32 {
+        let mut sum = 0.0f64;
+        for index in (center - self.half_width + 1)..=(center + self.half_width) {
+            if index < self.buffer_start as isize {
+                continue;
+            }
+            let Some(&sample) = self
+                .buffer
+                .get((index - self.buffer_start as isize) as usize)
+            else {
+                break;
+            };
+            let x = position - index as f64;
+            sum += sample as f64 * self.kernel(x);
+        }
+        sum as f32
+    }
+
+    /// 带Hann窗的低通sinc核
+    fn kernel(&self, x: f64) -> f64 {
+        let half_width = self.half_width as f64;
+        if x.abs() >= half_width {
+            return 0.0;
+        }
+        let window = 0.5 * (1.0 + (std::f64::consts::PI * x / half_width).cos());
+        let t = self.cutoff * x;
+        let sinc = if t.abs() < 1e-9 {
+            1.0
+        } else {
+            (std::f64::consts::PI * t).sin() / (std::f64::consts::PI * t)
+        };
+        self.cutoff * sinc * window
+    }
+}
+
+/// 将单个f32样本转换为i16（截断到[-1, 1]）
+fn sample_to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+/// 将f32音频样本转换为16位小端PCM字节（不做归一化，流式场景直接使用）
+pub fn samples_to_pcm16(samples: &[f32]) -> Vec<u8> {
+    let mut pcm = Vec::with_capacity(samples.len() * 2);
+    for &sample in samples {
+        pcm.extend_from_slice(&sample_to_i16(sample).to_le_bytes());
+    }
+    pcm
+}
+
+/// 编码一块流式音频数据（不做归一化，WAV格式需先单独发送[`streaming_wav_header`]）
+pub fn encode_stream_chunk(format: AudioFormat, samples: &[f32]) -> Result<Vec<u8>> {
+    match format {
+        AudioFormat::Wav | AudioFormat::Pcm => Ok(samples_to_pcm16(samples)),
+        AudioFormat::Mulaw => Ok(encode_g711(samples, linear_to_ulaw)),
+        AudioFormat::Alaw => Ok(encode_g711(samples, linear_to_alaw)),
+        other => Err(anyhow!("{:?}格式不支持流式输出", other)),
+    }
+}
+
+/// 生成单声道WAV文件头
+///
+/// `data_len`为None时表示流式输出，RIFF和data长度使用0xFFFFFFFF占位
+pub fn wav_header(
+    sample_rate: u32,
+    format_tag: u16,
+    bits_per_sample: u16,
+    data_len: Option<u32>,
+) -> Vec<u8> {
+    let block_align = bits_per_sample / 8;
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    let riff_len = data_len.map_or(u32::MAX, |len| len.saturating_add(36));
+    header.extend_from_slice(&riff_len.to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&16u32.to_le_bytes()); // chunk size
+    header.extend_from_slice(&format_tag.to_le_bytes()); // audio format
+    header.extend_from_slice(&1u16.to_le_bytes()); // num channels (mono)
+    header.extend_from_slice(&sample_rate.to_le_bytes()); // sample rate
+    header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes()); // byte rate
+    header.extend_from_slice(&block_align.to_le_bytes()); // block align
+    header.extend_from_slice(&bits_per_sample.to_le_bytes()); // bits per sample
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.unwrap_or(u32::MAX).to_le_bytes());
+    header
+}
+
+/// 生成流式WAV文件头（16位单声道PCM）
+pub fn streaming_wav_header(sample_rate: u32) -> Vec<u8> {
+    wav_header(sample_rate, 1, 16, None)
+}
+
+/// 编码为16位PCM WAV
+fn encode_wav_pcm16(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let data = samples_to_pcm16(samples);
+    let mut wav = wav_header(sample_rate, 1, 16, Some(data.len() as u32));
+    wav.extend_from_slice(&data);
+    wav
+}
+
+/// 编码为32位浮点WAV（格式标签3，IEEE float）
+fn encode_wav_f32(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let mut wav = wav_header(sample_rate, 3, 32, Some(samples.len() as u32 * 4));
+    wav.reserve(samples.len() * 4);
+    for &sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+/// 编码为MP3（单声道），比特率取最接近的LAME档位
+fn encode_mp3(samples: &[f32], sample_rate: u32, bitrate_kbps: u32) -> Result<Vec<u8>> {
+    use mp3lame_encoder::{Bitrate, Builder, FlushNoGap, InterleavedPcm, Quality};
+    use std::mem::MaybeUninit;
+
+    let i16_samples: Vec<i16> = samples.iter().map(|&s| sample_to_i16(s)).collect();
+
+    let bitrate = match bitrate_kbps {
+        8 => Bitrate::Kbps8,
+        16 => Bitrate::Kbps16,
+        24 => Bitrate::Kbps24,
+        32 => Bitrate::Kbps32,
+        40 => Bitrate::Kbps40,
+        48 => Bitrate::Kbps48,
+        64 => Bitrate::Kbps64,
+        80 => Bitrate::Kbps80,
+        96 => Bitrate::Kbps96,
+        112 => Bitrate::Kbps112,
+        128 => Bitrate::Kbps128,
+        160 => Bitrate::Kbps160,
+        192 => Bitrate::Kbps192,
+        224 => Bitrate::Kbps224,
+        256 => Bitrate::Kbps256,
+        320 => Bitrate::Kbps320,
+        other => return Err(anyhow!("不支持的MP3比特率: {}kbps", other)),
+    };
+
+    let mut builder = Builder::new().ok_or_else(|| anyhow!("创建MP3编码器失败"))?;
+    builder
+        .set_num_channels(1)
+        .map_err(|e| anyhow!("设置声道数失败: {}", e))?;
+    builder
+        .set_sample_rate(sample_rate)
+        .map_err(|e| anyhow!("设置采样率失败: {}", e))?;
+    builder
+        .set_brate(bitrate)
+        .map_err(|e| anyhow!("设置比特率失败: {}", e))?;
+    builder
+        .set_quality(Quality::Best)
+        .map_err(|e| anyhow!("设置质量失败: {}", e))?;
+    let mut encoder = builder
+        .build()
+        .map_err(|e| anyhow!("构建MP3编码器失败: {}", e))?;
+
+    // LAME建议的最坏情况缓冲区大小：1.25 * 样本数 + 7200
+    let mut mp3_buffer: Vec<MaybeUninit<u8>> =
+        vec![MaybeUninit::uninit(); i16_samples.len() * 5 / 4 + 7200];
+    let encoded_size = encoder
+        .encode(InterleavedPcm(&i16_samples), &mut mp3_buffer)
+        .map_err(|e| anyhow!("MP3编码失败: {}", e))?;
+
+    // 安全地转换MaybeUninit<u8>到u8
+    let mut encoded_data: Vec<u8> = mp3_buffer[..encoded_size]
+        .iter()
+        .map(|x| unsafe { x.assume_init() })
+        .collect();
+
+    // 刷新编码器并追加剩余数据
+    let mut flush_buffer: Vec<MaybeUninit<u8>> = vec![MaybeUninit::uninit(); 7200];
+    let flush_size = encoder
+        .flush::<FlushNoGap>(&mut flush_buffer)
+        .map_err(|e| anyhow!("刷新MP3编码器失败: {}", e))?;
+    encoded_data.extend(
+        flush_buffer[..flush_size]
+            .iter()
+            .map(|x| unsafe { x.assume_init() }),
+    );
+
+    Ok(encoded_data)
+}
+
+/// 编码为Ogg封装的Opus（RFC 7845），使用20ms帧
+fn encode_ogg_opus(samples: &[f32], sample_rate: u32, bitrate_kbps: u32) -> Result<Vec<u8>> {
+    use audiopus::coder::Encoder;
+    use audiopus::{Application, Bitrate, Channels, SampleRate};
+    use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+    let opus_rate = match sample_rate {
+        8000 => SampleRate::Hz8000,
+        12000 => SampleRate::Hz12000,
+        16000 => SampleRate::Hz16000,
+        24000 => SampleRate::Hz24000,
+        48000 => SampleRate::Hz48000,
+        other => {
+            return Err(anyhow!(
+                "Opus仅支持8000/12000/16000/24000/48000Hz采样率，当前为: {}",
+                other
+            ))
+        }
+    };
+
+    let mut encoder = Encoder::new(opus_rate, Channels::Mono, Application::Audio)
+        .map_err(|e| anyhow!("创建Opus编码器失败: {}", e))?;
+    let bits_per_second = bitrate_kbps
+        .checked_mul(1000)
+        .and_then(|bps| i32::try_from(bps).ok())
+        .ok_or_else(|| anyhow!("Opus比特率超出范围: {}kbps", bitrate_kbps))?;
+    encoder
+        .set_bitrate(Bitrate::BitsPerSecond(bits_per_second))
+        .map_err(|e| anyhow!("设置Opus比特率失败: {}", e))?;
+
+    // Ogg Opus的granule position固定以48kHz计数
+    let granule_scale = (48000 / sample_rate) as u64;
+    let lookahead = encoder
+        .lookahead()
+        .map_err(|e| anyhow!("获取Opus编码延迟失败: {}", e))?;
+    let pre_skip = lookahead as u64 * granule_scale;
+    let frame_size = (sample_rate / 50) as usize;
+
+    let serial = rand::random::<u32>();
+    let mut writer = PacketWriter::new(Vec::new());
+
+    // OpusHead
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1); // version
+    head.push(1); // channel count
+    head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+    head.extend_from_slice(&sample_rate.to_le_bytes()); // input sample rate
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // channel mapping family
+    writer
+        .write_packet(
+            head.into_boxed_slice(),
+            serial,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )
+        .map_err(|e| anyhow!("写入OpusHead失败: {}", e))?;
+
+    // OpusTags
+    let vendor = concat!("rwkv-tts-rs ", env!("CARGO_PKG_VERSION"));
+    let mut tags = Vec::new();
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor.as_bytes());
+    tags.extend_from_slice(&0u32.to_le_bytes()); // user comment list length
+    writer
+        .write_packet(
+            tags.into_boxed_slice(),
+            serial,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )
+        .map_err(|e| anyhow!("写入OpusTags失败: {}", e))?;
+
+    // 末尾补零以冲刷编码器延迟，并对齐到整帧
+    let mut pcm: Vec<i16> = samples.iter().map(|&s| sample_to_i16(s)).collect();
+    let padded_len = (pcm.len() + lookahead as usize).div_ceil(frame_size) * frame_size;
+    pcm.resize(padded_len.max(frame_size), 0);
+
+    let end_granule = pre_skip + samples.len() as u64 * granule_scale;
+    let frame_count = pcm.len() / frame_size;
+    let mut packet = vec![0u8; 4000];
+    for (index, frame) in pcm.chunks(frame_size).enumerate() {
+        let packet_len = encoder
+            .encode(frame, &mut packet)
+            .map_err(|e| anyhow!("Opus编码失败: {}", e))?;
+        let is_last = index + 1 == frame_count;
+        let granule = if is_last {
+            end_granule
+        } else {
+            ((index + 1) * frame_size) as u64 * granule_scale
+        };
+        let end_info = if is_last {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        writer
+            .write_packet(
+                packet[..packet_len].to_vec().into_boxed_slice(),
+                serial,
+                end_info,
+                granule,
+            )
+            .map_err(|e| anyhow!("写入Ogg页失败: {}", e))?;
+    }
+
+    Ok(writer.into_inner())
+}
+
+/// 将音频编码为G.711裸帧（每样本1字节）
+fn encode_g711(samples: &[f32], encode_sample: fn(i16) -> u8) -> Vec<u8> {
+    samples
+        .iter()
+        .map(|&s| encode_sample(sample_to_i16(s)))
+        .collect()
+}
+
+/// 编码为WAV封装的G.711（格式标签6为A-law，7为μ-law）
+///
+/// 非PCM格式的WAV按规范使用18字节fmt块并附带fact块
+fn encode_wav_g711(samples: &[f32], format_tag: u16, encode_sample: fn(i16) -> u8) -> Vec<u8> {
+    let data = encode_g711(samples, encode_sample);
+    let data_len = data.len() as u32;
+
+    let mut wav = Vec::with_capacity(58 + data.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(50 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&18u32.to_le_bytes()); // chunk size
+    wav.extend_from_slice(&format_tag.to_le_bytes()); // audio format
+    wav.extend_from_slice(&1u16.to_le_bytes()); // num channels (mono)
+    wav.extend_from_slice(&G711_SAMPLE_RATE.to_le_bytes()); // sample rate
+    wav.extend_from_slice(&G711_SAMPLE_RATE.to_le_bytes()); // byte rate
+    wav.extend_from_slice(&1u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&8u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(&0u16.to_le_bytes()); // extra format bytes
+    wav.extend_from_slice(b"fact");
+    wav.extend_from_slice(&4u32.to_le_bytes());
+    wav.extend_from_slice(&data_len.to_le_bytes()); // sample count
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.extend_from_slice(&data);
+    wav
+}
+
+/// 16位线性PCM转G.711 μ-law（ITU-T G.711）
+pub fn linear_to_ulaw(sample: i16) -> u8 {
+    const BIAS: i32 = 0x21;
+    const CLIP: i32 = 8159;
+
+    // 按14位精度处理
+    let mut pcm = (sample as i32) >> 2;
+    let mask = if pcm < 0 {
+        pcm = -pcm;
+        0x7F
+    } else {
+        0xFF
+    };
+    pcm = pcm.min(CLIP) + BIAS;
+
+    // 段号：pcm <= 0x3F为第0段，之后每段上限翻倍
+    let segment = (0..8).find(|&seg| pcm < (0x40 << seg)).unwrap_or(8);
+    if segment >= 8 {
+        return (0x7F ^ mask) as u8;
+    }
+
+    let mantissa = (pcm >> (segment + 1)) & 0x0F;
+    (((segment << 4) | mantissa) ^ mask) as u8
+}
+
+/// 16位线性PCM转G.711 A-law（ITU-T G.711）
+pub fn linear_to_alaw(sample: i16) -> u8 {
+    // 按13位精度处理
+    let mut pcm = (sample as i32) >> 3;
+    let mask = if pcm >= 0 {
+        0xD5
+    } else {
+        pcm = -pcm - 1;
+        0x55
+    };
+
+    // 段号：pcm <= 0x1F为第0段，之后每段上限翻倍
+    let segment = (0..8).find(|&seg| pcm < (0x20 << seg)).unwrap_or(8);
+    if segment >= 8 {
+        return (0x7F ^ mask) as u8;
+    }
+
+    let mantissa = if segment < 2 {
+        (pcm >> 1) & 0x0F
+    } else {
+        (pcm >> segment) & 0x0F
+    };
+    (((segment << 4) | mantissa) ^ mask) as u8
+}
+
+/// FLAC块大小（每帧样本数）
+const FLAC_BLOCK_SIZE: usize = 4096;
+
+/// 编码为16位单声道FLAC（固定阶预测 + Rice残差编码）
+fn encode_flac(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let pcm: Vec<i32> = samples.iter().map(|&s| sample_to_i16(s) as i32).collect();
+
+    let mut out = Vec::with_capacity(pcm.len() + 64);
+    out.extend_from_slice(b"fLaC");
+
+    // STREAMINFO元数据块（最后一个元数据块，长度34字节）
+    let mut info = FlacBitWriter::new();
+    info.write_bits(1, 1);
+    info.write_bits(0, 7);
+    info.write_bits(34, 24);
+    let max_block = pcm.len().clamp(16, FLAC_BLOCK_SIZE) as u64;
+    let min_block = if pcm.len() > FLAC_BLOCK_SIZE {
+        max_block
+    } else {
+        max_block.min(pcm.len().max(16) as u64)
+    };
+    info.write_bits(min_block, 16);
+    info.write_bits(max_block, 16);
+    info.write_bits(0, 24); // 最小帧大小（未知）
+    info.write_bits(0, 24); // 最大帧大小（未知）
+    info.write_bits(sample_rate as u64, 20);
+    info.write_bits(0, 3); // 声道数-1
+    info.write_bits(15, 5); // 位深-1
+    info.write_bits(pcm.len() as u64, 36);
+    info.write_bits(0, 64); // MD5（未计算）
+    info.write_bits(0, 64);
+    out.extend_from_slice(&info.into_bytes());
+
+    for (frame_number, block) in pcm.chunks(FLAC_BLOCK_SIZE).enumerate() {
+        out.extend_from_slice(&encode_flac_frame(block, frame_number as u64, sample_rate));
+    }
+
+    out
+}
+
+/// 编码一个FLAC帧
+fn encode_flac_frame(block: &[i32], frame_number: u64, sample_rate: u32) -> Vec<u8> {
+    let mut w = FlacBitWriter::new();
+
+    // 帧头
+    w.write_bits(0b11_1111_1111_1110, 14); // 同步码
+    w.write_bits(0, 1); // 保留位
+    w.write_bits(0, 1); // 固定块大小
+    let block_size_code = if block.len() == FLAC_BLOCK_SIZE {
+        0b1100
+    } else {
+        0b0111
+    };
+    w.write_bits(block_size_code, 4);
+    let sample_rate_code = match sample_rate {
+        8000 => 0b0100,
+        16000 => 0b0101,
+        22050 => 0b0110,
+        24000 => 0b0111,
+        32000 => 0b1000,
+        44100 => 0b1001,
+        48000 => 0b1010,
+        96000 => 0b1011,
+        _ => 0b0000, // 从STREAMINFO读取
+    };
+    w.write_bits(sample_rate_code, 4);
+    w.write_bits(0, 4); // 单声道
+    w.write_bits(0b100, 3); // 16位
+    w.write_bits(0, 1); // 保留位
+    w.write_utf8_number(frame_number);
+    if block_size_code == 0b0111 {
+        w.write_bits(block.len() as u64 - 1, 16);
+    }
+    let crc8 = flac_crc8(w.bytes());
+    w.write_bits(crc8 as u64, 8);
+
+    write_flac_subframe(&mut w, block);
+
+    w.align_to_byte();
+    let crc16 = flac_crc16(w.bytes());
+    w.write_bits(crc16 as u64, 16);
+    w.into_bytes()
+}
+
+/// 写入子帧：选择残差绝对值和最小的固定阶预测器（0-4阶）
+fn write_flac_subframe(w: &mut FlacBitWriter, block: &[i32]) {
+    let max_order = block.len().saturating_sub(1).min(4);
+    let (order, residuals) = (0..=max_order)
+        .map(|order| (order, fixed_residuals(block, order)))
+        .min_by_key(|(_, residuals)| {
+            residuals
+                .iter()
+                .map(|r| r.unsigned_abs() as u64)
+                .sum::<u64>()
+        })
+        .expect("至少存在0阶预测器");
+
+    w.write_bits(0, 1); // 填充位
+    w.write_bits(0b001000 | order as u64, 6); // FIXED子帧类型
+    w.write_bits(0, 1); // 无wasted bits
+
+    for &warmup in &block[..order] {
+        w.write_signed(warmup, 16);
+    }
+
+    // 残差：Rice编码，分区阶数0，选择总位数最小的Rice参数
+    let rice_param = (0..15u32)
+        .min_by_key(|&k| {
+            residuals
+                .iter()
+                .map(|&r| (zigzag(r) >> k) as u64 + 1 + k as u64)
+                .sum::<u64>()
+        })
+        .unwrap_or(0);
+    w.write_bits(0, 2); // 4位Rice参数
+    w.write_bits(0, 4); // 分区阶数0
+    w.write_bits(rice_param as u64, 4);
+    for &residual in &residuals {
+        let value = zigzag(residual);
+        w.write_unary(value >> rice_param);
+        w.write_bits((value & ((1 << rice_param) - 1)) as u64, rice_param);
+    }
+}
+
+/// 计算固定阶预测残差
+fn fixed_residuals(block: &[i32], order: usize) -> Vec<i32> {
+    (order..block.len())
+        .map(|i| {
+            let s = |k: usize| block[i - k];
+            match order {
+                0 => s(0),
+                1 => s(0) - s(1),
+                2 => s(0) - 2 * s(1) + s(2),
+                3 => s(0) - 3 * s(1) + 3 * s(2) - s(3),
+                _ => s(0) - 4 * s(1) + 6 * s(2) - 4 * s(3) + s(4),
+            }
+        })
+        .collect()
+}
+
+/// 有符号残差折叠为无符号数（Rice编码要求）
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+/// FLAC帧头CRC-8（多项式0x07）
+fn flac_crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// FLAC帧CRC-16（多项式0x8005）
+fn flac_crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// 按大端位序写入的位流
+struct FlacBitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    acc_bits: u32,
+}
+
+impl FlacBitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            acc: 0,
+            acc_bits: 0,
+        }
+    }
+
+    /// 写入value的低bits位（bits不超过32）
+    fn write_bits(&mut self, value: u64, bits: u32) {
+        if bits > 32 {
+            self.write_bits(value >> 32, bits - 32);
+            self.write_bits(value & 0xFFFF_FFFF, 32);
+            return;
+        }
+        if bits == 0 {
+            return;
+        }
+        self.acc = (self.acc << bits) | (value & ((1u64 << bits) - 1));
+        self.acc_bits += bits;
+        while self.acc_bits >= 8 {
+            self.acc_bits -= 8;
+            self.bytes.push((self.acc >> self.acc_bits) as u8);
+        }
+        self.acc &= (1u64 << self.acc_bits) - 1;
+    }
+
+    fn write_signed(&mut self, value: i32, bits: u32) {
+        self.write_bits(value as u32 as u64, bits);
+    }
+
+    /// 一元编码：count个0后接一个1
+    fn write_unary(&mut self, mut count: u32) {
+        while count >= 32 {
+            self.write_bits(0, 32);
+            count -= 32;
+        }
+        self.write_bits(1, count + 1);
+    }
+
+    /// FLAC帧号使用的类UTF-8变长编码
+    fn write_utf8_number(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write_bits(value, 8);
+            return;
+        }
+        let mut continuation = Vec::new();
+        let mut rest = value;
+        let mut lead_capacity = 6u32;
+        while rest >= (1 << lead_capacity) {
+            continuation.push(0x80 | (rest & 0x3F));
+            rest >>= 6;
+            lead_capacity -= 1;
+        }
+        let total = continuation.len() as u32 + 1;
+        let prefix = (0xFFu64 << (8 - total)) & 0xFF;
+        self.write_bits(prefix | rest, 8);
+        for byte in continuation.into_iter().rev() {
+            self.write_bits(byte, 8);
+        }
+    }
+
+    fn align_to_byte(&mut self) {
+        if self.acc_bits > 0 {
+            self.write_bits(0, 8 - self.acc_bits);
+        }
+    }
+
+    /// 已写满的完整字节
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align_to_byte();
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_parsing() {
+        assert_eq!(AudioFormat::from_name("MP3"), Some(AudioFormat::Mp3));
+        assert_eq!(
+            AudioFormat::from_name("wav_f32"),
+            Some(AudioFormat::WavFloat)
+        );
+        assert_eq!(AudioFormat::from_name("ogg"), Some(AudioFormat::Opus));
+        assert_eq!(AudioFormat::from_name("aac"), None);
+        assert_eq!(
+            AudioFormat::from_mime("audio/x-flac"),
+            Some(AudioFormat::Flac)
+        );
+        assert_eq!(AudioFormat::Opus.content_type(), "audio/ogg");
+    }
+
+    #[test]
+    fn test_bitrate_validation() {
+        assert!(AudioFormat::Wav.validate_bitrate(None).is_ok());
+        assert!(AudioFormat::Flac.validate_bitrate(Some(128)).is_err());
+        assert!(AudioFormat::Mp3.validate_bitrate(Some(128)).is_ok());
+        assert!(AudioFormat::Mp3.validate_bitrate(Some(100)).is_err());
+        assert!(AudioFormat::Mp3.validate_bitrate(Some(512)).is_err());
+        assert!(AudioFormat::Opus.validate_bitrate(Some(6)).is_ok());
+        assert!(AudioFormat::Opus.validate_bitrate(Some(510)).is_ok());
+        assert!(AudioFormat::Opus.validate_bitrate(Some(5000)).is_err());
+
+        // 编码前同样校验，避免比特率换算溢出
+        let options = AudioEncodeOptions::new(AudioFormat::Opus, 16000).with_bitrate(Some(5000));
+        assert!(encode_audio(&[0.0; 320], &options).is_err());
+    }
+
+    #[test]
+    fn test_wav_encoding() {
+        let samples = vec![0.0f32, 0.5, -0.5, 1.0];
+        let options = AudioEncodeOptions::new(AudioFormat::Wav, 16000);
+        let wav = encode_audio(&samples, &options).unwrap();
+        assert_eq!(wav.len(), 44 + samples.len() * 2);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(wav[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 16000);
+
+        let options = AudioEncodeOptions::new(AudioFormat::WavFloat, 16000);
+        let wav = encode_audio(&samples, &options).unwrap();
+        assert_eq!(wav.len(), 44 + samples.len() * 4);
+        assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), 3);
+
+        // 关闭归一化时保留原始幅度
+        let options = AudioEncodeOptions::new(AudioFormat::WavFloat, 16000).without_normalization();
+        let wav = encode_audio(&[0.1, -0.2], &options).unwrap();
+        assert_eq!(f32::from_le_bytes(wav[44..48].try_into().unwrap()), 0.1);
+        assert_eq!(f32::from_le_bytes(wav[48..52].try_into().unwrap()), -0.2);
+    }
+
+    #[test]
+    fn test_save_audio_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let samples = vec![0.1f32, -0.2, 0.3];
+
+        // WAV按32位浮点保存且不归一化，其他扩展名按对应格式编码
+        let wav_path = temp_dir.path().join("out.wav");
+        save_audio_file(&samples, wav_path.to_str().unwrap(), 16000).unwrap();
+        let wav = std::fs::read(&wav_path).unwrap();
+        assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), 3);
+        assert_eq!(f32::from_le_bytes(wav[44..48].try_into().unwrap()), 0.1);
+
+        let flac_path = temp_dir.path().join("out.flac");
+        save_audio_file(&samples, flac_path.to_str().unwrap(), 16000).unwrap();
+        assert_eq!(&std::fs::read(&flac_path).unwrap()[0..4], b"fLaC");
+    }
+
+    #[test]
+    fn test_flac_encoding() {
+        let samples: Vec<f32> = (0..10000).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let options = AudioEncodeOptions::new(AudioFormat::Flac, 16000);
+        let flac = encode_audio(&samples, &options).unwrap();
+        assert_eq!(&flac[0..4], b"fLaC");
+        // 平滑信号经预测编码后应明显小于原始PCM
+        assert!(flac.len() < samples.len() * 2);
+        // 第一帧紧跟在STREAMINFO之后，以同步码开头
+        assert_eq!(flac[42], 0xFF);
+        assert_eq!(flac[43] & 0xFE, 0xF8);
+    }
+
+    #[test]
+    fn test_flac_round_trip() {
+        // 平滑信号、静音、削波和伪随机噪声，覆盖多种预测阶数与残差分布；
+        // 长度不是块大小的整数倍，最后一帧为非标准块大小
+        let mut seed = 12345u32;
+        let mut samples: Vec<f32> = (0..5000).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        samples.resize(samples.len() + 3000, 0.0);
+        samples.extend((0..200).map(|i| if i % 2 == 0 { 1.5 } else { -1.5 }));
+        samples.extend((0..4000).map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as f32 / 32768.0 - 1.0
+        }));
+
+        for sample_rate in [16000, 44100] {
+            let options = AudioEncodeOptions::new(AudioFormat::Flac, sample_rate);
+            let flac = encode_audio(&samples, &options).unwrap();
+
+            let mut reader = claxon::FlacReader::new(std::io::Cursor::new(flac)).unwrap();
+            let info = reader.streaminfo();
+            assert_eq!(info.sample_rate, sample_rate);
+            assert_eq!(info.channels, 1);
+            assert_eq!(info.bits_per_sample, 16);
+            assert_eq!(info.samples, Some(samples.len() as u64));
+
+            // 解码结果与编码前量化的16位样本逐一相同
+            let decoded: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+            let expected: Vec<i32> = normalize_samples(&samples)
+                .iter()
+                .map(|&s| sample_to_i16(s) as i32)
+                .collect();
+            assert_eq!(decoded, expected);
+        }
+
+        // 少于一个最小块的输入
+        let options = AudioEncodeOptions::new(AudioFormat::Flac, 16000);
+        let flac = encode_audio(&[0.1, -0.2, 0.3], &options).unwrap();
+        let mut reader = claxon::FlacReader::new(std::io::Cursor::new(flac)).unwrap();
+        let decoded: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(decoded.len(), 3);
+    }
+
+    #[test]
+    fn test_resample() {
+        let samples: Vec<f32> = (0..16000)
+            .map(|i| (i as f32 * 2.0 * std::f32::consts::PI * 440.0 / 16000.0).sin())
+            .collect();
+        assert_eq!(resample(&samples, 16000, 48000).len(), 48000);
+        assert_eq!(resample(&samples, 16000, 8000).len(), 8000);
+
+        // 分块处理与一次性处理结果一致
+        let mut resampler = Resampler::new(16000, 8000);
+        let mut streamed: Vec<f32> = samples
+            .chunks(1000)
+            .flat_map(|chunk| resampler.process(chunk))
+            .collect();
+        streamed.extend(resampler.flush());
+        assert_eq!(streamed, resample(&samples, 16000, 8000));
+
+        assert!(validate_output_sample_rate(8000).is_ok());
+        assert!(validate_output_sample_rate(96000).is_err());
+    }
+
+    #[test]
+    fn test_supported_sample_rates() {
+        assert!(AudioFormat::Wav.supports_sample_rate(44100));
+        assert!(!AudioFormat::Wav.supports_sample_rate(96000));
+        assert!(AudioFormat::Opus.supports_sample_rate(24000));
+        assert!(!AudioFormat::Opus.supports_sample_rate(44100));
+        assert!(AudioFormat::Mp3.supports_sample_rate(22050));
+        assert!(!AudioFormat::Mp3.supports_sample_rate(44000));
+        assert!(AudioFormat::Mulaw.supports_sample_rate(8000));
+        assert!(!AudioFormat::WavAlaw.supports_sample_rate(16000));
+    }
+
+    #[test]
+    fn test_g711_encoding() {
+        assert_eq!(linear_to_ulaw(0), 0xFF);
+        assert_eq!(linear_to_ulaw(1000), 0xCE);
+        assert_eq!(linear_to_ulaw(i16::MAX), 0x80);
+        assert_eq!(linear_to_ulaw(i16::MIN), 0x00);
+        assert_eq!(linear_to_alaw(0), 0xD5);
+        assert_eq!(linear_to_alaw(1000), 0xFA);
+        assert_eq!(linear_to_alaw(i16::MAX), 0xAA);
+        assert_eq!(linear_to_alaw(i16::MIN), 0x2A);
+
+        let samples = vec![0.0f32; 160];
+        let options = AudioEncodeOptions::new(AudioFormat::WavMulaw, 8000);
+        let wav = encode_audio(&samples, &options).unwrap();
+        assert_eq!(wav.len(), 58 + 160);
+        assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), 7);
+
+        let options = AudioEncodeOptions::new(AudioFormat::Alaw, 16000);
+        assert!(encode_audio(&samples, &options).is_err());
+    }
+
+    #[test]
+    fn test_flac_utf8_frame_number() {
+        let mut w = FlacBitWriter::new();
+        w.write_utf8_number(0x7F);
+        w.write_utf8_number(0x80);
+        w.write_utf8_number(0x800);
+        assert_eq!(w.into_bytes(), vec![0x7F, 0xC2, 0x80, 0xE0, 0xA0, 0x80]);
+    }
+}
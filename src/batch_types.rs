@@ -8,10 +8,13 @@
 
 use anyhow::Result;
 use flume::Sender;
-use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
 
-use crate::rwkv_sampler::{SamplerArgs, TtsBatchRequest};
+use crate::rwkv_sampler::SamplerArgs;
 
 /// 采样参数结构体，用于传递给sample_logits函数
 #[derive(Debug, Clone)]
@@ -33,6 +36,235 @@ pub struct SamplingArgsRef<'a> {
     pub sampler_args: SamplerArgs,
 }
 
+/// 推理过程中实时推送的token事件，用于流式输出和进度上报
+#[derive(Debug, Clone)]
+pub enum TtsTokenEvent {
+    /// 请求已进入批处理队列，position为入队时的队列深度加1（实际调度顺序还取决于优先级），
+    /// estimated_wait_ms为按近期平均推理耗时估算的开始推理前等待时间
+    Queued {
+        position: usize,
+        estimated_wait_ms: u64,
+    },
+    /// Prefill阶段完成
+    PrefillDone,
+    /// Global tokens已确定（普通模式生成完成或zero-shot模式直接使用预提取tokens）
+    GlobalTokens(Vec<i32>),
+    /// 新生成的一个semantic token
+    SemanticToken(i32),
+}
+
+/// 请求优先级，批处理收集线程优先调度高优先级请求
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPriority {
+    /// 交互式请求（如对话助手的实时回复），延迟敏感
+    Interactive,
+    /// 普通同步请求
+    #[default]
+    Normal,
+    /// 批量请求（如异步长文本任务），可以让路
+    Bulk,
+}
+
+impl RequestPriority {
+    /// 调度等级，数值越小越优先
+    pub fn rank(self) -> u8 {
+        match self {
+            RequestPriority::Interactive => 0,
+            RequestPriority::Normal => 1,
+            RequestPriority::Bulk => 2,
+        }
+    }
+
+    /// 名称（与serde一致）
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RequestPriority::Interactive => "interactive",
+            RequestPriority::Normal => "normal",
+            RequestPriority::Bulk => "bulk",
+        }
+    }
+}
+
+/// 请求终止原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    /// 客户端断开或显式取消
+    Cancelled,
+    /// 超过截止时间
+    DeadlineExceeded,
+}
+
+/// 请求被终止时返回的错误，可通过`anyhow::Error::downcast_ref`与其他推理错误区分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtsCancelled(pub CancelReason);
+
+impl std::fmt::Display for TtsCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            CancelReason::Cancelled => write!(f, "请求已取消"),
+            CancelReason::DeadlineExceeded => write!(f, "请求超过截止时间，已终止"),
+        }
+    }
+}
+
+impl std::error::Error for TtsCancelled {}
+
+/// 请求队列已满，新请求被拒绝
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull {
+    /// 当前排队的请求数
+    pub queue_depth: usize,
+    /// 队列容量
+    pub max_queue_depth: usize,
+    /// 预计队列让出空位所需的时间
+    pub estimated_wait: Duration,
+}
+
+impl QueueFull {
+    /// 建议客户端重试前等待的秒数（至少1秒）
+    pub fn retry_after_secs(&self) -> u64 {
+        self.estimated_wait.as_secs_f64().ceil().max(1.0) as u64
+    }
+}
+
+impl std::fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "请求队列已满（{}/{}），请在{}秒后重试",
+            self.queue_depth,
+            self.max_queue_depth,
+            self.retry_after_secs()
+        )
+    }
+}
+
+impl std::error::Error for QueueFull {}
+
+/// 请求取消令牌
+///
+/// 随请求传递到推理循环，每个解码步骤前检查一次，被取消或超过截止时间后立即退出并释放运行时。
+/// 克隆共享同一状态；`child`创建的子令牌在父令牌取消时一并取消，自身取消不影响父令牌。
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    deadline: Option<Instant>,
+    parent: Option<CancelToken>,
+}
+
+impl CancelToken {
+    /// 创建无截止时间的令牌
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建带截止时间的令牌
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            inner: Arc::new(CancelState {
+                deadline: Some(deadline),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 创建子令牌，父令牌取消或超过截止时间时子令牌同样视为终止
+    pub fn child(&self) -> Self {
+        Self {
+            inner: Arc::new(CancelState {
+                parent: Some(self.clone()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 创建带截止时间的子令牌，实际截止时间与父令牌取较早者
+    pub fn child_with_deadline(&self, deadline: Instant) -> Self {
+        Self {
+            inner: Arc::new(CancelState {
+                deadline: Some(deadline),
+                parent: Some(self.clone()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 截止时间（含父令牌）
+    pub fn deadline(&self) -> Option<Instant> {
+        let parent = self.inner.parent.as_ref().and_then(|p| p.deadline());
+        match (self.inner.deadline, parent) {
+            (Some(own), Some(parent)) => Some(own.min(parent)),
+            (own, parent) => own.or(parent),
+        }
+    }
+
+    /// 取消请求
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// 终止原因，未终止时为None
+    pub fn reason(&self) -> Option<CancelReason> {
+        if self.inner.cancelled.load(Ordering::SeqCst) {
+            return Some(CancelReason::Cancelled);
+        }
+        if let Some(reason) = self.inner.parent.as_ref().and_then(|p| p.reason()) {
+            return Some(reason);
+        }
+        match self.inner.deadline {
+            Some(deadline) if Instant::now() >= deadline => Some(CancelReason::DeadlineExceeded),
+            _ => None,
+        }
+    }
+
+    /// 是否已取消或超过截止时间
+    pub fn is_cancelled(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// 已终止时返回`TtsCancelled`错误
+    pub fn check(&self) -> Result<()> {
+        match self.reason() {
+            Some(reason) => Err(TtsCancelled(reason).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// 创建守卫，在被丢弃前未调用`disarm`时取消请求（用于等待方的future被提前丢弃的情况）
+    pub fn cancel_on_drop(&self) -> CancelOnDrop {
+        CancelOnDrop {
+            token: Some(self.clone()),
+        }
+    }
+}
+
+/// 丢弃时取消请求的守卫，见`CancelToken::cancel_on_drop`
+#[derive(Debug)]
+pub struct CancelOnDrop {
+    token: Option<CancelToken>,
+}
+
+impl CancelOnDrop {
+    /// 请求已正常结束，丢弃时不再取消
+    pub fn disarm(mut self) {
+        self.token = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = self.token.take() {
+            token.cancel();
+        }
+    }
+}
+
 /// TTS请求项，包含完整的请求信息和响应通道
 #[derive(Debug)]
 pub struct DynamicTtsRequest {
@@ -43,28 +275,19 @@ pub struct DynamicTtsRequest {
     pub voice_id: Option<String>, // 音色ID，用于从缓存获取tokens
     pub args: SamplerArgs,
     pub response_tx: oneshot::Sender<Result<(Vec<i32>, Vec<i32>)>>,
+    /// 可选的token事件通道，设置后推理过程中会实时推送生成的tokens
+    pub token_tx: Option<Sender<TtsTokenEvent>>,
+    /// 取消令牌，推理循环每步检查
+    pub cancel: CancelToken,
+    /// 调度优先级
+    pub priority: RequestPriority,
+    /// 请求自带的截止时间，同优先级内截止时间早的先调度
+    pub deadline: Option<Instant>,
     pub submitted_at: Instant,
-    pub batch_id: usize,
-}
-
-/// 推理批次
-#[derive(Debug)]
-pub enum InferBatch {
-    /// 执行推理
-    Run {
-        batch_id: usize,
-        requests: Vec<TtsBatchRequest>,
-        sender: Sender<Vec<(Vec<i32>, Vec<i32>)>>,
-    },
-    /// 获取结果
-    Result {
-        batch_id: usize,
-        sender: oneshot::Sender<Vec<(Vec<i32>, Vec<i32>)>>,
-    },
 }
 
 /// 动态批处理配置
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DynamicBatchConfig {
     /// 最小批处理大小
     pub min_batch_size: usize,
@@ -72,14 +295,16 @@ pub struct DynamicBatchConfig {
     pub max_batch_size: usize,
     /// 批处理收集超时时间（毫秒）
     pub collect_timeout_ms: u64,
-    /// 推理超时时间（毫秒）
+    /// 单个请求的推理超时时间（毫秒），从提交开始计时，包含排队时间
     pub inference_timeout_ms: u64,
-    /// 最大并发批次数
+    /// 最大并发批次数，同时也是模型状态槽位数（一次解码最多容纳的请求数）
     pub max_concurrent_batches: usize,
-    /// 信号量许可数量（基于硬件和负载调整）
-    pub semaphore_permits: usize,
     /// Prefill阶段每次送入的token块大小（提高吞吐，默认256）
     pub token_chunk_size: usize,
+    /// 等待收集的最大请求数，队列满时新请求立即被拒绝（0表示不限制）
+    pub max_queue_depth: usize,
+    /// 优先级老化间隔（毫秒）：请求每等待这么久提升一级优先级，避免低优先级请求饿死
+    pub priority_aging_ms: u64,
 }
 
 impl Default for DynamicBatchConfig {
@@ -90,8 +315,9 @@ impl Default for DynamicBatchConfig {
             collect_timeout_ms: 50,
             inference_timeout_ms: 60000,
             max_concurrent_batches: 4, // 合理的默认并发数
-            semaphore_permits: 3,      // 信号量许可数量略小于并发数
             token_chunk_size: 256,
+            max_queue_depth: 256,
+            priority_aging_ms: 5000,
         }
     }
 }
@@ -135,3 +361,55 @@ impl Default for TtsInferOptions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token_child_and_drop_guard() {
+        let parent = CancelToken::new();
+        let child = parent.child();
+
+        // 子令牌取消不影响父令牌和兄弟令牌
+        let sibling = parent.child();
+        drop(sibling.cancel_on_drop());
+        assert!(sibling.is_cancelled());
+        assert!(!parent.is_cancelled());
+        assert!(child.check().is_ok());
+
+        // 解除的守卫不会取消
+        child.cancel_on_drop().disarm();
+        assert!(!child.is_cancelled());
+
+        parent.cancel();
+        let error = child.check().unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TtsCancelled>(),
+            Some(&TtsCancelled(CancelReason::Cancelled))
+        );
+    }
+
+    #[test]
+    fn test_cancel_token_deadline() {
+        let now = Instant::now();
+        let token = CancelToken::with_deadline(now);
+        assert_eq!(token.reason(), Some(CancelReason::DeadlineExceeded));
+
+        let later = CancelToken::with_deadline(now + Duration::from_secs(60));
+        let child = later.child();
+        assert_eq!(child.deadline(), Some(now + Duration::from_secs(60)));
+        assert!(!child.is_cancelled());
+
+        // 子令牌的截止时间与父令牌取较早者
+        let sooner = later.child_with_deadline(now + Duration::from_secs(1));
+        assert_eq!(sooner.deadline(), Some(now + Duration::from_secs(1)));
+        let capped = later.child_with_deadline(now + Duration::from_secs(600));
+        assert_eq!(capped.deadline(), Some(now + Duration::from_secs(60)));
+        assert_eq!(
+            later.child_with_deadline(now).reason(),
+            Some(CancelReason::DeadlineExceeded)
+        );
+        assert!(CancelToken::new().deadline().is_none());
+    }
+}
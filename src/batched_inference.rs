@@ -0,0 +1,702 @@
+//! 多槽位批量解码
+//!
+//! `v7::Bundle`按`max_concurrent_batches`分配了多个状态槽位。正在解码的请求各占一个槽位，
+//! 拼成同一个`RnnInput`一起解码：每次`runtime.infer`为所有还有待处理token的槽位各推进一步。
+//! 每个槽位独立维护采样RNG和解码阶段（Prefill → Global → Semantic），互不影响；
+//! 请求结束或取消后立即释放槽位，下一步即可接纳新请求。
+
+use anyhow::{anyhow, Result};
+use rand::rngs::StdRng;
+use std::future::Future;
+use std::time::Instant;
+use tracing::{info, warn};
+use web_rwkv::runtime::infer::{RnnInput, RnnInputBatch, RnnOption};
+use web_rwkv::runtime::model::{Bundle, State};
+
+use crate::batch_types::TtsTokenEvent;
+use crate::metrics::{global_metrics, InferenceStage};
+use crate::rwkv_sampler::{
+    sample_logits, sample_logits_with_top_p_k, SamplerArgs, TtsBatchRequest, GLOBAL_TOKEN_OFFSET,
+    TTS_EOS_TOKEN, TTS_TAG_0, TTS_TAG_1, TTS_TAG_2,
+};
+use crate::shared_runtime::SharedRwkvRuntime;
+
+/// 每个请求生成的global token数
+pub const GLOBAL_TOKEN_COUNT: usize = 32;
+
+/// Global token取值范围[0..4096)
+const GLOBAL_VOCAB_SIZE: usize = 4096;
+
+/// Semantic阶段最大生成步数
+const MAX_SEMANTIC_TOKENS: usize = 2048;
+
+/// Global阶段采样参数（temperature, top_p, top_k），与Python版本一致
+const GLOBAL_SAMPLING: (f32, f32, usize) = (1.0, 0.95, 20);
+
+/// Semantic阶段采样参数（temperature, top_p, top_k），与Python版本一致
+const SEMANTIC_SAMPLING: (f32, f32, usize) = (1.0, 0.95, 80);
+
+/// 单个请求的生成结果：(global_tokens, semantic_tokens)
+pub type SlotOutput = (Vec<i32>, Vec<i32>);
+
+/// 槽位解码阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlotPhase {
+    Prefill,
+    Global,
+    Semantic,
+    Done,
+}
+
+/// 占用一个状态槽位的请求解码状态
+pub struct TtsSlot {
+    request_id: String,
+    request: TtsBatchRequest,
+    /// Prefill阶段输入
+    prefill_tokens: Vec<u32>,
+    /// Zero-shot模式下预提取的global tokens，Prefill后直接回灌，跳过Global阶段
+    reference_globals: Option<Vec<i32>>,
+    phase: SlotPhase,
+    global_tokens: Vec<i32>,
+    semantic_tokens: Vec<i32>,
+    global_rng: Option<StdRng>,
+    semantic_rng: Option<StdRng>,
+    /// Semantic阶段采样参数，未设置时使用`SEMANTIC_SAMPLING`
+    semantic_args: Option<SamplerArgs>,
+    semantic_limit: usize,
+    /// 当前阶段开始时间，用于记录阶段耗时
+    stage_start: Instant,
+}
+
+impl TtsSlot {
+    /// 创建槽位，Global tokens由模型生成
+    pub fn new(
+        request_id: String,
+        request: TtsBatchRequest,
+        prefill_tokens: Vec<i32>,
+        semantic_rng: StdRng,
+    ) -> Self {
+        let semantic_limit = request.args.max_tokens.min(MAX_SEMANTIC_TOKENS);
+        Self {
+            request_id,
+            request,
+            prefill_tokens: prefill_tokens.into_iter().map(|t| t as u32).collect(),
+            reference_globals: None,
+            phase: SlotPhase::Prefill,
+            global_tokens: Vec::with_capacity(GLOBAL_TOKEN_COUNT),
+            semantic_tokens: Vec::new(),
+            global_rng: None,
+            semantic_rng: Some(semantic_rng),
+            semantic_args: None,
+            semantic_limit,
+            stage_start: Instant::now(),
+        }
+    }
+
+    /// 设置Global阶段的RNG
+    pub fn with_global_rng(mut self, rng: StdRng) -> Self {
+        self.global_rng = Some(rng);
+        self
+    }
+
+    /// 使用预提取的global tokens（Zero-shot模式）
+    pub fn with_reference_globals(mut self, tokens: Vec<i32>) -> Self {
+        self.reference_globals = Some(tokens);
+        self
+    }
+
+    /// 指定Semantic阶段的采样参数，生成上限取`args.max_tokens`（不超过2048）
+    pub fn with_semantic_args(mut self, args: SamplerArgs) -> Self {
+        self.semantic_limit = args.max_tokens.min(MAX_SEMANTIC_TOKENS);
+        self.semantic_args = Some(args);
+        self
+    }
+
+    /// 请求ID
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// 是否已结束解码
+    pub fn is_done(&self) -> bool {
+        self.phase == SlotPhase::Done
+    }
+
+    /// 检查请求是否已取消或超时，是则结束解码
+    pub fn check_cancelled(&mut self) -> Result<()> {
+        let result = self.request.cancel.check();
+        if result.is_err() {
+            self.phase = SlotPhase::Done;
+        }
+        result
+    }
+
+    /// 取出生成结果
+    pub fn take_output(&mut self) -> SlotOutput {
+        (
+            std::mem::take(&mut self.global_tokens),
+            std::mem::take(&mut self.semantic_tokens),
+        )
+    }
+
+    /// 处理本槽位的输出logits，返回下一步需要送入模型的tokens；返回空表示解码结束
+    pub fn advance(&mut self, logits: &[f32]) -> Vec<u32> {
+        match self.phase {
+            SlotPhase::Prefill => self.finish_prefill(logits),
+            SlotPhase::Global => self.sample_global(logits),
+            SlotPhase::Semantic => self.sample_semantic(logits),
+            SlotPhase::Done => Vec::new(),
+        }
+    }
+
+    fn finish_prefill(&mut self, logits: &[f32]) -> Vec<u32> {
+        global_metrics().observe_stage(InferenceStage::Prefill, self.stage_start.elapsed());
+        self.send_event(TtsTokenEvent::PrefillDone);
+
+        // 根据logits长度推断词表大小，校验属性token是否越界
+        let out_of_range: Vec<i32> = self
+            .request
+            .property_tokens
+            .iter()
+            .copied()
+            .filter(|&t| t as usize >= logits.len())
+            .collect();
+        if !out_of_range.is_empty() {
+            warn!(
+                "🚨 [{}] 属性tokens超出词表范围，可能被模型忽略：越界token={:?}，词表大小={}",
+                self.request_id,
+                out_of_range,
+                logits.len()
+            );
+        }
+
+        match self.reference_globals.take() {
+            Some(globals) => {
+                // Zero-shot：回灌预提取的global tokens（不加偏移）后直接进入Semantic阶段
+                let mut feed: Vec<u32> = globals.iter().map(|&t| t as u32).collect();
+                feed.push(TTS_TAG_1 as u32);
+                self.global_tokens = globals;
+                self.send_event(TtsTokenEvent::GlobalTokens(self.global_tokens.clone()));
+                self.enter_semantic();
+                feed
+            }
+            None => {
+                self.phase = SlotPhase::Global;
+                self.stage_start = Instant::now();
+                self.sample_global(logits)
+            }
+        }
+    }
+
+    fn sample_global(&mut self, logits: &[f32]) -> Vec<u32> {
+        let (temperature, top_p, top_k) = GLOBAL_SAMPLING;
+        let vocab = logits.len().min(GLOBAL_VOCAB_SIZE);
+        let next_id = sample_logits_with_top_p_k(
+            &logits[..vocab],
+            temperature,
+            top_p,
+            top_k,
+            None,
+            &mut self.global_rng,
+        ) as i32;
+        self.global_tokens.push(next_id);
+
+        // 回灌到模型：加上GLOBAL_TOKEN_OFFSET以进入Global域
+        let mut feed = vec![(next_id + GLOBAL_TOKEN_OFFSET) as u32];
+        if self.global_tokens.len() == GLOBAL_TOKEN_COUNT {
+            global_metrics().observe_stage(InferenceStage::Global, self.stage_start.elapsed());
+            info!(
+                "🎯 [{}] Global阶段生成前8个token: {:?}",
+                self.request_id,
+                &self.global_tokens[..8]
+            );
+            self.send_event(TtsTokenEvent::GlobalTokens(self.global_tokens.clone()));
+            feed.push(TTS_TAG_1 as u32);
+            self.enter_semantic();
+        }
+        feed
+    }
+
+    fn sample_semantic(&mut self, logits: &[f32]) -> Vec<u32> {
+        let masked = mask_semantic_logits(logits);
+        let next_id = match &self.semantic_args {
+            Some(args) => sample_logits(&masked, args, None, &mut self.semantic_rng),
+            None => {
+                let (temperature, top_p, top_k) = SEMANTIC_SAMPLING;
+                sample_logits_with_top_p_k(
+                    &masked,
+                    temperature,
+                    top_p,
+                    top_k,
+                    None,
+                    &mut self.semantic_rng,
+                )
+            }
+        };
+
+        // 采样到EOS（或越界token）时结束
+        if next_id >= TTS_EOS_TOKEN as usize {
+            self.finish();
+            return Vec::new();
+        }
+
+        let next_id = next_id as i32;
+        self.semantic_tokens.push(next_id);
+        self.send_event(TtsTokenEvent::SemanticToken(next_id));
+        if self.semantic_tokens.len() >= self.semantic_limit {
+            self.finish();
+            return Vec::new();
+        }
+        // 反馈到模型：直接使用原始ID
+        vec![next_id as u32]
+    }
+
+    fn enter_semantic(&mut self) {
+        self.phase = SlotPhase::Semantic;
+        self.stage_start = Instant::now();
+    }
+
+    fn finish(&mut self) {
+        self.phase = SlotPhase::Done;
+        global_metrics().observe_stage(InferenceStage::Semantic, self.stage_start.elapsed());
+        if self.semantic_tokens.is_empty() {
+            warn!(
+                "⚠️ [{}] Semantic阶段未生成任何token（可能过早采样到EOS或输入序列构建异常）",
+                self.request_id
+            );
+        } else {
+            let head = self.semantic_tokens.len().min(12);
+            info!(
+                "🗣️ [{}] Semantic阶段生成前{}个token: {:?}",
+                self.request_id,
+                head,
+                &self.semantic_tokens[..head]
+            );
+        }
+    }
+
+    fn send_event(&self, event: TtsTokenEvent) {
+        if let Some(token_tx) = &self.request.token_tx {
+            let _ = token_tx.send(event);
+        }
+    }
+}
+
+/// Semantic阶段只在[0..=EOS]范围内采样，屏蔽TTS_TAG_*与其它域
+fn mask_semantic_logits(logits: &[f32]) -> Vec<f32> {
+    let mut masked = logits.to_vec();
+    for (index, value) in masked.iter_mut().enumerate() {
+        if index > TTS_EOS_TOKEN as usize {
+            *value = f32::NEG_INFINITY;
+        }
+    }
+    for tag in [TTS_TAG_0, TTS_TAG_1, TTS_TAG_2] {
+        if let Some(value) = masked.get_mut(tag as usize) {
+            *value = f32::NEG_INFINITY;
+        }
+    }
+    masked
+}
+
+/// 槽位解码后端：加载槽位初始状态并执行一步推理
+///
+/// 生产环境由`SharedRwkvRuntime`实现，单元测试中用桩实现代替模型。
+pub trait SlotBackend {
+    /// 为槽位`index`加载初始状态
+    fn reset_slot(&self, index: usize) -> Result<()>;
+
+    /// 执行一步推理，返回剩余输入与每个槽位的logits（本步没有输出的槽位为空）
+    fn infer(
+        &self,
+        input: RnnInput,
+    ) -> impl Future<Output = Result<(RnnInput, Vec<Vec<f32>>)>> + Send;
+}
+
+impl SlotBackend for SharedRwkvRuntime {
+    fn reset_slot(&self, index: usize) -> Result<()> {
+        let state = self.model_bundle().state();
+        state.load(state.init(), index)?;
+        Ok(())
+    }
+
+    async fn infer(&self, input: RnnInput) -> Result<(RnnInput, Vec<Vec<f32>>)> {
+        let (next, output) = self.runtime().infer(input).await?;
+        let logits = output
+            .iter()
+            .map(|batch| match batch.0.size() {
+                0 => Vec::new(),
+                _ => batch.0.clone().to_vec(),
+            })
+            .collect();
+        Ok((next, logits))
+    }
+}
+
+fn empty_batch() -> RnnInputBatch {
+    RnnInputBatch::new(Vec::new(), RnnOption::Last)
+}
+
+/// 状态槽位表，实现迭代级（continuous）批处理
+///
+/// 与ai00-core的`run.rs`相同：新请求随时加入空闲槽位，请求结束或取消后立即离开，
+/// 每次`step`只为仍在解码的槽位推进一步，长短不一的请求不必互相等待。
+/// `T`为调用方随请求携带的数据（如结果通道），请求结束时一并返回。
+pub struct SlotBatch<T> {
+    slots: Vec<Option<(TtsSlot, T)>>,
+    inference: RnnInput,
+}
+
+impl<T> SlotBatch<T> {
+    /// 创建槽位表，`num_slots`须与模型分配的状态槽位数一致
+    pub fn new(num_slots: usize, token_chunk_size: usize) -> Self {
+        Self {
+            slots: (0..num_slots).map(|_| None).collect(),
+            inference: RnnInput::new(vec![empty_batch(); num_slots], token_chunk_size),
+        }
+    }
+
+    /// 正在解码的请求数
+    pub fn active(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// 空闲槽位数
+    pub fn free_slots(&self) -> usize {
+        self.slots.len() - self.active()
+    }
+
+    /// 是否没有正在解码的请求
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    /// 将请求放入空闲槽位并加载初始状态，返回槽位编号；失败时交还调用方数据
+    pub fn insert(
+        &mut self,
+        backend: &impl SlotBackend,
+        slot: TtsSlot,
+        data: T,
+    ) -> std::result::Result<usize, (T, anyhow::Error)> {
+        let Some(index) = self.slots.iter().position(Option::is_none) else {
+            return Err((data, anyhow!("没有空闲的状态槽位")));
+        };
+        if let Err(e) = backend.reset_slot(index) {
+            return Err((data, anyhow!("加载槽位 {} 初始状态失败: {}", index, e)));
+        }
+        self.inference.batches[index] =
+            RnnInputBatch::new(slot.prefill_tokens.clone(), RnnOption::Last);
+        self.slots[index] = Some((slot, data));
+        Ok(index)
+    }
+
+    /// 执行一个解码步骤，返回本步结束（完成、取消或出错）的请求
+    ///
+    /// 推理出错时所有正在解码的请求都以错误结束。
+    pub async fn step(&mut self, backend: &impl SlotBackend) -> Vec<(T, Result<SlotOutput>)> {
+        let mut finished = Vec::new();
+
+        // 已取消或超时的请求立即释放槽位
+        for index in 0..self.slots.len() {
+            let cancelled = match &mut self.slots[index] {
+                Some((slot, _)) => slot.check_cancelled().err(),
+                None => None,
+            };
+            if let Some(e) = cancelled {
+                if let Some((slot, data)) = self.release(index) {
+                    info!("请求 {} 已终止，释放槽位 {}", slot.request_id, index);
+                    finished.push((data, Err(e)));
+                }
+            }
+        }
+        if self.is_empty() {
+            return finished;
+        }
+
+        let output = match backend.infer(self.inference.clone()).await {
+            Ok((next, output)) => {
+                self.inference = next;
+                output
+            }
+            Err(e) => {
+                let message = e.to_string();
+                for index in 0..self.slots.len() {
+                    if let Some((_, data)) = self.release(index) {
+                        finished.push((data, Err(anyhow!("批量推理失败: {}", message))));
+                    }
+                }
+                return finished;
+            }
+        };
+
+        for (index, logits) in output.iter().enumerate() {
+            let Some((slot, _)) = &mut self.slots[index] else {
+                continue;
+            };
+            if logits.is_empty() {
+                continue;
+            }
+            for token in slot.advance(logits) {
+                self.inference.batches[index].push(token);
+            }
+            if slot.is_done() {
+                if let Some((mut slot, data)) = self.release(index) {
+                    finished.push((data, Ok(slot.take_output())));
+                }
+            }
+        }
+        finished
+    }
+
+    /// 释放槽位并清空其待处理输入
+    fn release(&mut self, index: usize) -> Option<(TtsSlot, T)> {
+        self.inference.batches[index] = empty_batch();
+        self.slots[index].take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch_types::{CancelToken, RequestPriority};
+    use rand::SeedableRng;
+    use std::sync::Mutex;
+
+    /// 词表大小：覆盖semantic、TAG与global域
+    const VOCAB: usize = GLOBAL_TOKEN_OFFSET as usize + GLOBAL_VOCAB_SIZE;
+
+    /// 只在`token`处有显著峰值的logits，采样结果与RNG无关
+    fn peaked(token: i32) -> Vec<f32> {
+        let mut logits = vec![0.0; VOCAB];
+        logits[token as usize] = 100.0;
+        logits
+    }
+
+    fn request(max_tokens: usize) -> (TtsBatchRequest, flume::Receiver<TtsTokenEvent>) {
+        let (token_tx, token_rx) = flume::unbounded();
+        let request = TtsBatchRequest {
+            text: String::new(),
+            property_tokens: vec![1, 2],
+            ref_global_tokens: None,
+            ref_semantic_tokens: None,
+            args: SamplerArgs {
+                max_tokens,
+                ..Default::default()
+            },
+            voice_id: None,
+            token_tx: Some(token_tx),
+            cancel: CancelToken::new(),
+            priority: RequestPriority::Normal,
+        };
+        (request, token_rx)
+    }
+
+    fn slot(request_id: &str, request: TtsBatchRequest) -> TtsSlot {
+        TtsSlot::new(
+            request_id.to_string(),
+            request,
+            vec![1, 2, TTS_TAG_2, 7, TTS_TAG_0],
+            StdRng::seed_from_u64(0),
+        )
+        .with_global_rng(StdRng::seed_from_u64(1))
+    }
+
+    /// 桩后端：每个槽位生成`semantic_len`个semantic token后输出EOS
+    struct MockBackend {
+        semantic_len: Mutex<Vec<usize>>,
+        /// 每个槽位自加载初始状态以来的输出次数
+        outputs: Mutex<Vec<usize>>,
+        resets: Mutex<Vec<usize>>,
+    }
+
+    impl MockBackend {
+        fn new(num_slots: usize) -> Self {
+            Self {
+                semantic_len: Mutex::new(vec![0; num_slots]),
+                outputs: Mutex::new(vec![0; num_slots]),
+                resets: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn outputs(&self, index: usize) -> usize {
+            self.outputs.lock().unwrap()[index]
+        }
+    }
+
+    impl SlotBackend for MockBackend {
+        fn reset_slot(&self, index: usize) -> Result<()> {
+            self.outputs.lock().unwrap()[index] = 0;
+            self.resets.lock().unwrap().push(index);
+            Ok(())
+        }
+
+        fn infer(
+            &self,
+            mut input: RnnInput,
+        ) -> impl Future<Output = Result<(RnnInput, Vec<Vec<f32>>)>> + Send {
+            let semantic_len = self.semantic_len.lock().unwrap().clone();
+            let mut outputs = self.outputs.lock().unwrap();
+            let logits = input
+                .batches
+                .iter_mut()
+                .enumerate()
+                .map(|(index, batch)| {
+                    if batch.tokens.is_empty() {
+                        return Vec::new();
+                    }
+                    batch.tokens.clear();
+                    let count = outputs[index];
+                    outputs[index] += 1;
+                    // 第0次输出来自prefill，第1..32次之后进入semantic阶段
+                    match count {
+                        _ if count < GLOBAL_TOKEN_COUNT => peaked(5),
+                        _ if count - GLOBAL_TOKEN_COUNT < semantic_len[index] => peaked(42),
+                        _ => peaked(TTS_EOS_TOKEN),
+                    }
+                })
+                .collect();
+            async move { Ok((input, logits)) }
+        }
+    }
+
+    #[test]
+    fn test_slot_phases() {
+        let (request, token_rx) = request(2048);
+        let mut slot = slot("req_1", request);
+
+        // Prefill输出采样出第一个global token，回灌时加上偏移
+        assert_eq!(
+            slot.advance(&peaked(5)),
+            vec![(5 + GLOBAL_TOKEN_OFFSET) as u32]
+        );
+        for _ in 1..GLOBAL_TOKEN_COUNT - 1 {
+            assert_eq!(
+                slot.advance(&peaked(5)),
+                vec![(5 + GLOBAL_TOKEN_OFFSET) as u32]
+            );
+        }
+        // 第32个global token之后紧跟TTS_TAG_1
+        assert_eq!(
+            slot.advance(&peaked(5)),
+            vec![(5 + GLOBAL_TOKEN_OFFSET) as u32, TTS_TAG_1 as u32]
+        );
+
+        // Semantic阶段直接回灌原始ID，采样到EOS时结束
+        assert_eq!(slot.advance(&peaked(42)), vec![42]);
+        assert_eq!(slot.advance(&peaked(42)), vec![42]);
+        assert!(!slot.is_done());
+        assert!(slot.advance(&peaked(TTS_EOS_TOKEN)).is_empty());
+        assert!(slot.is_done());
+        assert!(slot.advance(&peaked(42)).is_empty());
+
+        let (globals, semantic) = slot.take_output();
+        assert_eq!(globals, vec![5; GLOBAL_TOKEN_COUNT]);
+        assert_eq!(semantic, vec![42, 42]);
+
+        let events: Vec<TtsTokenEvent> = token_rx.try_iter().collect();
+        assert!(matches!(events[0], TtsTokenEvent::PrefillDone));
+        assert!(matches!(&events[1], TtsTokenEvent::GlobalTokens(tokens) if tokens.len() == 32));
+        assert!(matches!(events[2], TtsTokenEvent::SemanticToken(42)));
+        assert_eq!(events.len(), 4);
+    }
+
+    #[test]
+    fn test_slot_reference_globals_and_limit() {
+        let (request, _token_rx) = request(2);
+        let mut slot = slot("req_2", request).with_reference_globals(vec![9; GLOBAL_TOKEN_COUNT]);
+
+        // Zero-shot：prefill后直接回灌预提取的global tokens并进入semantic阶段
+        let mut expected = vec![9; GLOBAL_TOKEN_COUNT];
+        expected.push(TTS_TAG_1 as u32);
+        assert_eq!(slot.advance(&peaked(5)), expected);
+
+        // 达到max_tokens即结束
+        assert_eq!(slot.advance(&peaked(42)), vec![42]);
+        assert!(slot.advance(&peaked(42)).is_empty());
+        assert!(slot.is_done());
+        assert_eq!(
+            slot.take_output(),
+            (vec![9; GLOBAL_TOKEN_COUNT], vec![42, 42])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slot_batch_reuse_and_cancel() {
+        let backend = MockBackend::new(2);
+        let mut batch: SlotBatch<&str> = SlotBatch::new(2, 64);
+        *backend.semantic_len.lock().unwrap() = vec![2, 10];
+
+        let (request_a, _rx_a) = request(2048);
+        let (request_b, _rx_b) = request(2048);
+        let cancel_b = request_b.cancel.clone();
+        assert_eq!(
+            batch.insert(&backend, slot("a", request_a), "a").ok(),
+            Some(0)
+        );
+        assert_eq!(
+            batch.insert(&backend, slot("b", request_b), "b").ok(),
+            Some(1)
+        );
+        assert_eq!(batch.free_slots(), 0);
+
+        // 槽位已满时交还调用方数据
+        let (request_c, _rx_c) = request(2048);
+        let Err((data, _)) = batch.insert(&backend, slot("c", request_c.clone()), "c") else {
+            panic!("没有空闲槽位时插入应失败");
+        };
+        assert_eq!(data, "c");
+
+        // a先结束，b继续解码
+        let mut finished = Vec::new();
+        while finished.is_empty() {
+            finished = batch.step(&backend).await;
+        }
+        assert_eq!(finished.len(), 1);
+        let (data, result) = finished.pop().unwrap();
+        assert_eq!(data, "a");
+        let (globals, semantic) = result.unwrap();
+        assert_eq!(globals.len(), GLOBAL_TOKEN_COUNT);
+        assert_eq!(semantic, vec![42, 42]);
+        assert_eq!(batch.active(), 1);
+
+        // 空出的槽位0接纳新请求
+        backend.semantic_len.lock().unwrap()[0] = 3;
+        assert_eq!(
+            batch.insert(&backend, slot("c", request_c), "c").ok(),
+            Some(0)
+        );
+        assert_eq!(*backend.resets.lock().unwrap(), vec![0, 1, 0]);
+        assert!(batch.step(&backend).await.is_empty());
+
+        // b在解码中途取消：立即释放，c照常推进
+        cancel_b.cancel();
+        let outputs_c = backend.outputs(0);
+        let finished = batch.step(&backend).await;
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].0, "b");
+        assert!(finished[0].1.is_err());
+        assert_eq!(backend.outputs(0), outputs_c + 1);
+        assert_eq!(batch.free_slots(), 1);
+
+        let mut finished = Vec::new();
+        while !batch.is_empty() {
+            finished.extend(batch.step(&backend).await);
+        }
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].0, "c");
+        assert_eq!(finished[0].1.as_ref().unwrap().1, vec![42, 42, 42]);
+    }
+
+    #[test]
+    fn test_mask_semantic_logits() {
+        let logits = vec![0.5; TTS_TAG_2 as usize + 4];
+        let masked = mask_semantic_logits(&logits);
+        assert_eq!(masked[0], 0.5);
+        assert_eq!(masked[TTS_EOS_TOKEN as usize], 0.5);
+        assert!(masked[TTS_EOS_TOKEN as usize + 1..]
+            .iter()
+            .all(|&value| value == f32::NEG_INFINITY));
+
+        // 词表小于TAG位置时不越界
+        assert_eq!(mask_semantic_logits(&[1.0, 2.0]), vec![1.0, 2.0]);
+    }
+}
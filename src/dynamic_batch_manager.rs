@@ -1,21 +1,158 @@
 use anyhow::Result;
 use flume::{Receiver, Sender};
 
-use rand::SeedableRng;
-use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::oneshot;
-use tracing::{error, warn};
+use tokio::sync::{oneshot, watch};
+use tracing::{error, info, warn};
 
 // 导入拆分的模块
 use crate::batch_types::*;
 
+use crate::request_scheduler::RequestScheduler;
 use crate::shared_runtime::*;
 
-// 重新导入推理函数以便使用优化组件
-use crate::normal_mode_inference::execute_normal_inference;
-use crate::zero_shot_inference::execute_zero_shot_inference;
+// 各模式的槽位构建函数
+use crate::batched_inference::{SlotBatch, TtsSlot};
+use crate::normal_mode_inference::build_normal_slot;
+use crate::zero_shot_inference::build_zero_shot_slot;
+
+/// 在途请求跟踪与关闭控制
+///
+/// 关闭后拒绝新请求；宽限期内等待在途请求完成，超时后让仍在等待结果的调用方立即返回错误。
+struct RequestGate {
+    /// 已提交、尚未拿到结果的请求数（含排队中）
+    in_flight: Arc<AtomicUsize>,
+    /// 是否已开始关闭
+    closed: AtomicBool,
+    /// 宽限期结束信号
+    abort_tx: watch::Sender<bool>,
+}
+
+/// 在途请求计数守卫，离开作用域时计数减一
+struct InFlightGuard {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl RequestGate {
+    fn new() -> Self {
+        Self {
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            closed: AtomicBool::new(false),
+            abort_tx: watch::channel(false).0,
+        }
+    }
+
+    /// 登记新请求，已关闭时返回错误
+    fn enter(&self) -> Result<InFlightGuard> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(anyhow::anyhow!("服务器正在关闭，不再接受新的合成请求"));
+        }
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Ok(InFlightGuard {
+            in_flight: self.in_flight.clone(),
+        })
+    }
+
+    /// 等待请求结果，宽限期结束时返回错误
+    async fn wait<T>(&self, response_rx: oneshot::Receiver<Result<T>>) -> Result<T> {
+        let mut abort_rx = self.abort_tx.subscribe();
+        tokio::select! {
+            response = response_rx => {
+                response.map_err(|e| anyhow::anyhow!("接收响应失败: {}", e))?
+            }
+            Ok(_) = abort_rx.wait_for(|aborted| *aborted) => {
+                Err(anyhow::anyhow!("服务器关闭，请求未能在宽限期内完成，已终止"))
+            }
+        }
+    }
+
+    /// 停止接受新请求并等待在途请求完成，返回宽限期结束时被终止的请求数
+    async fn close(&self, grace: Duration) -> usize {
+        self.closed.store(true, Ordering::SeqCst);
+        let deadline = Instant::now() + grace;
+        while self.in_flight() > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let remaining = self.in_flight();
+        if remaining > 0 {
+            self.abort_tx.send_replace(true);
+        }
+        remaining
+    }
+
+    fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+}
+
+/// 单个请求推理耗时估计（指数移动平均），用于估算排队等待时间
+struct ServiceTimeEstimator {
+    /// 平均耗时（毫秒），0表示尚无样本
+    average_ms: AtomicU64,
+}
+
+impl ServiceTimeEstimator {
+    /// 尚无样本时假定的单个请求耗时
+    const INITIAL_ESTIMATE: Duration = Duration::from_secs(1);
+
+    fn new() -> Self {
+        Self {
+            average_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录一个请求的推理耗时，新样本权重为1/8
+    fn record(&self, elapsed: Duration) {
+        let sample = (elapsed.as_millis() as u64).max(1);
+        let _ = self
+            .average_ms
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| {
+                Some(if average == 0 {
+                    sample
+                } else {
+                    (average * 7 + sample) / 8
+                })
+            });
+    }
+
+    /// 估算依次处理`requests`个请求所需的时间
+    fn estimate(&self, requests: usize) -> Duration {
+        let average = match self.average_ms.load(Ordering::Relaxed) {
+            0 => Self::INITIAL_ESTIMATE,
+            ms => Duration::from_millis(ms),
+        };
+        average.saturating_mul(requests as u32)
+    }
+
+    /// 估算前面还有`ahead`个请求（含正在解码的）时新请求开始解码前的等待时间
+    ///
+    /// `slots`个槽位并行解码：有空闲槽位时无需等待，否则每轮约等待一个请求的耗时。
+    fn estimate_wait(&self, ahead: usize, slots: usize) -> Duration {
+        let slots = slots.max(1);
+        self.estimate((ahead + 1).saturating_sub(slots).div_ceil(slots))
+    }
+}
+
+/// 正在槽位中解码的请求
+struct ActiveRequest {
+    request_id: String,
+    state_id: TtsStateId,
+    response_tx: oneshot::Sender<Result<(Vec<i32>, Vec<i32>)>>,
+    started_at: Instant,
+}
 
 /// 动态批处理管理器
 /// 负责收集请求、组织批次、协调推理工作线程
@@ -24,8 +161,18 @@ pub struct DynamicBatchManager {
     config: DynamicBatchConfig,
     /// 请求发送通道
     request_tx: Sender<DynamicTtsRequest>,
+    /// 排队中（尚未进入槽位）的请求数，包括通道中和调度队列中的请求
+    queued: Arc<AtomicUsize>,
+    /// 正在槽位中解码的请求数
+    active: Arc<AtomicUsize>,
+    /// 在途请求跟踪（用于优雅关闭）
+    gate: RequestGate,
+    /// 推理耗时估计
+    service_time: Arc<ServiceTimeEstimator>,
+    /// 因队列已满被拒绝的请求数
+    rejected: AtomicU64,
     /// 共享运行时
-    _shared_runtime: Arc<SharedRwkvRuntime>,
+    shared_runtime: Arc<SharedRwkvRuntime>,
 }
 
 impl DynamicBatchManager {
@@ -50,39 +197,33 @@ impl DynamicBatchManager {
             .await?,
         );
 
-        // 创建请求通道
+        // 创建请求通道：解码循环会把请求移入调度队列，排队上限由queued计数控制
         let (request_tx, request_rx) = flume::unbounded();
-        let (infer_tx, infer_rx) = flume::unbounded();
-
-        // 启动核心运行时
-        let shared_runtime_clone = shared_runtime.clone();
-        let config_clone = config.clone();
-        tokio::spawn(async move {
-            Self::run_core_runtime(shared_runtime_clone, request_rx, infer_tx, config_clone).await;
-        });
-
-        // 启动推理工作线程
-        for worker_id in 0..config.max_concurrent_batches {
-            let infer_rx_clone = infer_rx.clone();
-            let shared_runtime_clone = shared_runtime.clone();
-            let config_clone = config.clone();
-            tokio::spawn(async move {
-                Self::infer_worker(
-                    worker_id,
-                    infer_rx_clone,
-                    shared_runtime_clone,
-                    config_clone,
-                )
-                .await;
-            });
-        }
+        let queued = Arc::new(AtomicUsize::new(0));
+        let active = Arc::new(AtomicUsize::new(0));
+        let service_time = Arc::new(ServiceTimeEstimator::new());
+
+        // 启动连续批处理解码循环
+        tokio::spawn(Self::decode_loop(
+            shared_runtime.clone(),
+            request_rx,
+            queued.clone(),
+            active.clone(),
+            service_time.clone(),
+            config.clone(),
+        ));
 
         // 动态批处理管理器初始化完成
 
         Ok(Self {
             config,
             request_tx,
-            _shared_runtime: shared_runtime,
+            queued,
+            active,
+            gate: RequestGate::new(),
+            service_time,
+            rejected: AtomicU64::new(0),
+            shared_runtime,
         })
     }
 
@@ -96,28 +237,88 @@ impl DynamicBatchManager {
         voice_id: Option<String>,
         args: crate::rwkv_sampler::SamplerArgs,
     ) -> Result<(Vec<i32>, Vec<i32>)> {
-        let (response_tx, response_rx) = oneshot::channel();
-
-        let request = DynamicTtsRequest {
+        self.generate_tts_request(crate::rwkv_sampler::TtsBatchRequest {
             text,
             property_tokens,
             ref_global_tokens,
             ref_semantic_tokens,
-            voice_id,
             args,
+            voice_id,
+            token_tx: None,
+            cancel: CancelToken::new(),
+            priority: RequestPriority::Normal,
+        })
+        .await
+    }
+
+    /// 使用完整的批处理请求生成TTS（支持通过token_tx实时接收生成的tokens）
+    ///
+    /// 返回的future被提前丢弃（如客户端断开）时自动取消该请求，推理在下一个解码步骤退出。
+    /// 截止时间取`inference_timeout_ms`与请求自带截止时间中较早者，超时返回`TtsCancelled`错误。
+    /// 请求队列已满时立即返回`QueueFull`错误。
+    pub async fn generate_tts_request(
+        &self,
+        request: crate::rwkv_sampler::TtsBatchRequest,
+    ) -> Result<(Vec<i32>, Vec<i32>)> {
+        let _guard = self.gate.enter()?;
+        request.cancel.check()?;
+        let (response_tx, response_rx) = oneshot::channel();
+        let deadline = request.cancel.deadline();
+        // 子令牌：只取消本次请求，不影响共用同一参数的其他请求
+        let cancel = request
+            .cancel
+            .child_with_deadline(self.inference_deadline());
+        let cancel_on_drop = cancel.cancel_on_drop();
+
+        let request = DynamicTtsRequest {
+            text: request.text,
+            property_tokens: request.property_tokens,
+            ref_global_tokens: request.ref_global_tokens,
+            ref_semantic_tokens: request.ref_semantic_tokens,
+            voice_id: request.voice_id,
+            args: request.args,
             response_tx,
+            token_tx: request.token_tx,
+            cancel: cancel.clone(),
+            priority: request.priority,
+            deadline,
             submitted_at: Instant::now(),
-            batch_id: 0, // 将在收集阶段设置
         };
 
-        self.request_tx
-            .send_async(request)
-            .await
-            .map_err(|e| anyhow::anyhow!("发送请求失败: {}", e))?;
+        if let Some(token_tx) = &request.token_tx {
+            let position = self.queue_depth() + 1;
+            let _ = token_tx.send(TtsTokenEvent::Queued {
+                position,
+                estimated_wait_ms: self.estimated_wait().as_millis() as u64,
+            });
+        }
+        self.enqueue(request)?;
 
-        response_rx
-            .await
-            .map_err(|e| anyhow::anyhow!("接收响应失败: {}", e))?
+        let result = self.wait_with_deadline(&cancel, response_rx).await;
+        cancel_on_drop.disarm();
+        result
+    }
+
+    /// 按配置的推理超时计算新请求的截止时间
+    fn inference_deadline(&self) -> Instant {
+        Instant::now() + Duration::from_millis(self.config.inference_timeout_ms)
+    }
+
+    /// 等待请求结果，到达截止时间时不再等待推理线程，直接返回超时错误
+    ///
+    /// 推理线程会在下一个解码步骤检测到超时并清理该请求的状态。
+    async fn wait_with_deadline(
+        &self,
+        cancel: &CancelToken,
+        response_rx: oneshot::Receiver<Result<(Vec<i32>, Vec<i32>)>>,
+    ) -> Result<(Vec<i32>, Vec<i32>)> {
+        let Some(deadline) = cancel.deadline() else {
+            return self.gate.wait(response_rx).await;
+        };
+        match tokio::time::timeout_at(deadline.into(), self.gate.wait(response_rx)).await {
+            Ok(result) => result,
+            Err(_) => Err(TtsCancelled(CancelReason::DeadlineExceeded).into()),
+        }
     }
 
     /// 批量生成TTS（支持音频解码批处理）
@@ -126,12 +327,19 @@ impl DynamicBatchManager {
         requests: Vec<crate::rwkv_sampler::TtsBatchRequest>,
     ) -> Result<Vec<(Vec<i32>, Vec<i32>)>> {
         let batch_size = requests.len();
-        let mut response_rxs = Vec::with_capacity(batch_size);
+        let _guards = (0..batch_size)
+            .map(|_| self.gate.enter())
+            .collect::<Result<Vec<_>>>()?;
+        let mut pending = Vec::with_capacity(batch_size);
 
         // 创建所有请求
         for request in requests {
             let (response_tx, response_rx) = oneshot::channel();
-            response_rxs.push(response_rx);
+            let deadline = request.cancel.deadline();
+            let cancel = request
+                .cancel
+                .child_with_deadline(self.inference_deadline());
+            pending.push((cancel.clone(), cancel.cancel_on_drop(), response_rx));
 
             let dynamic_request = DynamicTtsRequest {
                 text: request.text,
@@ -141,412 +349,341 @@ impl DynamicBatchManager {
                 voice_id: request.voice_id,
                 args: request.args,
                 response_tx,
+                token_tx: request.token_tx,
+                cancel,
+                priority: request.priority,
+                deadline,
                 submitted_at: Instant::now(),
-                batch_id: 0,
             };
 
-            self.request_tx
-                .send_async(dynamic_request)
-                .await
-                .map_err(|e| anyhow::anyhow!("发送批处理请求失败: {}", e))?;
+            // 队列已满时返回错误，已入队的请求随守卫丢弃而取消
+            self.enqueue(dynamic_request)?;
         }
 
-        // 等待所有响应
+        // 等待所有响应，中途返回错误时其余请求随守卫丢弃而取消
         let mut results = Vec::with_capacity(batch_size);
-        for response_rx in response_rxs {
-            let result = response_rx
-                .await
-                .map_err(|e| anyhow::anyhow!("接收批处理响应失败: {}", e))??;
+        let mut cancel_guards = Vec::with_capacity(batch_size);
+        for (cancel, cancel_on_drop, response_rx) in pending {
+            cancel_guards.push(cancel_on_drop);
+            let result = self.wait_with_deadline(&cancel, response_rx).await?;
             results.push(result);
         }
+        cancel_guards.into_iter().for_each(CancelOnDrop::disarm);
 
         Ok(results)
     }
 
-    /// 核心运行时 - 负责收集请求并分发到推理工作线程
-    async fn run_core_runtime(
-        _shared_runtime: Arc<SharedRwkvRuntime>,
-        request_rx: Receiver<DynamicTtsRequest>,
-        infer_tx: Sender<InferBatch>,
-        config: DynamicBatchConfig,
-    ) {
-        // 核心运行时启动
+    /// 将请求放入请求队列，队列已满时立即返回`QueueFull`错误
+    fn enqueue(&self, request: DynamicTtsRequest) -> Result<()> {
+        let max_queue_depth = self.config.max_queue_depth;
+        let admitted = self
+            .queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                (max_queue_depth == 0 || queued < max_queue_depth).then_some(queued + 1)
+            });
+        if admitted.is_err() {
+            return Err(self.reject().into());
+        }
+        if let Err(e) = self.request_tx.send(request) {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(anyhow::anyhow!("发送请求失败: {}", e));
+        }
+        Ok(())
+    }
 
-        // 启动请求收集工作线程
-        tokio::spawn(Self::enqueue_worker(request_rx, infer_tx, config));
+    /// 请求队列已满时返回`QueueFull`错误，供流式接口在开始响应前快速拒绝
+    pub fn check_capacity(&self) -> std::result::Result<(), QueueFull> {
+        let max_queue_depth = self.config.max_queue_depth;
+        if max_queue_depth > 0 && self.queue_depth() >= max_queue_depth {
+            return Err(self.reject());
+        }
+        Ok(())
+    }
 
-        // 保持运行时活跃
-        loop {
-            tokio::time::sleep(Duration::from_secs(1)).await;
+    /// 记录一次因队列已满而拒绝的请求
+    fn reject(&self) -> QueueFull {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        let queue_depth = self.queue_depth();
+        warn!("请求队列已满（{}），拒绝新请求", queue_depth);
+        QueueFull {
+            queue_depth,
+            max_queue_depth: self.config.max_queue_depth,
+            estimated_wait: self.estimated_wait(),
         }
     }
 
-    /// 请求收集工作线程
-    async fn enqueue_worker(
+    /// 连续批处理解码循环
+    ///
+    /// 每个解码步骤前，把新到达的请求按优先级、截止时间和等待时间放入空闲槽位；
+    /// 请求结束后立即返回结果并释放槽位，长短不一的请求不必等待同批次中最长的请求。
+    ///
+    /// 管理器只启动一个解码循环，它是模型状态槽位的唯一使用者，加载和推进槽位状态无需加锁。
+    async fn decode_loop(
+        shared_runtime: Arc<SharedRwkvRuntime>,
         request_rx: Receiver<DynamicTtsRequest>,
-        infer_tx: Sender<InferBatch>,
+        queued: Arc<AtomicUsize>,
+        active: Arc<AtomicUsize>,
+        service_time: Arc<ServiceTimeEstimator>,
         config: DynamicBatchConfig,
     ) {
-        // 请求收集工作线程启动
-        let mut pending_requests = VecDeque::new();
-        let mut batch_counter = 1usize;
+        let mut scheduler = RequestScheduler::new(Duration::from_millis(config.priority_aging_ms));
+        let num_slots = shared_runtime.num_slots();
+        // 同时解码的请求数不超过max_batch_size
+        let max_active = config.max_batch_size.clamp(1, num_slots);
+        let mut batch = SlotBatch::new(num_slots, config.token_chunk_size.max(1));
 
         loop {
-            let collect_start = Instant::now();
-
-            // 收集请求的逻辑
-            loop {
-                let timeout = Duration::from_millis(config.collect_timeout_ms);
-                match tokio::time::timeout(timeout, request_rx.recv_async()).await {
-                    Ok(Ok(mut request)) => {
-                        request.batch_id = batch_counter;
-                        pending_requests.push_back(request);
-
-                        // 激进地收集所有立即可用的请求（非阻塞）
-                        let mut quick_collect_count = 0;
-                        let _quick_collect_start = Instant::now();
-                        while pending_requests.len() < config.max_batch_size
-                            && quick_collect_count < 50
-                        {
-                            match request_rx.try_recv() {
-                                Ok(mut req) => {
-                                    req.batch_id = batch_counter;
-                                    pending_requests.push_back(req);
-                                    quick_collect_count += 1;
-                                }
-                                Err(_) => break,
-                            }
-                        }
-
-                        if quick_collect_count > 0 {
-                            // 快速收集到额外请求
-                        }
-
-                        // 如果收集到多个请求，立即处理
-                        if pending_requests.len() > 1 {
-                            break;
-                        }
-
-                        // 如果只有一个请求，只等待很短时间（10ms）收集更多请求
-                        if pending_requests.len() == 1 && collect_start.elapsed().as_millis() >= 10
-                        {
-                            break;
-                        }
-                    }
-                    Ok(Err(_)) => {
-                        // 请求通道关闭，工作线程退出
+            if batch.is_empty() && scheduler.is_empty() {
+                // 空闲时阻塞等待新请求
+                match request_rx.recv_async().await {
+                    Ok(request) => Self::schedule(&mut scheduler, request),
+                    Err(_) => {
+                        // 请求通道关闭，解码循环退出
                         return;
                     }
-                    Err(_) => {
-                        // 超时，如果有请求则立即处理
-                        if !pending_requests.is_empty() {
-                            break;
-                        }
+                }
+
+                // 只有一个请求时短暂等待（最多10ms），让同时到达的请求一起prefill
+                if request_rx.is_empty() {
+                    let timeout = Duration::from_millis(config.collect_timeout_ms.min(10));
+                    if let Ok(Ok(request)) =
+                        tokio::time::timeout(timeout, request_rx.recv_async()).await
+                    {
+                        Self::schedule(&mut scheduler, request);
                     }
                 }
             }
 
-            // 处理收集到的请求
-            if !pending_requests.is_empty() {
-                let batch_id = batch_counter;
-                batch_counter += 1;
-
-                // 收集到批次请求
-
-                Self::process_collected_batch(
-                    pending_requests.drain(..).collect(),
-                    &infer_tx,
-                    batch_id,
-                )
-                .await;
+            // 取出通道中所有已到达的请求，一起参与调度
+            while let Ok(request) = request_rx.try_recv() {
+                Self::schedule(&mut scheduler, request);
             }
-        }
-    }
-
-    /// 处理收集到的批次
-    async fn process_collected_batch(
-        requests: Vec<DynamicTtsRequest>,
-        infer_tx: &Sender<InferBatch>,
-        batch_id: usize,
-    ) {
-        let batch_size = requests.len();
-        let (result_tx, result_rx) = flume::unbounded();
-
-        // 开始处理批次
-
-        // 转换为批处理请求
-        let batch_requests: Vec<crate::rwkv_sampler::TtsBatchRequest> = requests
-            .iter()
-            .map(|req| crate::rwkv_sampler::TtsBatchRequest {
-                text: req.text.clone(),
-                property_tokens: req.property_tokens.clone(),
-                ref_global_tokens: req.ref_global_tokens.clone(),
-                ref_semantic_tokens: req.ref_semantic_tokens.clone(),
-                voice_id: req.voice_id.clone(),
-                args: req.args.clone(),
-            })
-            .collect();
 
-        // 发送到推理队列
-        let infer_batch = InferBatch::Run {
-            batch_id,
-            requests: batch_requests,
-            sender: result_tx,
-        };
+            // 排队期间已取消或超时的请求立即出队，不再计入队列深度
+            let cancelled = scheduler.remove_if(|request| request.cancel.check().is_err());
+            queued.fetch_sub(cancelled.len(), Ordering::SeqCst);
+            for request in cancelled {
+                if let Err(e) = request.cancel.check() {
+                    let _ = request.response_tx.send(Err(e));
+                }
+            }
 
-        // 发送批次到推理队列
-        if let Err(e) = infer_tx.send_async(infer_batch).await {
-            error!("❌ 发送推理批次 {} 失败: {}", batch_id, e);
-            // 发送错误给所有请求
-            for request in requests {
-                let _ = request
-                    .response_tx
-                    .send(Err(anyhow::anyhow!("推理队列发送失败")));
+            // 空闲槽位按调度顺序接纳新请求
+            let free = max_active.saturating_sub(batch.active());
+            let admitted = scheduler.pop_batch(free, Instant::now());
+            queued.fetch_sub(admitted.len(), Ordering::SeqCst);
+            for request in admitted {
+                Self::admit(&shared_runtime, &mut batch, request).await;
             }
-            return;
-        }
+            active.store(batch.active(), Ordering::SeqCst);
 
-        // 等待推理结果
-        // 等待批次推理结果
-        match result_rx.recv_async().await {
-            Ok(results) => {
-                // 检查结果数量是否匹配
-                if results.len() == batch_size {
-                    // 分发结果
-                    for (request, result) in requests.into_iter().zip(results.into_iter()) {
-                        let _ = request.response_tx.send(Ok(result));
-                    }
-                    // 批次处理完成
-                } else {
-                    // 结果数量不匹配，可能是推理失败
-                    error!(
-                        "❌ 批次 {} 结果数量不匹配: 期望 {}, 实际 {}",
-                        batch_id,
-                        batch_size,
-                        results.len()
-                    );
-                    // 发送错误给所有请求
-                    for request in requests {
-                        let _ = request
-                            .response_tx
-                            .send(Err(anyhow::anyhow!("推理失败，结果数量不匹配")));
-                    }
-                }
+            if batch.is_empty() {
+                continue;
             }
-            Err(e) => {
-                error!("❌ 接收批次 {} 推理结果失败: {}", batch_id, e);
-                // 发送错误给所有请求
-                for request in requests {
-                    let _ = request
-                        .response_tx
-                        .send(Err(anyhow::anyhow!("推理结果接收失败")));
-                }
+            for (request, result) in batch.step(shared_runtime.as_ref()).await {
+                Self::finish(&shared_runtime, &service_time, request, result).await;
             }
+            active.store(batch.active(), Ordering::SeqCst);
         }
     }
 
-    /// 推理工作线程 - 重构版：使用独立状态管理确保状态隔离
-    /// 关键改进：每个请求创建独立的推理上下文，避免状态污染
-    async fn infer_worker(
-        _worker_id: usize,
-        infer_rx: Receiver<InferBatch>,
-        shared_runtime: Arc<SharedRwkvRuntime>,
-        _config: DynamicBatchConfig,
-    ) {
-        // 推理工作线程启动，使用独立状态管理架构
-        // 状态隔离：工作线程将为每个请求创建独立推理上下文
-
-        while let Ok(batch) = infer_rx.recv_async().await {
-            match batch {
-                InferBatch::Run {
-                    batch_id,
-                    requests,
-                    sender,
-                } => {
-                    let batch_size = requests.len();
-
-                    // 工作线程开始推理批次
-
-                    // 🔧 关键改进：为每个请求创建独立的推理上下文
-                    // 确保完全的状态隔离，避免并发请求间的状态污染
-                    let result = Self::process_batch_with_independent_contexts(
-                        shared_runtime.clone(),
-                        requests,
-                        batch_id as u64,
-                    )
-                    .await;
-
-                    match result {
-                        Ok(results) => {
-                            // 工作线程批次推理完成
-
-                            if let Err(e) = sender.send_async(results).await {
-                                error!("发送推理结果失败: {}", e);
-                            }
-                        }
-                        Err(e) => {
-                            error!("批次 {} 推理失败: {}", batch_id, e);
-                            // 发送与请求数量匹配的错误结果
-                            let error_results: Vec<(Vec<i32>, Vec<i32>)> =
-                                (0..batch_size).map(|_| (vec![], vec![])).collect();
-                            let _ = sender.send_async(error_results).await;
-                        }
-                    }
-                }
-                InferBatch::Result { batch_id, sender } => {
-                    // 处理结果请求（如果需要）
-                    warn!("收到结果请求 {}，当前实现不支持", batch_id);
-                    let _ = sender.send(vec![]);
-                }
-            }
-        }
-
-        // 推理工作线程退出
+    /// 将请求放入调度队列
+    fn schedule(scheduler: &mut RequestScheduler<DynamicTtsRequest>, request: DynamicTtsRequest) {
+        let (priority, deadline, submitted_at) =
+            (request.priority, request.deadline, request.submitted_at);
+        scheduler.push(request, priority, deadline, submitted_at);
     }
 
-    /// 使用独立上下文处理批次
-    /// 为每个请求创建独立的推理上下文，确保状态完全隔离
-    async fn process_batch_with_independent_contexts(
-        shared_runtime: Arc<SharedRwkvRuntime>,
-        requests: Vec<crate::rwkv_sampler::TtsBatchRequest>,
-        _batch_id: u64,
-    ) -> Result<Vec<(Vec<i32>, Vec<i32>)>> {
-        let batch_size = requests.len();
-        let mut results = Vec::with_capacity(batch_size);
-
-        // 为批次创建独立推理上下文
-
-        // 为每个请求创建独立的推理上下文并顺序处理（避免GPU资源争用）
-        // 注意：这里改为顺序处理而不是并行处理，因为GPU资源是有限的
-        for request in requests.into_iter() {
-            let shared_runtime_clone = shared_runtime.clone();
-            // 统一使用全局请求ID命名：req_<number>
-            let request_id = shared_runtime_clone.generate_request_id();
-
-            // 检测是否为声音克隆场景
-            let is_voice_cloning =
-                request.ref_global_tokens.is_some() || request.ref_semantic_tokens.is_some();
-
-            // 创建独立的推理上下文
-            let options = TtsInferOptions {
-                temperature: request.args.temperature,
-                top_k: request.args.top_k,
-                top_p: request.args.top_p,
-                seed: if is_voice_cloning {
-                    // 声音克隆时忽略用户提供的seed参数，确保确定性
-                    // 声音克隆场景：忽略用户seed参数，使用确定性采样
-                    None
-                } else {
-                    request.args.seed
-                },
-                voice_fidelity: request.args.voice_fidelity,
-                layered_randomness: request.args.layered_randomness.clone(),
-                sampling: None,
-                token_chunk_size: request.args.token_chunk_size,
-            };
-
-            let infer_context = shared_runtime_clone
-                .create_infer_context(request_id.clone(), request.text.clone(), options)
-                .await?;
-
-            // 保存状态ID用于清理
-            let state_id = infer_context.state_id;
-
-            // 执行独立推理
-            let result = Self::execute_independent_inference(infer_context, request).await;
-
-            // 清理状态
-            shared_runtime_clone.cleanup_state(state_id).await;
-
-            match result {
-                Ok(res) => {
-                    results.push(res);
-                    // 请求处理完成
-                }
-                Err(e) => {
-                    error!("❌ 请求 {} 处理失败: {}", request_id, e);
-                    results.push((vec![], vec![]));
-                }
-            }
+    /// 为请求创建推理上下文并放入空闲槽位，失败时直接返回错误
+    async fn admit(
+        shared_runtime: &SharedRwkvRuntime,
+        batch: &mut SlotBatch<ActiveRequest>,
+        request: DynamicTtsRequest,
+    ) {
+        // 排队期间已取消或超时的请求直接返回，不占用槽位
+        if let Err(e) = request.cancel.check() {
+            let _ = request.response_tx.send(Err(e));
+            return;
         }
 
-        // 批次独立推理完成
-
-        Ok(results)
-    }
-
-    /// 执行独立推理
-    async fn execute_independent_inference(
-        infer_context: TtsInferContext,
-        request: crate::rwkv_sampler::TtsBatchRequest,
-    ) -> Result<(Vec<i32>, Vec<i32>)> {
-        let _request_id = &infer_context.request_id;
-        // 开始独立推理
+        // 统一使用全局请求ID命名：req_<number>
+        let request_id = shared_runtime.generate_request_id();
 
         // 检测是否为声音克隆场景
         let is_voice_cloning =
             request.ref_global_tokens.is_some() || request.ref_semantic_tokens.is_some();
 
-        // 为本次请求创建独立RNG（可复现且互不干扰）
-        let rng: rand::rngs::StdRng = if is_voice_cloning {
-            // 声音克隆时不使用随机数，使用固定种子确保确定性
-            // 声音克隆模式：使用固定种子确保结果一致性
-            rand::rngs::StdRng::seed_from_u64(0) // 使用固定种子
-        } else if let Some(seed) = request.args.seed {
-            rand::rngs::StdRng::seed_from_u64(seed)
-        } else {
-            rand::rngs::StdRng::from_rng(rand::thread_rng()).expect("failed to seed StdRng")
+        // 创建独立的推理上下文
+        let options = TtsInferOptions {
+            temperature: request.args.temperature,
+            top_k: request.args.top_k,
+            top_p: request.args.top_p,
+            seed: if is_voice_cloning {
+                // 声音克隆场景：忽略用户seed参数，使用确定性采样
+                None
+            } else {
+                request.args.seed
+            },
+            voice_fidelity: request.args.voice_fidelity,
+            layered_randomness: request.args.layered_randomness.clone(),
+            sampling: None,
+            token_chunk_size: request.args.token_chunk_size,
         };
 
-        // 获取tokenizer
-        let tokenizer = &infer_context.tokenizer;
+        let infer_context = match shared_runtime
+            .create_infer_context(request_id.clone(), request.text.clone(), options)
+            .await
+        {
+            Ok(infer_context) => infer_context,
+            Err(e) => {
+                let _ = request.response_tx.send(Err(e));
+                return;
+            }
+        };
+        let state_id = infer_context.state_id;
+
+        let response_tx = request.response_tx;
+        let batch_request = crate::rwkv_sampler::TtsBatchRequest {
+            text: request.text,
+            property_tokens: request.property_tokens,
+            ref_global_tokens: request.ref_global_tokens,
+            ref_semantic_tokens: request.ref_semantic_tokens,
+            voice_id: request.voice_id,
+            args: request.args,
+            token_tx: request.token_tx,
+            cancel: request.cancel,
+            priority: request.priority,
+        };
+        let active_request = ActiveRequest {
+            request_id: request_id.clone(),
+            state_id,
+            response_tx,
+            started_at: Instant::now(),
+        };
 
-        // 编码文本
-        let text_tokens_u32: Vec<u32> = tokenizer
-            .encode(request.text.as_bytes())
-            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-        let text_tokens_raw: Vec<i32> = text_tokens_u32.into_iter().map(|t| t as i32).collect();
+        let inserted = match Self::build_slot(&infer_context, batch_request) {
+            Ok(slot) => batch.insert(shared_runtime, slot, active_request),
+            Err(e) => Err((active_request, e)),
+        };
+        if let Err((active_request, e)) = inserted {
+            error!("❌ 请求 {} 处理失败: {}", request_id, e);
+            shared_runtime.cleanup_state(state_id).await;
+            let _ = active_request.response_tx.send(Err(e));
+        }
+    }
 
-        // 根据C++代码逻辑，文本tokens直接使用原始ID，不需要任何偏移
-        let text_tokens: Vec<i32> = text_tokens_raw.clone();
+    /// 请求结束：清理状态、记录耗时并返回结果
+    async fn finish(
+        shared_runtime: &SharedRwkvRuntime,
+        service_time: &ServiceTimeEstimator,
+        request: ActiveRequest,
+        result: Result<(Vec<i32>, Vec<i32>)>,
+    ) {
+        // 清理状态
+        shared_runtime.cleanup_state(request.state_id).await;
 
-        // 文本编码结果
+        match &result {
+            Ok(_) => {
+                // 请求处理完成，只用成功的请求更新耗时估计
+                service_time.record(request.started_at.elapsed());
+            }
+            Err(e) if e.downcast_ref::<TtsCancelled>().is_some() => {
+                info!("请求 {} 已终止: {}", request.request_id, e);
+            }
+            Err(e) => {
+                error!("❌ 请求 {} 处理失败: {}", request.request_id, e);
+            }
+        }
+        let _ = request.response_tx.send(result);
+    }
 
-        // 释放原始tokens变量
-        drop(text_tokens_raw);
+    /// 编码文本并根据请求模式构建解码槽位
+    fn build_slot(
+        infer_context: &TtsInferContext,
+        request: crate::rwkv_sampler::TtsBatchRequest,
+    ) -> Result<TtsSlot> {
+        // 编码文本：根据C++代码逻辑，文本tokens直接使用原始ID，不需要任何偏移
+        let text_tokens: Vec<i32> = infer_context
+            .tokenizer
+            .encode(request.text.as_bytes())
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?
+            .into_iter()
+            .map(|t| t as i32)
+            .collect();
 
         // 检测是否为Zero-shot模式（有预提取的音色特征）
         let is_zero_shot =
             request.ref_global_tokens.is_some() && request.ref_semantic_tokens.is_some();
-
-        // 检测是否为Zero-shot模式（有预提取的音色特征）
         if is_zero_shot {
-            // 检测到Zero-shot模式，调用zero-shot推理
-            return execute_zero_shot_inference(
-                infer_context,
-                text_tokens,
-                request.property_tokens.clone(),
-                rng,
-                &request,
-            )
-            .await;
+            return build_zero_shot_slot(infer_context, &text_tokens, request);
         }
 
-        // 普通模式推理
-        execute_normal_inference(
-            infer_context,
-            text_tokens,
-            request.property_tokens.clone(),
-            rng,
-            &request,
-        )
-        .await
+        // 普通模式
+        Ok(build_normal_slot(infer_context, &text_tokens, request))
     }
 
     /// 获取配置
     pub fn config(&self) -> &DynamicBatchConfig {
         &self.config
     }
+
+    /// 排队中（尚未进入槽位）的请求数
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// 正在槽位中解码的请求数
+    pub fn active_slots(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// 状态槽位数
+    pub fn num_slots(&self) -> usize {
+        self.shared_runtime.num_slots()
+    }
+
+    /// 请求队列容量（0表示不限制）
+    pub fn max_queue_depth(&self) -> usize {
+        self.config.max_queue_depth
+    }
+
+    /// 按近期平均推理耗时和空闲槽位估算新请求开始解码前的等待时间
+    pub fn estimated_wait(&self) -> Duration {
+        self.service_time
+            .estimate_wait(self.queue_depth() + self.active_slots(), self.num_slots())
+    }
+
+    /// 因队列已满被拒绝的请求总数
+    pub fn rejected_requests(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// 已提交、尚未返回结果的请求数（含排队中和推理中）
+    pub fn in_flight(&self) -> usize {
+        self.gate.in_flight()
+    }
+
+    /// 是否已开始关闭
+    pub fn is_shutting_down(&self) -> bool {
+        self.gate.is_closed()
+    }
+
+    /// 优雅关闭：立即拒绝新请求，等待排队和推理中的请求在`grace`内完成，
+    /// 超时后剩余请求以错误返回。返回被终止的请求数
+    pub async fn shutdown(&self, grace: Duration) -> usize {
+        info!(
+            "动态批处理管理器开始关闭，等待 {} 个在途请求（宽限期 {:?}）",
+            self.gate.in_flight(),
+            grace
+        );
+        let aborted = self.gate.close(grace).await;
+        if aborted > 0 {
+            warn!("宽限期结束，{} 个未完成的请求已终止", aborted);
+        } else {
+            info!("所有在途请求已完成");
+        }
+        aborted
+    }
 }
 
 /// 全局动态批处理管理器单例
@@ -576,3 +713,57 @@ pub fn get_global_dynamic_batch_manager() -> Result<Arc<DynamicBatchManager>> {
         .cloned()
         .ok_or_else(|| anyhow::anyhow!("全局动态批处理管理器未初始化"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_gate_drain_and_abort() {
+        let gate = Arc::new(RequestGate::new());
+
+        // 宽限期内完成的请求正常返回
+        let guard = gate.enter().unwrap();
+        let (tx, rx) = oneshot::channel::<Result<u32>>();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let _ = tx.send(Ok(7));
+            drop(guard);
+        });
+
+        // 超过宽限期的请求被终止
+        let stuck_guard = gate.enter().unwrap();
+        let (_stuck_tx, stuck_rx) = oneshot::channel::<Result<u32>>();
+        let waiting_gate = gate.clone();
+        let stuck = tokio::spawn(async move {
+            let result = waiting_gate.wait(stuck_rx).await;
+            drop(stuck_guard);
+            result
+        });
+
+        assert_eq!(gate.wait(rx).await.unwrap(), 7);
+        assert_eq!(gate.close(Duration::from_millis(100)).await, 1);
+        assert!(gate.enter().is_err());
+        assert!(stuck.await.unwrap().is_err());
+        assert_eq!(gate.in_flight(), 0);
+    }
+
+    #[test]
+    fn test_service_time_estimate() {
+        let estimator = ServiceTimeEstimator::new();
+        assert_eq!(estimator.estimate(3), Duration::from_secs(3));
+
+        estimator.record(Duration::from_millis(800));
+        assert_eq!(estimator.estimate(2), Duration::from_millis(1600));
+
+        // 新样本按1/8权重平滑
+        estimator.record(Duration::from_millis(1600));
+        assert_eq!(estimator.estimate(1), Duration::from_millis(900));
+        assert_eq!(estimator.estimate(0), Duration::ZERO);
+
+        // 有空闲槽位时无需等待，否则按需要等待的轮数估算
+        assert_eq!(estimator.estimate_wait(3, 4), Duration::ZERO);
+        assert_eq!(estimator.estimate_wait(4, 4), Duration::from_millis(900));
+        assert_eq!(estimator.estimate_wait(8, 4), Duration::from_millis(1800));
+    }
+}
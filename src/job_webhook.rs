@@ -0,0 +1,484 @@
+//! 异步任务完成回调
+//!
+//! 任务结束（完成、失败或取消）后向提交时指定的`callback_url`发送JSON通知。
+//! 请求体使用HMAC-SHA256签名，签名内容为`<timestamp>.<body>`，通过以下请求头传递：
+//!
+//! - `X-Webhook-Id`: 任务ID
+//! - `X-Webhook-Timestamp`: Unix时间戳（秒），接收方可据此拒绝过旧的请求以防重放
+//! - `X-Webhook-Signature`: `sha256=<十六进制签名>`
+//!
+//! 网络错误、5xx、408和429视为临时失败，按指数退避重试；其余4xx视为接收方拒绝，不再重试。
+//!
+//! 为防止SSRF，回调地址解析出的IP必须都是公网地址，投递时固定使用校验过的地址并且不跟随重定向。
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::tts_job_manager::{JobStatus, TtsJob};
+
+/// SHA-256分组长度（字节）
+const SHA256_BLOCK_SIZE: usize = 64;
+
+/// 回调投递配置
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// 签名密钥
+    pub secret: String,
+    /// 服务对外地址（如`https://tts.example.com`），用于生成完整的下载链接；未设置时通知中不含下载链接
+    pub public_base_url: Option<String>,
+    /// 最大投递次数（含首次）
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间，之后每次翻倍
+    pub initial_backoff: Duration,
+    /// 重试等待时间上限
+    pub max_backoff: Duration,
+    /// 单次请求超时
+    pub request_timeout: Duration,
+    /// 是否允许回调内网、回环等非公网地址（仅用于开发测试）
+    pub allow_private_hosts: bool,
+}
+
+impl WebhookConfig {
+    /// 使用默认重试策略创建配置：最多6次，退避2秒起，上限5分钟
+    pub fn new(secret: String) -> Self {
+        Self {
+            secret,
+            public_base_url: None,
+            max_attempts: 6,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(300),
+            request_timeout: Duration::from_secs(10),
+            allow_private_hosts: false,
+        }
+    }
+
+    /// 设置服务对外地址
+    pub fn with_public_base_url(mut self, url: Option<String>) -> Self {
+        self.public_base_url = url.map(|url| url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// 第`attempt`次失败（从1开始）后的等待时间
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// 回调通知内容
+#[derive(Debug, Clone, Serialize)]
+pub struct JobNotification {
+    /// 事件类型：job.completed、job.failed或job.cancelled
+    pub event: String,
+    pub job_id: String,
+    pub status: JobStatus,
+    pub audio_duration_secs: Option<f64>,
+    pub rtf: Option<f64>,
+    /// 完整下载链接，仅在任务完成且配置了服务对外地址时提供
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    pub error: Option<String>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// 回调地址解析到了非公网地址，投递被拒绝
+#[derive(Debug, Clone)]
+struct BlockedCallbackAddress {
+    host: String,
+    ip: IpAddr,
+}
+
+impl std::fmt::Display for BlockedCallbackAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "回调地址 {} 指向内网或保留地址 {}，已拒绝",
+            self.host, self.ip
+        )
+    }
+}
+
+impl std::error::Error for BlockedCallbackAddress {}
+
+/// 回调投递器
+pub struct WebhookNotifier {
+    config: WebhookConfig,
+}
+
+impl WebhookNotifier {
+    /// 创建投递器
+    pub fn new(config: WebhookConfig) -> Result<Self> {
+        if config.secret.is_empty() {
+            return Err(anyhow!("webhook签名密钥不能为空"));
+        }
+        Ok(Self { config })
+    }
+
+    /// 校验回调地址：只接受http和https，且主机解析出的地址必须都是公网地址
+    pub async fn validate_url(&self, url: &str) -> Result<()> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| anyhow!("无效的回调地址: {}", e))?;
+        match parsed.scheme() {
+            "http" | "https" => {}
+            scheme => return Err(anyhow!("回调地址不支持{}协议", scheme)),
+        }
+        self.resolve(&parsed).await?;
+        Ok(())
+    }
+
+    /// 解析回调地址的主机，返回主机名与全部地址；任一地址不是公网地址时拒绝
+    async fn resolve(&self, url: &reqwest::Url) -> Result<(String, Vec<SocketAddr>)> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("回调地址缺少主机名"))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![SocketAddr::new(ip, port)],
+            Err(_) => tokio::net::lookup_host((host.as_str(), port))
+                .await
+                .map_err(|e| anyhow!("解析回调地址 {} 失败: {}", host, e))?
+                .collect(),
+        };
+        if addrs.is_empty() {
+            return Err(anyhow!("回调地址 {} 没有可用的IP地址", host));
+        }
+
+        if !self.config.allow_private_hosts {
+            if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+                return Err(BlockedCallbackAddress {
+                    host,
+                    ip: addr.ip(),
+                }
+                .into());
+            }
+        }
+        Ok((host, addrs))
+    }
+
+    /// 创建只连接校验过的地址、不跟随重定向的HTTP客户端，避免校验后DNS结果被替换（DNS rebinding）
+    async fn pinned_client(&self, url: &reqwest::Url) -> Result<reqwest::Client> {
+        let (host, addrs) = self.resolve(url).await?;
+        let mut builder = reqwest::Client::builder()
+            .timeout(self.config.request_timeout)
+            .redirect(reqwest::redirect::Policy::none());
+        if host.parse::<IpAddr>().is_err() {
+            builder = builder.resolve_to_addrs(&host, &addrs);
+        }
+        builder
+            .build()
+            .map_err(|e| anyhow!("创建HTTP客户端失败: {}", e))
+    }
+
+    /// 根据任务生成通知内容
+    pub fn notification(&self, job: &TtsJob) -> JobNotification {
+        let event = match job.status {
+            JobStatus::Completed => "job.completed",
+            JobStatus::Cancelled => "job.cancelled",
+            _ => "job.failed",
+        };
+        // 接收方无法解析相对路径，未配置服务对外地址时不提供下载链接
+        let download_url = self
+            .config
+            .public_base_url
+            .as_deref()
+            .filter(|_| job.status == JobStatus::Completed)
+            .map(|base_url| format!("{}/api/jobs/{}/audio", base_url, job.id));
+
+        JobNotification {
+            event: event.to_string(),
+            job_id: job.id.clone(),
+            status: job.status,
+            audio_duration_secs: job.audio_duration_secs,
+            rtf: job.rtf,
+            download_url,
+            error: job.error.clone(),
+            finished_at: job.finished_at,
+        }
+    }
+
+    /// 投递通知，失败时按指数退避重试，返回是否投递成功
+    pub async fn deliver(&self, url: &str, notification: &JobNotification) -> bool {
+        let body = match serde_json::to_vec(notification) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("序列化任务 {} 的回调通知失败: {}", notification.job_id, e);
+                return false;
+            }
+        };
+        let url = match reqwest::Url::parse(url) {
+            Ok(url) => url,
+            Err(e) => {
+                warn!("任务 {} 的回调地址无效: {}", notification.job_id, e);
+                return false;
+            }
+        };
+
+        for attempt in 1..=self.config.max_attempts {
+            let timestamp = Utc::now().timestamp();
+            let signature = sign_payload(self.config.secret.as_bytes(), timestamp, &body);
+            // 每次投递重新解析并校验地址
+            let result = match self.pinned_client(&url).await {
+                Ok(client) => client
+                    .post(url.clone())
+                    .header("content-type", "application/json")
+                    .header("x-webhook-id", &notification.job_id)
+                    .header("x-webhook-timestamp", timestamp.to_string())
+                    .header("x-webhook-signature", format!("sha256={}", signature))
+                    .body(body.clone())
+                    .send()
+                    .await
+                    .map_err(anyhow::Error::from),
+                Err(e) => Err(e),
+            };
+
+            let retryable = match result {
+                Ok(response) if response.status().is_success() => {
+                    info!(
+                        "📨 任务 {} 回调投递成功（第{}次）",
+                        notification.job_id, attempt
+                    );
+                    return true;
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    warn!(
+                        "任务 {} 回调返回 {}（第{}次）",
+                        notification.job_id, status, attempt
+                    );
+                    is_retryable_status(status.as_u16())
+                }
+                Err(e) => {
+                    warn!(
+                        "任务 {} 回调请求失败（第{}次）: {}",
+                        notification.job_id, attempt, e
+                    );
+                    // 地址被拒绝不是临时失败
+                    !e.is::<BlockedCallbackAddress>()
+                }
+            };
+
+            if !retryable || attempt == self.config.max_attempts {
+                break;
+            }
+            tokio::time::sleep(self.config.backoff_delay(attempt)).await;
+        }
+
+        warn!("任务 {} 回调投递失败，已放弃", notification.job_id);
+        false
+    }
+}
+
+/// 临时性失败的状态码，可以重试
+fn is_retryable_status(status: u16) -> bool {
+    status >= 500 || status == 408 || status == 429
+}
+
+/// 是否为公网地址：排除回环、私有、链路本地（含云服务元数据地址169.254.169.254）、
+/// 运营商级NAT、未指定、组播、广播及文档和保留地址
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || a == 0
+                || a >= 240
+                // 100.64.0.0/10 运营商级NAT
+                || (a == 100 && (64..128).contains(&b))
+                // 198.18.0.0/15 基准测试
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let [first, second, ..] = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // fc00::/7 唯一本地地址
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10 链路本地地址
+                || (first & 0xffc0) == 0xfe80
+                // 2001:db8::/32 文档地址
+                || (first == 0x2001 && second == 0x0db8))
+        }
+    }
+}
+
+/// 计算`<timestamp>.<body>`的HMAC-SHA256签名（十六进制）
+pub fn sign_payload(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let mut message = Vec::with_capacity(body.len() + 21);
+    message.extend_from_slice(timestamp.to_string().as_bytes());
+    message.push(b'.');
+    message.extend_from_slice(body);
+    format!("{:x}", hmac_sha256(secret, &message))
+}
+
+/// HMAC-SHA256（RFC 2104）
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> sha2::digest::Output<Sha256> {
+    let mut block = [0u8; SHA256_BLOCK_SIZE];
+    if key.len() > SHA256_BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let inner_hash = inner.finalize();
+
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner_hash);
+    outer.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        // RFC 4231 测试用例1与用例6（密钥长于分组长度）
+        let mac = hmac_sha256(&[0x0b; 20], b"Hi There");
+        assert_eq!(
+            format!("{:x}", mac),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+
+        let mac = hmac_sha256(
+            &[0xaa; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First",
+        );
+        assert_eq!(
+            format!("{:x}", mac),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+
+        assert_eq!(
+            sign_payload(b"secret", 1700000000, b"{}"),
+            format!("{:x}", hmac_sha256(b"secret", b"1700000000.{}"))
+        );
+    }
+
+    #[test]
+    fn test_backoff_and_retry_policy() {
+        let config = WebhookConfig::new("secret".to_string());
+        assert_eq!(config.backoff_delay(1), Duration::from_secs(2));
+        assert_eq!(config.backoff_delay(3), Duration::from_secs(8));
+        assert_eq!(config.backoff_delay(20), Duration::from_secs(300));
+
+        assert!(is_retryable_status(503));
+        assert!(is_retryable_status(429));
+        assert!(!is_retryable_status(404));
+    }
+
+    #[tokio::test]
+    async fn test_validate_url_rejects_private_hosts() {
+        let notifier = WebhookNotifier::new(WebhookConfig::new("secret".to_string())).unwrap();
+        assert!(notifier.validate_url("https://8.8.8.8/hook").await.is_ok());
+        assert!(notifier.validate_url("ftp://8.8.8.8/hook").await.is_err());
+        assert!(notifier.validate_url("not a url").await.is_err());
+
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://10.0.0.1/hook",
+            "http://192.168.1.10/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+            "http://[fd00::1]/hook",
+            "http://localhost/hook",
+        ] {
+            let error = notifier.validate_url(url).await.unwrap_err();
+            assert!(error.is::<BlockedCallbackAddress>(), "{}: {}", url, error);
+        }
+
+        let mut config = WebhookConfig::new("secret".to_string());
+        config.allow_private_hosts = true;
+        let notifier = WebhookNotifier::new(config).unwrap();
+        assert!(notifier
+            .validate_url("http://127.0.0.1:8080/hook")
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn test_notification_download_url() {
+        let mut job = TtsJob {
+            id: "job_1".to_string(),
+            status: JobStatus::Completed,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            segments_total: 1,
+            segments_done: 1,
+            text_chars: 2,
+            output: crate::tts_job_manager::JobOutput {
+                format: "wav".to_string(),
+                sample_rate: 16000,
+                bitrate_kbps: None,
+            },
+            output_file: None,
+            audio_duration_secs: None,
+            rtf: None,
+            error: None,
+            callback_url: None,
+            callback_delivered: None,
+            owner: None,
+            args: Default::default(),
+        };
+
+        // 未配置服务对外地址时不输出相对路径
+        let notifier = WebhookNotifier::new(WebhookConfig::new("secret".to_string())).unwrap();
+        let notification = notifier.notification(&job);
+        assert_eq!(notification.download_url, None);
+        let body = serde_json::to_value(&notification).unwrap();
+        assert!(body.get("download_url").is_none());
+
+        let config = WebhookConfig::new("secret".to_string())
+            .with_public_base_url(Some("https://tts.example.com/".to_string()));
+        let notifier = WebhookNotifier::new(config).unwrap();
+        assert_eq!(
+            notifier.notification(&job).download_url.as_deref(),
+            Some("https://tts.example.com/api/jobs/job_1/audio")
+        );
+        job.status = JobStatus::Failed;
+        assert_eq!(notifier.notification(&job).download_url, None);
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "0.0.0.0",
+            "100.64.0.1",
+            "172.16.0.1",
+            "198.18.0.1",
+            "224.0.0.1",
+            "255.255.255.255",
+            "fe80::1",
+            "2001:db8::1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+}
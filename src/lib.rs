@@ -4,9 +4,18 @@
 
 // Core modules
 // pub mod batch_manager; // 已移动到备份目录
+pub mod api_auth;
+pub mod audio_encoder;
+pub mod job_webhook;
+pub mod metrics;
 pub mod properties_util;
+pub mod rate_limit;
 pub mod ref_audio_utilities;
+pub mod request_scheduler;
 pub mod rwkv_sampler;
+pub mod sentence_buffer;
+pub mod server_config;
+pub mod tts_job_manager;
 pub mod tts_state_manager;
 // pub mod tts_pipeline; // 已移动到备份目录
 pub mod tts_pipeline_fixes;
@@ -27,6 +36,7 @@ pub mod sampler_manager;
 pub mod shared_runtime;
 
 // Inference modules
+pub mod batched_inference;
 pub mod normal_mode_inference;
 pub mod zero_shot_inference;
 
@@ -50,7 +60,7 @@ pub use rwkv_sampler::{RwkvSampler, SamplerArgs, TtsBatchRequest};
 pub mod tts_generator {
     // TTS Generator implementation
 
-    use crate::{RefAudioUtilities, RwkvSampler};
+    use crate::{audio_encoder::save_audio_file, RefAudioUtilities, RwkvSampler};
 
     use anyhow::Result;
 
@@ -125,31 +135,14 @@ pub mod tts_generator {
             ))
         }
 
-        /// 保存音频到WAV文件
+        /// 保存音频到文件，按扩展名选择格式（见[`save_audio_file`]）
         pub fn save_audio(
             &self,
             audio_samples: &[f32],
             output_path: &str,
             sample_rate: u32,
         ) -> Result<()> {
-            // 保存音频到WAV文件
-            // 保存音频到指定路径
-
-            // 使用hound库保存WAV文件
-            let spec = hound::WavSpec {
-                channels: 1,
-                sample_rate,
-                bits_per_sample: 32,
-                sample_format: hound::SampleFormat::Float,
-            };
-
-            let mut writer = hound::WavWriter::create(output_path, spec)?;
-            for &sample in audio_samples {
-                writer.write_sample(sample)?;
-            }
-            writer.finalize()?;
-
-            Ok(())
+            save_audio_file(audio_samples, output_path, sample_rate)
         }
     }
 
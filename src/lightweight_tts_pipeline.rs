@@ -2,7 +2,10 @@
 //! 复用全局资源，不再每次创建新的模型实例
 
 use crate::{
+    audio_encoder::save_audio_file,
+    batch_types::{CancelToken, RequestPriority, TtsTokenEvent},
     dynamic_batch_manager::get_global_dynamic_batch_manager,
+    metrics::{global_metrics, InferenceStage},
     onnx_session_pool::get_global_onnx_manager,
     properties_util,
     rwkv_sampler::{SamplerArgs, TtsBatchRequest},
@@ -11,11 +14,57 @@ use crate::{
 use anyhow::Result;
 use ndarray::{Array1, Array2};
 use ort::{session::SessionInputValue, value::Value};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tracing;
 
+/// 流式解码时每块携带的左侧上下文token数（semantic tokens为50Hz，约0.3秒）
+const STREAM_CONTEXT_TOKENS: usize = 16;
+
+/// 每秒音频对应的semantic token数
+const SEMANTIC_TOKENS_PER_SECOND: f64 = 50.0;
+
+/// 进度上报时每隔多少个semantic token推送一次（约0.5秒音频）
+const PROGRESS_TOKEN_INTERVAL: usize = 25;
+
+/// 合成进度事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum TtsProgress {
+    /// 已进入批处理队列，附带预计开始推理前的等待时间
+    Queued {
+        position: usize,
+        estimated_wait_ms: u64,
+    },
+    /// Prefill完成
+    PrefillDone,
+    /// Global tokens已确定
+    GlobalTokensDone,
+    /// 已生成的semantic token数及对应的预估音频时长
+    SemanticTokens {
+        generated: usize,
+        estimated_audio_secs: f64,
+    },
+    /// 推理完成，开始解码音频
+    Decoding { semantic_tokens: usize },
+}
+
+impl TtsProgress {
+    /// 事件名称（与serde标签一致，用作SSE的event字段）
+    pub fn name(&self) -> &'static str {
+        match self {
+            TtsProgress::Queued { .. } => "queued",
+            TtsProgress::PrefillDone => "prefill_done",
+            TtsProgress::GlobalTokensDone => "global_tokens_done",
+            TtsProgress::SemanticTokens { .. } => "semantic_tokens",
+            TtsProgress::Decoding { .. } => "decoding",
+        }
+    }
+}
+
 /// 轻量级TTS流水线参数
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LightweightTtsPipelineArgs {
     pub text: String,
     pub prompt_text: String,
@@ -37,6 +86,13 @@ pub struct LightweightTtsPipelineArgs {
     // 新增：直接传入的音色特征tokens
     pub voice_global_tokens: Option<Vec<i32>>,
     pub voice_semantic_tokens: Option<Vec<i32>>,
+    /// 单次推理的超时时间（毫秒），与服务器配置的推理超时取较小者；分段合成时对每段分别计时
+    pub timeout_ms: Option<u64>,
+    /// 调度优先级：interactive、normal（默认）或bulk
+    pub priority: RequestPriority,
+    /// 取消令牌，取消后正在进行的推理在下一个解码步骤退出
+    #[serde(skip)]
+    pub cancel: CancelToken,
 }
 
 impl Default for LightweightTtsPipelineArgs {
@@ -60,6 +116,9 @@ impl Default for LightweightTtsPipelineArgs {
             voice_id: None,
             voice_global_tokens: None,
             voice_semantic_tokens: None,
+            timeout_ms: None,
+            priority: RequestPriority::Normal,
+            cancel: CancelToken::new(),
         }
     }
 }
@@ -93,6 +152,8 @@ mod tests {
         assert_eq!(args.voice_id, None);
         assert_eq!(args.voice_global_tokens, None);
         assert_eq!(args.voice_semantic_tokens, None);
+        assert_eq!(args.timeout_ms, None);
+        assert_eq!(args.priority, RequestPriority::Normal);
     }
 
     #[test]
@@ -134,6 +195,16 @@ mod tests {
     }
 }
 
+/// 为单次推理请求创建取消令牌，设置了`timeout_ms`时从现在开始计算截止时间
+fn request_cancel_token(args: &LightweightTtsPipelineArgs) -> CancelToken {
+    match args.timeout_ms {
+        Some(timeout_ms) => args.cancel.child_with_deadline(
+            std::time::Instant::now() + std::time::Duration::from_millis(timeout_ms),
+        ),
+        None => args.cancel.clone(),
+    }
+}
+
 /// 轻量级TTS流水线，复用全局资源
 #[derive(Debug)]
 pub struct LightweightTtsPipeline {}
@@ -195,8 +266,11 @@ impl LightweightTtsPipeline {
         }
     }
 
-    /// 处理参考音频（Zero-shot模式）
-    async fn process_reference_audio(&self, ref_audio_path: &str) -> Result<(Vec<i32>, Vec<i32>)> {
+    /// 处理参考音频（Zero-shot模式），返回(global_tokens, semantic_tokens)
+    pub async fn process_reference_audio(
+        &self,
+        ref_audio_path: &str,
+    ) -> Result<(Vec<i32>, Vec<i32>)> {
         if ref_audio_path.is_empty() || !Path::new(ref_audio_path).exists() {
             return Err(anyhow::anyhow!("参考音频文件不存在: {}", ref_audio_path));
         }
@@ -612,6 +686,7 @@ impl LightweightTtsPipeline {
         semantic_tokens: &[i32],
     ) -> Result<Vec<f32>> {
         let onnx_manager = get_global_onnx_manager()?;
+        let decode_start = std::time::Instant::now();
 
         // 获取BiCodec Detokenize会话
         let detokenize_session = onnx_manager.acquire_bicodec_detokenize_session().await?;
@@ -620,6 +695,7 @@ impl LightweightTtsPipeline {
         let audio = self
             .detokenize_audio_with_session(global_tokens, semantic_tokens, detokenize_session)
             .await?;
+        global_metrics().observe_stage(InferenceStage::Decode, decode_start.elapsed());
 
         Ok(audio)
     }
@@ -641,7 +717,7 @@ impl LightweightTtsPipeline {
         // 并行执行解码（使用CPU多核心）
         let mut tasks = Vec::with_capacity(batch_size);
         for ((global_tokens, semantic_tokens), session_guard) in
-            batch_requests.iter().zip(session_guards.into_iter())
+            batch_requests.iter().zip(session_guards)
         {
             let global_tokens_clone = global_tokens.clone();
             let semantic_tokens_clone = semantic_tokens.clone();
@@ -732,21 +808,19 @@ impl LightweightTtsPipeline {
         Ok(audio_slice.to_vec())
     }
 
-    /// 生成语音（使用批处理调度器）
-    pub async fn generate_speech(&self, args: &LightweightTtsPipelineArgs) -> Result<Vec<f32>> {
-        let total_start = std::time::Instant::now();
-
+    /// 根据流水线参数准备批处理请求（文本处理、属性tokens/参考音频处理、采样参数）
+    async fn prepare_batch_request(
+        &self,
+        args: &LightweightTtsPipelineArgs,
+    ) -> Result<TtsBatchRequest> {
         // 1. 处理文本
-        let text_start = std::time::Instant::now();
         let processed_text = if args.zero_shot {
             self.process_text_zero_shot(&args.text, &args.prompt_text)
         } else {
             self.process_text(&args.text)
         };
-        let text_processing_time = text_start.elapsed();
 
         // 2. 处理属性tokens或参考音频
-        let ref_start = std::time::Instant::now();
         let (property_tokens, ref_global_tokens, ref_semantic_tokens) =
             // 优先使用voice_id从缓存获取tokens
             if let Some(voice_id) = &args.voice_id {
@@ -788,7 +862,6 @@ impl LightweightTtsPipeline {
                 println!("generate_property_tokens: {:?}", tokens);
                 (tokens, None, None)
             };
-        let reference_processing_time = ref_start.elapsed();
 
         // 3. 创建采样参数
         let sampler_args = SamplerArgs {
@@ -803,33 +876,48 @@ impl LightweightTtsPipeline {
         };
 
         // 4. 创建批处理请求
-        let request = TtsBatchRequest {
+        Ok(TtsBatchRequest {
             text: processed_text,
             property_tokens,
             ref_global_tokens,
             ref_semantic_tokens,
             args: sampler_args,
             voice_id: args.voice_id.clone(),
-        };
+            token_tx: None,
+            cancel: request_cancel_token(args),
+            priority: args.priority,
+        })
+    }
+
+    /// 生成语音（使用批处理调度器）
+    pub async fn generate_speech(&self, args: &LightweightTtsPipelineArgs) -> Result<Vec<f32>> {
+        let (audio, _, _) = self.generate_speech_with_tokens(args).await?;
+        Ok(audio)
+    }
+
+    /// 生成语音，同时返回生成的global tokens和semantic tokens
+    ///
+    /// 长文本分段合成时，可将首段的tokens作为后续段落的zero-shot参考，保持音色一致。
+    pub async fn generate_speech_with_tokens(
+        &self,
+        args: &LightweightTtsPipelineArgs,
+    ) -> Result<(Vec<f32>, Vec<i32>, Vec<i32>)> {
+        let total_start = std::time::Instant::now();
+
+        // 1-4. 处理文本、属性tokens或参考音频，创建批处理请求
+        let prepare_start = std::time::Instant::now();
+        let request = self.prepare_batch_request(args).await?;
+        let prepare_time = prepare_start.elapsed();
 
         // 5. 提交到动态批处理管理器并等待RWKV推理
         let inference_start = std::time::Instant::now();
         let manager = get_global_dynamic_batch_manager()?;
-        let (global_tokens, semantic_tokens) = manager
-            .generate_tts(
-                request.text,
-                request.property_tokens,
-                request.ref_global_tokens,
-                request.ref_semantic_tokens,
-                request.voice_id,
-                request.args,
-            )
-            .await?;
+        let (global_tokens, semantic_tokens) = manager.generate_tts_request(request).await?;
         let inference_time = inference_start.elapsed();
 
         // 6. 解码音频
         if global_tokens.is_empty() && semantic_tokens.is_empty() {
-            return Ok(vec![0.0; 16000]);
+            return Ok((vec![0.0; 16000], global_tokens, semantic_tokens));
         }
 
         let decode_start = std::time::Instant::now();
@@ -838,20 +926,168 @@ impl LightweightTtsPipeline {
 
         let total_time = total_start.elapsed();
         let audio_duration = audio.len() as f64 / 16000.0; // 假设16kHz采样率
-        let _rtf = total_time.as_secs_f64() / audio_duration;
+        let rtf = total_time.as_secs_f64() / audio_duration;
+        global_metrics().observe_rtf(rtf);
 
         // 输出详细的耗时统计
         println!("⏱️  TTS生成详细耗时统计:");
-        println!("  文本处理耗时: {:.2}ms", text_processing_time.as_millis());
         println!(
-            "  参考音频处理耗时: {:.2}ms",
-            reference_processing_time.as_millis()
+            "  文本及参考音频处理耗时: {:.2}ms",
+            prepare_time.as_millis()
         );
         println!("  RWKV推理耗时: {:.2}ms", inference_time.as_millis());
         println!("  音频解码耗时: {:.2}ms", audio_decoding_time.as_millis());
         println!("  总耗时: {:.2}ms", total_time.as_millis());
 
-        Ok(audio)
+        Ok((audio, global_tokens, semantic_tokens))
+    }
+
+    /// 流式生成语音
+    ///
+    /// 在semantic tokens生成的同时分块解码，每凑够`chunk_tokens`个新token就通过`audio_tx`
+    /// 推送一段16kHz音频（首块使用更小的块以降低首包延迟）。每块解码时带上
+    /// `STREAM_CONTEXT_TOKENS`个已输出的token作为左侧上下文，只输出新增部分对应的音频。
+    /// 接收端断开或出错提前返回时取消推理。
+    pub async fn generate_speech_stream(
+        &self,
+        args: &LightweightTtsPipelineArgs,
+        chunk_tokens: usize,
+        audio_tx: flume::Sender<Vec<f32>>,
+    ) -> Result<()> {
+        let chunk_tokens = chunk_tokens.max(1);
+        let first_chunk_tokens = (chunk_tokens / 2).max(1);
+
+        let mut request = self.prepare_batch_request(args).await?;
+        let (token_tx, token_rx) = flume::unbounded();
+        request.token_tx = Some(token_tx);
+        // 推理在独立任务中进行，当前future提前结束时需要显式取消
+        request.cancel = request.cancel.child();
+        let cancel_on_drop = request.cancel.cancel_on_drop();
+
+        // 推理在后台进行，当前任务负责边接收tokens边解码
+        let manager = get_global_dynamic_batch_manager()?;
+        let inference = tokio::spawn(async move { manager.generate_tts_request(request).await });
+
+        let mut global_tokens: Option<Vec<i32>> = None;
+        let mut semantic_tokens: Vec<i32> = Vec::new();
+        let mut emitted = 0usize;
+
+        while let Ok(event) = token_rx.recv_async().await {
+            match event {
+                TtsTokenEvent::GlobalTokens(tokens) => global_tokens = Some(tokens),
+                TtsTokenEvent::SemanticToken(token) => semantic_tokens.push(token),
+                TtsTokenEvent::Queued { .. } | TtsTokenEvent::PrefillDone => continue,
+            }
+
+            let target = if emitted == 0 {
+                first_chunk_tokens
+            } else {
+                chunk_tokens
+            };
+            if let Some(global) = &global_tokens {
+                if semantic_tokens.len() - emitted >= target {
+                    let audio = self
+                        .decode_audio_window(global, &semantic_tokens, emitted)
+                        .await?;
+                    emitted = semantic_tokens.len();
+                    if audio_tx.send_async(audio).await.is_err() {
+                        // 接收端已断开
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        // 推理结束，以最终结果为准解码剩余tokens
+        let (global_tokens, semantic_tokens) = inference
+            .await
+            .map_err(|e| anyhow::anyhow!("流式推理任务失败: {}", e))??;
+        if global_tokens.is_empty() && semantic_tokens.is_empty() {
+            return Err(anyhow::anyhow!("推理未生成任何tokens"));
+        }
+        cancel_on_drop.disarm();
+        if semantic_tokens.len() > emitted {
+            let audio = self
+                .decode_audio_window(&global_tokens, &semantic_tokens, emitted)
+                .await?;
+            let _ = audio_tx.send_async(audio).await;
+        }
+
+        Ok(())
+    }
+
+    /// 生成语音，同时通过`progress_tx`推送各阶段进度
+    ///
+    /// semantic token每生成`PROGRESS_TOKEN_INTERVAL`个推送一次。接收端断开不影响合成，
+    /// 需要中止时通过`args.cancel`取消。
+    pub async fn generate_speech_with_progress(
+        &self,
+        args: &LightweightTtsPipelineArgs,
+        progress_tx: flume::Sender<TtsProgress>,
+    ) -> Result<Vec<f32>> {
+        let mut request = self.prepare_batch_request(args).await?;
+        let (token_tx, token_rx) = flume::unbounded();
+        request.token_tx = Some(token_tx);
+        request.cancel = request.cancel.child();
+        let cancel_on_drop = request.cancel.cancel_on_drop();
+
+        let manager = get_global_dynamic_batch_manager()?;
+        let inference = tokio::spawn(async move { manager.generate_tts_request(request).await });
+
+        let mut generated = 0usize;
+        while let Ok(event) = token_rx.recv_async().await {
+            let progress = match event {
+                TtsTokenEvent::Queued {
+                    position,
+                    estimated_wait_ms,
+                } => TtsProgress::Queued {
+                    position,
+                    estimated_wait_ms,
+                },
+                TtsTokenEvent::PrefillDone => TtsProgress::PrefillDone,
+                TtsTokenEvent::GlobalTokens(_) => TtsProgress::GlobalTokensDone,
+                TtsTokenEvent::SemanticToken(_) => {
+                    generated += 1;
+                    if generated % PROGRESS_TOKEN_INTERVAL != 0 {
+                        continue;
+                    }
+                    TtsProgress::SemanticTokens {
+                        generated,
+                        estimated_audio_secs: generated as f64 / SEMANTIC_TOKENS_PER_SECOND,
+                    }
+                }
+            };
+            let _ = progress_tx.send(progress);
+        }
+
+        let (global_tokens, semantic_tokens) = inference
+            .await
+            .map_err(|e| anyhow::anyhow!("推理任务失败: {}", e))??;
+        cancel_on_drop.disarm();
+        if global_tokens.is_empty() && semantic_tokens.is_empty() {
+            return Err(anyhow::anyhow!("推理未生成任何tokens"));
+        }
+
+        let _ = progress_tx.send(TtsProgress::Decoding {
+            semantic_tokens: semantic_tokens.len(),
+        });
+        self.decode_audio(&global_tokens, &semantic_tokens).await
+    }
+
+    /// 解码`semantic_tokens[start..]`对应的音频，带左侧上下文以保证块边界连续
+    async fn decode_audio_window(
+        &self,
+        global_tokens: &[i32],
+        semantic_tokens: &[i32],
+        start: usize,
+    ) -> Result<Vec<f32>> {
+        let context_start = start.saturating_sub(STREAM_CONTEXT_TOKENS);
+        let window = &semantic_tokens[context_start..];
+        let audio = self.decode_audio(global_tokens, window).await?;
+
+        // 按token比例丢弃上下文部分对应的音频
+        let skip = audio.len() * (start - context_start) / window.len();
+        Ok(audio[skip..].to_vec())
     }
 
     /// 批量生成语音（CPU优化：支持批处理推理和音频解码）
@@ -954,6 +1190,9 @@ impl LightweightTtsPipeline {
                 ref_semantic_tokens: ref_semantic_tokens.clone(),
                 args: sampler_args,
                 voice_id: args.voice_id.clone(),
+                token_tx: None,
+                cancel: request_cancel_token(args),
+                priority: args.priority,
             };
             batch_requests.push(request);
         }
@@ -975,151 +1214,14 @@ impl LightweightTtsPipeline {
         Ok(audio_results)
     }
 
-    /// 保存音频到文件（支持WAV和MP3格式）
+    /// 保存音频到文件，按扩展名选择格式（见[`save_audio_file`]）
     pub fn save_audio(
         &self,
         audio_samples: &[f32],
         output_path: &str,
         sample_rate: u32,
     ) -> Result<()> {
-        use std::path::Path;
-
-        #[cfg(debug_assertions)]
-        {
-            // 保存音频到指定路径
-        }
-
-        let path = Path::new(output_path);
-        let extension = path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("wav")
-            .to_lowercase();
-
-        match extension.as_str() {
-            "mp3" => self.save_audio_mp3(audio_samples, output_path, sample_rate),
-            "wav" => self.save_audio_wav(audio_samples, output_path, sample_rate),
-            _ => self.save_audio_wav(audio_samples, output_path, sample_rate),
-        }
-    }
-
-    /// 保存音频到WAV文件
-    fn save_audio_wav(
-        &self,
-        audio_samples: &[f32],
-        output_path: &str,
-        sample_rate: u32,
-    ) -> Result<()> {
-        let spec = hound::WavSpec {
-            channels: 1,
-            sample_rate,
-            bits_per_sample: 32,
-            sample_format: hound::SampleFormat::Float,
-        };
-
-        let mut writer = hound::WavWriter::create(output_path, spec)?;
-        for &sample in audio_samples {
-            writer.write_sample(sample)?;
-        }
-        writer.finalize()?;
-
-        #[cfg(debug_assertions)]
-        {
-            // WAV音频保存完成
-        }
-        Ok(())
-    }
-
-    /// 保存音频到MP3文件
-    fn save_audio_mp3(
-        &self,
-        audio_samples: &[f32],
-        output_path: &str,
-        sample_rate: u32,
-    ) -> Result<()> {
-        use mp3lame_encoder::{Builder, FlushNoGap};
-        use std::fs::File;
-        use std::io::Write;
-
-        // 转换f32样本到i16
-        let i16_samples: Vec<i16> = audio_samples
-            .iter()
-            .map(|&sample| {
-                let clamped = sample.clamp(-1.0, 1.0);
-                (clamped * i16::MAX as f32) as i16
-            })
-            .collect();
-
-        // 配置MP3编码器
-        let mut builder = Builder::new().ok_or_else(|| anyhow::anyhow!("创建MP3编码器失败"))?;
-
-        builder
-            .set_num_channels(1)
-            .map_err(|e| anyhow::anyhow!("设置声道数失败: {}", e))?;
-
-        builder
-            .set_sample_rate(sample_rate)
-            .map_err(|e| anyhow::anyhow!("设置采样率失败: {}", e))?;
-
-        builder
-            .set_brate(mp3lame_encoder::Bitrate::Kbps128)
-            .map_err(|e| anyhow::anyhow!("设置比特率失败: {}", e))?;
-
-        builder
-            .set_quality(mp3lame_encoder::Quality::Best)
-            .map_err(|e| anyhow::anyhow!("设置质量失败: {}", e))?;
-
-        let mut encoder = builder
-            .build()
-            .map_err(|e| anyhow::anyhow!("构建MP3编码器失败: {}", e))?;
-
-        // 创建输出文件
-        let mut output_file =
-            File::create(output_path).map_err(|e| anyhow::anyhow!("创建输出文件失败: {}", e))?;
-
-        // 编码音频数据
-        use mp3lame_encoder::InterleavedPcm;
-        use std::mem::MaybeUninit;
-
-        let mut mp3_buffer: Vec<MaybeUninit<u8>> =
-            vec![MaybeUninit::uninit(); i16_samples.len() * 2];
-        let pcm_input = InterleavedPcm(&i16_samples);
-        let encoded_size = encoder
-            .encode(pcm_input, &mut mp3_buffer)
-            .map_err(|e| anyhow::anyhow!("MP3编码失败: {}", e))?;
-
-        // 安全地转换MaybeUninit<u8>到u8
-        let encoded_data: Vec<u8> = mp3_buffer[..encoded_size]
-            .iter()
-            .map(|x| unsafe { x.assume_init() })
-            .collect();
-
-        output_file
-            .write_all(&encoded_data)
-            .map_err(|e| anyhow::anyhow!("写入MP3数据失败: {}", e))?;
-
-        // 刷新编码器并写入剩余数据
-        let mut flush_buffer: Vec<MaybeUninit<u8>> = vec![MaybeUninit::uninit(); 7200];
-        let flush_size = encoder
-            .flush::<FlushNoGap>(&mut flush_buffer)
-            .map_err(|e| anyhow::anyhow!("刷新MP3编码器失败: {}", e))?;
-
-        if flush_size > 0 {
-            let flush_data: Vec<u8> = flush_buffer[..flush_size]
-                .iter()
-                .map(|x| unsafe { x.assume_init() })
-                .collect();
-
-            output_file
-                .write_all(&flush_data)
-                .map_err(|e| anyhow::anyhow!("写入MP3刷新数据失败: {}", e))?;
-        }
-
-        #[cfg(debug_assertions)]
-        {
-            // MP3音频保存完成
-        }
-        Ok(())
+        save_audio_file(audio_samples, output_path, sample_rate)
     }
 }
 
@@ -0,0 +1,356 @@
+//! Prometheus指标收集与导出
+//!
+//! 进程内使用原子计数器和固定分桶直方图记录指标，抓取时按Prometheus文本格式输出。
+//! 队列深度、ONNX会话池占用和音色缓存统计在抓取时从对应组件实时读取。
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::dynamic_batch_manager::get_global_dynamic_batch_manager;
+use crate::onnx_session_pool::get_global_onnx_manager;
+use crate::voice_feature_manager::CacheStats;
+
+/// 阶段耗时直方图分桶（秒）
+const STAGE_LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// RTF直方图分桶
+const RTF_BUCKETS: &[f64] = &[0.05, 0.1, 0.2, 0.3, 0.5, 0.75, 1.0, 1.5, 2.0, 5.0];
+
+/// 推理阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InferenceStage {
+    /// 输入序列预填充
+    Prefill,
+    /// Global tokens生成
+    Global,
+    /// Semantic tokens生成
+    Semantic,
+    /// BiCodec音频解码
+    Decode,
+}
+
+impl InferenceStage {
+    const ALL: [InferenceStage; 4] = [
+        InferenceStage::Prefill,
+        InferenceStage::Global,
+        InferenceStage::Semantic,
+        InferenceStage::Decode,
+    ];
+
+    /// 指标标签值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InferenceStage::Prefill => "prefill",
+            InferenceStage::Global => "global",
+            InferenceStage::Semantic => "semantic",
+            InferenceStage::Decode => "decode",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// 固定分桶直方图（累积计数在输出时计算）
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// 每个分桶的计数，最后一个为+Inf
+    buckets: Vec<AtomicU64>,
+    /// 观测值之和（f64按位存储）
+    sum_bits: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    /// 使用给定的分桶上界创建直方图
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_bits: AtomicU64::new(0f64.to_bits()),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录一个观测值
+    pub fn observe(&self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        let index = self
+            .bounds
+            .iter()
+            .position(|&bound| value <= bound)
+            .unwrap_or(self.bounds.len());
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .sum_bits
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 观测总数
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// 观测值之和
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.sum_bits.load(Ordering::Relaxed))
+    }
+
+    /// 按Prometheus格式输出分桶、总和与计数
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0u64;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let le = match self.bounds.get(i) {
+                Some(bound) => bound.to_string(),
+                None => "+Inf".to_string(),
+            };
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, le, cumulative
+            );
+        }
+        let braces = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels)
+        };
+        let _ = writeln!(out, "{}_sum{} {}", name, braces, self.sum());
+        let _ = writeln!(out, "{}_count{} {}", name, braces, self.count());
+    }
+}
+
+/// 单个路由的请求计数
+#[derive(Debug, Default, Clone, Copy)]
+struct RouteCounter {
+    requests: u64,
+    errors: u64,
+}
+
+/// 服务指标注册表
+#[derive(Debug)]
+pub struct Metrics {
+    /// 按(方法, 路由)统计的请求数与错误数
+    routes: Mutex<BTreeMap<(String, String), RouteCounter>>,
+    /// 各推理阶段耗时
+    stage_latency: [Histogram; 4],
+    /// 实时率分布
+    rtf: Histogram,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// 创建空的指标注册表
+    pub fn new() -> Self {
+        Self {
+            routes: Mutex::new(BTreeMap::new()),
+            stage_latency: std::array::from_fn(|_| Histogram::new(STAGE_LATENCY_BUCKETS)),
+            rtf: Histogram::new(RTF_BUCKETS),
+        }
+    }
+
+    /// 记录一次HTTP请求，状态码>=400计为错误
+    pub fn record_request(&self, method: &str, route: &str, status: u16) {
+        let mut routes = self.routes.lock().unwrap();
+        let counter = routes
+            .entry((method.to_string(), route.to_string()))
+            .or_default();
+        counter.requests += 1;
+        if status >= 400 {
+            counter.errors += 1;
+        }
+    }
+
+    /// 记录推理阶段耗时
+    pub fn observe_stage(&self, stage: InferenceStage, elapsed: Duration) {
+        self.stage_latency[stage.index()].observe(elapsed.as_secs_f64());
+    }
+
+    /// 记录一次合成的实时率
+    pub fn observe_rtf(&self, rtf: f64) {
+        self.rtf.observe(rtf);
+    }
+
+    /// 按Prometheus文本格式输出全部指标
+    ///
+    /// 队列深度与ONNX会话池占用从全局管理器读取，尚未初始化时省略；
+    /// 音色缓存统计由调用方传入。
+    pub fn render(&self, voice_cache: Option<&CacheStats>) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP rwkvtts_http_requests_total HTTP requests handled, by route.\n");
+        out.push_str("# TYPE rwkvtts_http_requests_total counter\n");
+        let routes = self.routes.lock().unwrap().clone();
+        for ((method, route), counter) in &routes {
+            let _ = writeln!(
+                out,
+                "rwkvtts_http_requests_total{{method=\"{}\",route=\"{}\"}} {}",
+                method, route, counter.requests
+            );
+        }
+        out.push_str(
+            "# HELP rwkvtts_http_request_errors_total HTTP requests answered with status >= 400, by route.\n",
+        );
+        out.push_str("# TYPE rwkvtts_http_request_errors_total counter\n");
+        for ((method, route), counter) in &routes {
+            let _ = writeln!(
+                out,
+                "rwkvtts_http_request_errors_total{{method=\"{}\",route=\"{}\"}} {}",
+                method, route, counter.errors
+            );
+        }
+
+        out.push_str("# HELP rwkvtts_stage_duration_seconds Latency of each inference stage.\n");
+        out.push_str("# TYPE rwkvtts_stage_duration_seconds histogram\n");
+        for stage in InferenceStage::ALL {
+            self.stage_latency[stage.index()].render(
+                &mut out,
+                "rwkvtts_stage_duration_seconds",
+                &format!("stage=\"{}\"", stage.as_str()),
+            );
+        }
+
+        out.push_str("# HELP rwkvtts_rtf Real-time factor of completed syntheses.\n");
+        out.push_str("# TYPE rwkvtts_rtf histogram\n");
+        self.rtf.render(&mut out, "rwkvtts_rtf", "");
+
+        if let Ok(manager) = get_global_dynamic_batch_manager() {
+            out.push_str(
+                "# HELP rwkvtts_queue_depth Requests queued for the dynamic batch scheduler.\n",
+            );
+            out.push_str("# TYPE rwkvtts_queue_depth gauge\n");
+            let _ = writeln!(out, "rwkvtts_queue_depth {}", manager.queue_depth());
+            out.push_str(
+                "# HELP rwkvtts_queue_capacity Maximum queued requests before rejecting (0 = unbounded).\n",
+            );
+            out.push_str("# TYPE rwkvtts_queue_capacity gauge\n");
+            let _ = writeln!(out, "rwkvtts_queue_capacity {}", manager.max_queue_depth());
+            out.push_str(
+                "# HELP rwkvtts_queue_rejected_total Requests rejected because the queue was full.\n",
+            );
+            out.push_str("# TYPE rwkvtts_queue_rejected_total counter\n");
+            let _ = writeln!(
+                out,
+                "rwkvtts_queue_rejected_total {}",
+                manager.rejected_requests()
+            );
+            out.push_str(
+                "# HELP rwkvtts_active_slots Requests currently decoding in model state slots.\n",
+            );
+            out.push_str("# TYPE rwkvtts_active_slots gauge\n");
+            let _ = writeln!(out, "rwkvtts_active_slots {}", manager.active_slots());
+            out.push_str("# HELP rwkvtts_slots Model state slots available for decoding.\n");
+            out.push_str("# TYPE rwkvtts_slots gauge\n");
+            let _ = writeln!(out, "rwkvtts_slots {}", manager.num_slots());
+        }
+
+        if let Ok(manager) = get_global_onnx_manager() {
+            let sizes = manager.get_pool_stats();
+            let in_use = manager.get_pool_in_use();
+            let pools = [
+                ("bicodec_tokenize", sizes.0, in_use.0),
+                ("wav2vec2", sizes.1, in_use.1),
+                ("bicodec_detokenize", sizes.2, in_use.2),
+            ];
+            out.push_str("# HELP rwkvtts_onnx_pool_size ONNX sessions in each pool.\n");
+            out.push_str("# TYPE rwkvtts_onnx_pool_size gauge\n");
+            for (pool, size, _) in pools {
+                let _ = writeln!(out, "rwkvtts_onnx_pool_size{{pool=\"{}\"}} {}", pool, size);
+            }
+            out.push_str("# HELP rwkvtts_onnx_pool_in_use ONNX sessions currently checked out.\n");
+            out.push_str("# TYPE rwkvtts_onnx_pool_in_use gauge\n");
+            for (pool, _, used) in pools {
+                let _ = writeln!(
+                    out,
+                    "rwkvtts_onnx_pool_in_use{{pool=\"{}\"}} {}",
+                    pool, used
+                );
+            }
+        }
+
+        if let Some(stats) = voice_cache {
+            out.push_str("# HELP rwkvtts_voice_cache_hits_total Voice feature cache hits.\n");
+            out.push_str("# TYPE rwkvtts_voice_cache_hits_total counter\n");
+            let _ = writeln!(out, "rwkvtts_voice_cache_hits_total {}", stats.cache_hits);
+            out.push_str("# HELP rwkvtts_voice_cache_misses_total Voice feature cache misses.\n");
+            out.push_str("# TYPE rwkvtts_voice_cache_misses_total counter\n");
+            let _ = writeln!(
+                out,
+                "rwkvtts_voice_cache_misses_total {}",
+                stats.cache_misses
+            );
+            out.push_str("# HELP rwkvtts_voices Voices known to the voice feature manager.\n");
+            out.push_str("# TYPE rwkvtts_voices gauge\n");
+            let _ = writeln!(out, "rwkvtts_voices {}", stats.total_voices);
+        }
+
+        out
+    }
+}
+
+/// 全局指标注册表
+static GLOBAL_METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// 获取全局指标注册表（首次访问时创建）
+pub fn global_metrics() -> &'static Metrics {
+    GLOBAL_METRICS.get_or_init(Metrics::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets() {
+        let histogram = Histogram::new(&[0.1, 1.0]);
+        histogram.observe(0.05);
+        histogram.observe(0.5);
+        histogram.observe(3.0);
+        histogram.observe(f64::NAN);
+
+        let mut out = String::new();
+        histogram.render(&mut out, "test", "");
+        assert!(out.contains("test_bucket{le=\"0.1\"} 1\n"));
+        assert!(out.contains("test_bucket{le=\"1\"} 2\n"));
+        assert!(out.contains("test_bucket{le=\"+Inf\"} 3\n"));
+        assert!(out.contains("test_count 3\n"));
+        assert!((histogram.sum() - 3.55).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::new();
+        metrics.record_request("POST", "/api/tts", 200);
+        metrics.record_request("POST", "/api/tts", 500);
+        metrics.observe_stage(InferenceStage::Semantic, Duration::from_millis(300));
+        metrics.observe_rtf(0.4);
+
+        let out = metrics.render(None);
+        assert!(out.contains("rwkvtts_http_requests_total{method=\"POST\",route=\"/api/tts\"} 2\n"));
+        assert!(out
+            .contains("rwkvtts_http_request_errors_total{method=\"POST\",route=\"/api/tts\"} 1\n"));
+        assert!(out
+            .contains("rwkvtts_stage_duration_seconds_bucket{stage=\"semantic\",le=\"0.5\"} 1\n"));
+        assert!(out.contains("rwkvtts_stage_duration_seconds_count{stage=\"prefill\"} 0\n"));
+        assert!(out.contains("rwkvtts_rtf_count 1\n"));
+    }
+}
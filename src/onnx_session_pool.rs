@@ -164,6 +164,11 @@ impl OnnxSessionPool {
     pub fn pool_size(&self) -> usize {
         self.pool_size
     }
+
+    /// 获取当前被占用的会话数
+    pub fn in_use(&self) -> usize {
+        self.pool_size - self.inner.semaphore.available_permits()
+    }
 }
 
 /// 会话守卫，自动归还会话索引与并发许可
@@ -276,6 +281,15 @@ impl ConcurrentOnnxManager {
             self.bicodec_detokenize_pool.pool_size(),
         )
     }
+
+    /// 获取各池当前被占用的会话数
+    pub fn get_pool_in_use(&self) -> (usize, usize, usize) {
+        (
+            self.bicodec_tokenize_pool.in_use(),
+            self.wav2vec2_pool.in_use(),
+            self.bicodec_detokenize_pool.in_use(),
+        )
+    }
 }
 
 /// 全局ONNX管理器单例
@@ -274,6 +274,28 @@ pub fn classify_speed(speed: f32) -> String {
     }
 }
 
+/// 将倍率形式的语速（OpenAI风格，1.0为正常语速，范围0.25-4.0）映射到SPEED_MAP中的语速档位
+///
+/// # Arguments
+/// * `ratio` - 语速倍率
+///
+/// # Returns
+/// * `&'static str` - SPEED_MAP中对应的语速属性
+pub fn classify_speed_ratio(ratio: f32) -> &'static str {
+    let index = if ratio <= 0.6 {
+        0
+    } else if ratio <= 0.85 {
+        1
+    } else if ratio < 1.2 {
+        2
+    } else if ratio < 1.6 {
+        3
+    } else {
+        4
+    };
+    SPEED_MAP[index].0
+}
+
 /// 将年龄字符串转换为对应的数值
 ///
 /// # 参数
@@ -0,0 +1,455 @@
+//! 客户端限流与配额
+//!
+//! 按API密钥（未启用认证时按IP）限流：每分钟请求数使用令牌桶，
+//! 每日字符数与音频秒数按UTC自然日累计。超限时返回建议的重试等待秒数。
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// 一组限额，未设置的字段表示不限制
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimits {
+    /// 每分钟请求数（令牌桶容量，按秒平滑补充）
+    pub requests_per_minute: Option<u32>,
+    /// 每日合成文本字符数
+    pub chars_per_day: Option<u64>,
+    /// 每日生成音频秒数
+    pub audio_seconds_per_day: Option<f64>,
+}
+
+impl RateLimits {
+    /// 用`overrides`中已设置的字段覆盖当前限额
+    fn merged(&self, overrides: &RateLimits) -> RateLimits {
+        RateLimits {
+            requests_per_minute: overrides.requests_per_minute.or(self.requests_per_minute),
+            chars_per_day: overrides.chars_per_day.or(self.chars_per_day),
+            audio_seconds_per_day: overrides
+                .audio_seconds_per_day
+                .or(self.audio_seconds_per_day),
+        }
+    }
+
+    fn validate(&self, owner: &str) -> Result<()> {
+        if self.requests_per_minute == Some(0) {
+            return Err(anyhow::anyhow!("{}: requests_per_minute必须大于0", owner));
+        }
+        if self.chars_per_day == Some(0) {
+            return Err(anyhow::anyhow!("{}: chars_per_day必须大于0", owner));
+        }
+        if let Some(seconds) = self.audio_seconds_per_day {
+            if seconds.is_nan() || seconds <= 0.0 {
+                return Err(anyhow::anyhow!("{}: audio_seconds_per_day必须大于0", owner));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 限流配置文件
+///
+/// ```toml
+/// # 反向代理后部署时信任X-Forwarded-For中的客户端IP
+/// trust_forwarded_for = false
+///
+/// [default]
+/// requests_per_minute = 60
+/// chars_per_day = 200000
+/// audio_seconds_per_day = 3600
+///
+/// # 按API密钥名称覆盖，未写的字段沿用default
+/// [keys.ops]
+/// requests_per_minute = 600
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// 是否使用X-Forwarded-For中的第一个地址作为客户端IP
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+    /// 默认限额，适用于所有密钥与IP
+    #[serde(default)]
+    pub default: RateLimits,
+    /// 按密钥名称覆盖的限额
+    #[serde(default)]
+    pub keys: HashMap<String, RateLimits>,
+}
+
+impl RateLimitConfig {
+    /// 从TOML文件加载并校验配置
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("读取限流配置 {} 失败: {}", path.display(), e))?;
+        Self::from_toml(&content)
+    }
+
+    /// 从TOML字符串解析并校验配置
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let config: RateLimitConfig =
+            toml::from_str(content).map_err(|e| anyhow::anyhow!("解析限流配置失败: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 校验所有限额均为正数
+    pub fn validate(&self) -> Result<()> {
+        self.default.validate("default")?;
+        for (name, limits) in &self.keys {
+            limits.validate(&format!("keys.{}", name))?;
+        }
+        Ok(())
+    }
+}
+
+/// 限流对象
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateLimitClient {
+    /// 已认证的API密钥（按密钥名称）
+    ApiKey(String),
+    /// 未认证的客户端IP
+    Ip(String),
+}
+
+impl RateLimitClient {
+    fn state_key(&self) -> String {
+        match self {
+            RateLimitClient::ApiKey(name) => format!("key:{}", name),
+            RateLimitClient::Ip(ip) => format!("ip:{}", ip),
+        }
+    }
+}
+
+impl std::fmt::Display for RateLimitClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateLimitClient::ApiKey(name) => write!(f, "密钥 {}", name),
+            RateLimitClient::Ip(ip) => write!(f, "IP {}", ip),
+        }
+    }
+}
+
+/// 触发的限额类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKind {
+    RequestsPerMinute,
+    CharsPerDay,
+    AudioSecondsPerDay,
+}
+
+/// 超出限额
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitExceeded {
+    pub kind: RateLimitKind,
+    /// 建议的重试等待秒数（用于Retry-After）
+    pub retry_after_secs: u64,
+}
+
+impl std::fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match self.kind {
+            RateLimitKind::RequestsPerMinute => "每分钟请求数",
+            RateLimitKind::CharsPerDay => "每日字符配额",
+            RateLimitKind::AudioSecondsPerDay => "每日音频时长配额",
+        };
+        write!(f, "已超出{}，请在{}秒后重试", what, self.retry_after_secs)
+    }
+}
+
+impl std::error::Error for RateLimitExceeded {}
+
+/// 单个客户端的限流状态
+#[derive(Debug)]
+struct ClientState {
+    tokens: f64,
+    last_refill: Instant,
+    day: NaiveDate,
+    chars_today: u64,
+    audio_seconds_today: f64,
+}
+
+impl ClientState {
+    fn new(limits: &RateLimits, now: Instant, today: NaiveDate) -> Self {
+        Self {
+            tokens: limits.requests_per_minute.unwrap_or(0) as f64,
+            last_refill: now,
+            day: today,
+            chars_today: 0,
+            audio_seconds_today: 0.0,
+        }
+    }
+
+    /// 跨越UTC日期时清零每日用量
+    fn roll_day(&mut self, today: NaiveDate) {
+        if self.day != today {
+            self.day = today;
+            self.chars_today = 0;
+            self.audio_seconds_today = 0.0;
+        }
+    }
+
+    /// 每日配额是否已用尽
+    fn daily_exhausted(&self, limits: &RateLimits) -> Option<RateLimitKind> {
+        if let Some(limit) = limits.chars_per_day {
+            if self.chars_today >= limit {
+                return Some(RateLimitKind::CharsPerDay);
+            }
+        }
+        if let Some(limit) = limits.audio_seconds_per_day {
+            if self.audio_seconds_today >= limit {
+                return Some(RateLimitKind::AudioSecondsPerDay);
+            }
+        }
+        None
+    }
+}
+
+/// 限流器
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    clients: Mutex<HashMap<String, ClientState>>,
+}
+
+impl RateLimiter {
+    /// 根据配置创建限流器
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 是否信任X-Forwarded-For
+    pub fn trust_forwarded_for(&self) -> bool {
+        self.config.trust_forwarded_for
+    }
+
+    /// 获取客户端生效的限额（密钥覆盖合并到默认限额之上）
+    pub fn limits_for(&self, client: &RateLimitClient) -> RateLimits {
+        match client {
+            RateLimitClient::ApiKey(name) => match self.config.keys.get(name) {
+                Some(overrides) => self.config.default.merged(overrides),
+                None => self.config.default.clone(),
+            },
+            RateLimitClient::Ip(_) => self.config.default.clone(),
+        }
+    }
+
+    /// 接纳一个请求：检查每日配额是否已用尽，并从令牌桶中取出一个令牌
+    pub fn check_request(&self, client: &RateLimitClient) -> Result<(), RateLimitExceeded> {
+        self.check_request_at(client, Instant::now(), Utc::now())
+    }
+
+    /// 扣除本次请求的文本字符数
+    pub fn charge_characters(
+        &self,
+        client: &RateLimitClient,
+        chars: u64,
+    ) -> Result<(), RateLimitExceeded> {
+        self.charge_characters_at(client, chars, Instant::now(), Utc::now())
+    }
+
+    /// 记录本次请求生成的音频秒数（合成完成后才知道，只累计不拒绝）
+    pub fn record_audio_seconds(&self, client: &RateLimitClient, seconds: f64) {
+        self.record_audio_seconds_at(client, seconds, Instant::now(), Utc::now())
+    }
+
+    fn check_request_at(
+        &self,
+        client: &RateLimitClient,
+        now: Instant,
+        utc_now: DateTime<Utc>,
+    ) -> Result<(), RateLimitExceeded> {
+        let limits = self.limits_for(client);
+        let mut clients = self.clients.lock().unwrap();
+        Self::prune_stale(&mut clients, utc_now.date_naive());
+        let state = clients
+            .entry(client.state_key())
+            .or_insert_with(|| ClientState::new(&limits, now, utc_now.date_naive()));
+        state.roll_day(utc_now.date_naive());
+
+        if let Some(kind) = state.daily_exhausted(&limits) {
+            return Err(RateLimitExceeded {
+                kind,
+                retry_after_secs: seconds_until_utc_midnight(utc_now),
+            });
+        }
+
+        if let Some(rpm) = limits.requests_per_minute {
+            let capacity = rpm as f64;
+            let refill_per_sec = capacity / 60.0;
+            let elapsed = now
+                .saturating_duration_since(state.last_refill)
+                .as_secs_f64();
+            state.tokens = (state.tokens + elapsed * refill_per_sec).min(capacity);
+            state.last_refill = now;
+
+            if state.tokens < 1.0 {
+                let wait = ((1.0 - state.tokens) / refill_per_sec).ceil() as u64;
+                return Err(RateLimitExceeded {
+                    kind: RateLimitKind::RequestsPerMinute,
+                    retry_after_secs: wait.max(1),
+                });
+            }
+            state.tokens -= 1.0;
+        }
+
+        Ok(())
+    }
+
+    fn charge_characters_at(
+        &self,
+        client: &RateLimitClient,
+        chars: u64,
+        now: Instant,
+        utc_now: DateTime<Utc>,
+    ) -> Result<(), RateLimitExceeded> {
+        let limits = self.limits_for(client);
+        let mut clients = self.clients.lock().unwrap();
+        let state = clients
+            .entry(client.state_key())
+            .or_insert_with(|| ClientState::new(&limits, now, utc_now.date_naive()));
+        state.roll_day(utc_now.date_naive());
+
+        if let Some(limit) = limits.chars_per_day {
+            if state.chars_today + chars > limit {
+                return Err(RateLimitExceeded {
+                    kind: RateLimitKind::CharsPerDay,
+                    retry_after_secs: seconds_until_utc_midnight(utc_now),
+                });
+            }
+        }
+        state.chars_today += chars;
+        Ok(())
+    }
+
+    fn record_audio_seconds_at(
+        &self,
+        client: &RateLimitClient,
+        seconds: f64,
+        now: Instant,
+        utc_now: DateTime<Utc>,
+    ) {
+        let limits = self.limits_for(client);
+        let mut clients = self.clients.lock().unwrap();
+        let state = clients
+            .entry(client.state_key())
+            .or_insert_with(|| ClientState::new(&limits, now, utc_now.date_naive()));
+        state.roll_day(utc_now.date_naive());
+        state.audio_seconds_today += seconds.max(0.0);
+    }
+
+    /// 客户端较多时清理前一天之前的状态，避免按IP限流时无限增长
+    fn prune_stale(clients: &mut HashMap<String, ClientState>, today: NaiveDate) {
+        const PRUNE_THRESHOLD: usize = 10_000;
+        if clients.len() >= PRUNE_THRESHOLD {
+            clients.retain(|_, state| state.day == today);
+        }
+    }
+}
+
+/// 距下一个UTC零点的秒数
+fn seconds_until_utc_midnight(utc_now: DateTime<Utc>) -> u64 {
+    let tomorrow = utc_now
+        .date_naive()
+        .succ_opt()
+        .unwrap_or(utc_now.date_naive());
+    let midnight = tomorrow.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    (midnight - utc_now).num_seconds().max(1) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::time::Duration;
+
+    const CONFIG: &str = r#"
+[default]
+requests_per_minute = 2
+chars_per_day = 100
+
+[keys.ops]
+requests_per_minute = 60
+audio_seconds_per_day = 10.0
+"#;
+
+    #[test]
+    fn test_limits_merge() {
+        let limiter = RateLimiter::new(RateLimitConfig::from_toml(CONFIG).unwrap());
+
+        let ops = limiter.limits_for(&RateLimitClient::ApiKey("ops".to_string()));
+        assert_eq!(ops.requests_per_minute, Some(60));
+        assert_eq!(ops.chars_per_day, Some(100));
+        assert_eq!(ops.audio_seconds_per_day, Some(10.0));
+
+        let ip = limiter.limits_for(&RateLimitClient::Ip("127.0.0.1".to_string()));
+        assert_eq!(ip.requests_per_minute, Some(2));
+        assert_eq!(ip.audio_seconds_per_day, None);
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(RateLimitConfig::from_toml(CONFIG).unwrap());
+        let client = RateLimitClient::Ip("10.0.0.1".to_string());
+        let start = Instant::now();
+        let utc = Utc.with_ymd_and_hms(2025, 9, 20, 12, 0, 0).unwrap();
+
+        assert!(limiter.check_request_at(&client, start, utc).is_ok());
+        assert!(limiter.check_request_at(&client, start, utc).is_ok());
+        let err = limiter.check_request_at(&client, start, utc).unwrap_err();
+        assert_eq!(err.kind, RateLimitKind::RequestsPerMinute);
+        assert_eq!(err.retry_after_secs, 30);
+
+        // 2次/分钟，30秒补充一个令牌
+        let later = start + Duration::from_secs(30);
+        assert!(limiter.check_request_at(&client, later, utc).is_ok());
+        assert!(limiter.check_request_at(&client, later, utc).is_err());
+
+        // 其他客户端互不影响
+        let other = RateLimitClient::Ip("10.0.0.2".to_string());
+        assert!(limiter.check_request_at(&other, start, utc).is_ok());
+    }
+
+    #[test]
+    fn test_daily_quotas() {
+        let limiter = RateLimiter::new(RateLimitConfig::from_toml(CONFIG).unwrap());
+        let client = RateLimitClient::ApiKey("ops".to_string());
+        let now = Instant::now();
+        let utc = Utc.with_ymd_and_hms(2025, 9, 20, 23, 0, 0).unwrap();
+
+        assert!(limiter.charge_characters_at(&client, 80, now, utc).is_ok());
+        let err = limiter
+            .charge_characters_at(&client, 30, now, utc)
+            .unwrap_err();
+        assert_eq!(err.kind, RateLimitKind::CharsPerDay);
+        assert_eq!(err.retry_after_secs, 3600);
+
+        limiter.record_audio_seconds_at(&client, 12.0, now, utc);
+        let err = limiter.check_request_at(&client, now, utc).unwrap_err();
+        assert_eq!(err.kind, RateLimitKind::AudioSecondsPerDay);
+
+        // 跨过UTC零点后配额重置
+        let next_day = Utc.with_ymd_and_hms(2025, 9, 21, 0, 0, 1).unwrap();
+        assert!(limiter.check_request_at(&client, now, next_day).is_ok());
+        assert!(limiter
+            .charge_characters_at(&client, 100, now, next_day)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(RateLimitConfig::from_toml("[default]\nrequests_per_minute = 0\n").is_err());
+        assert!(RateLimitConfig::from_toml("[keys.a]\naudio_seconds_per_day = -1.0\n").is_err());
+        assert!(RateLimitConfig::from_toml("").is_ok());
+        // 拼错的字段名不会被静默忽略
+        assert!(RateLimitConfig::from_toml("[default]\nrequest_per_minute = 60\n").is_err());
+        assert!(RateLimitConfig::from_toml("trust_forwarded = true\n").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! 请求调度队列
+//!
+//! 批处理收集线程把到达的请求放入调度队列，每轮按以下规则选出下一批：
+//!
+//! 1. 有效优先级高者优先。请求每等待`aging`提升一级，低优先级请求最终会与交互式请求平级，不会饿死
+//! 2. 同一有效优先级内，截止时间早者优先；没有截止时间的排在有截止时间的之后
+//! 3. 以上都相同时按提交顺序
+
+use std::time::{Duration, Instant};
+
+use crate::batch_types::RequestPriority;
+
+/// 按优先级、截止时间和等待时间排序的调度队列
+#[derive(Debug)]
+pub struct RequestScheduler<T> {
+    entries: Vec<Entry<T>>,
+    aging: Duration,
+    next_seq: u64,
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    item: T,
+    priority: RequestPriority,
+    deadline: Option<Instant>,
+    submitted_at: Instant,
+    /// 入队序号，保证排序稳定
+    seq: u64,
+}
+
+impl<T> RequestScheduler<T> {
+    /// 创建调度队列，`aging`为提升一级优先级所需的等待时间
+    pub fn new(aging: Duration) -> Self {
+        Self {
+            entries: Vec::new(),
+            aging: aging.max(Duration::from_millis(1)),
+            next_seq: 0,
+        }
+    }
+
+    /// 加入一个请求
+    pub fn push(
+        &mut self,
+        item: T,
+        priority: RequestPriority,
+        deadline: Option<Instant>,
+        submitted_at: Instant,
+    ) {
+        self.entries.push(Entry {
+            item,
+            priority,
+            deadline,
+            submitted_at,
+            seq: self.next_seq,
+        });
+        self.next_seq += 1;
+    }
+
+    /// 取出下一批（最多`max`个）请求，按调度顺序排列
+    ///
+    /// 解码循环每一步都会调用，没有空闲槽位或队列为空时直接返回，不排序。
+    pub fn pop_batch(&mut self, max: usize, now: Instant) -> Vec<T> {
+        if max == 0 || self.entries.is_empty() {
+            return Vec::new();
+        }
+        let aging = self.aging;
+        self.entries.sort_by_cached_key(|entry| {
+            (
+                effective_rank(
+                    entry.priority,
+                    now.saturating_duration_since(entry.submitted_at),
+                    aging,
+                ),
+                entry.deadline.is_none(),
+                entry.deadline,
+                entry.seq,
+            )
+        });
+        let count = max.min(self.entries.len());
+        self.entries
+            .drain(..count)
+            .map(|entry| entry.item)
+            .collect()
+    }
+
+    /// 移出所有满足条件的请求（如排队期间已取消的请求），按入队顺序返回
+    pub fn remove_if(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Vec<T> {
+        let (removed, kept) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|entry| predicate(&entry.item));
+        self.entries = kept;
+        removed.into_iter().map(|entry| entry.item).collect()
+    }
+
+    /// 排队中的请求数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// 考虑等待时间后的调度等级，数值越小越优先
+fn effective_rank(priority: RequestPriority, waited: Duration, aging: Duration) -> u8 {
+    let promoted = (waited.as_millis() / aging.as_millis()).min(u8::MAX as u128) as u8;
+    priority.rank().saturating_sub(promoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_and_deadline_order() {
+        let now = Instant::now();
+        let mut scheduler = RequestScheduler::new(Duration::from_secs(10));
+        scheduler.push("bulk", RequestPriority::Bulk, None, now);
+        scheduler.push("normal", RequestPriority::Normal, None, now);
+        scheduler.push(
+            "normal_late_deadline",
+            RequestPriority::Normal,
+            Some(now + Duration::from_secs(30)),
+            now,
+        );
+        scheduler.push(
+            "normal_deadline",
+            RequestPriority::Normal,
+            Some(now + Duration::from_secs(5)),
+            now,
+        );
+        scheduler.push("interactive", RequestPriority::Interactive, None, now);
+        assert_eq!(scheduler.len(), 5);
+
+        // 没有空闲槽位时不取出任何请求
+        assert!(scheduler.pop_batch(0, now).is_empty());
+        assert_eq!(scheduler.len(), 5);
+
+        assert_eq!(
+            scheduler.pop_batch(4, now),
+            vec![
+                "interactive",
+                "normal_deadline",
+                "normal_late_deadline",
+                "normal"
+            ]
+        );
+        assert_eq!(scheduler.pop_batch(4, now), vec!["bulk"]);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_remove_if() {
+        let now = Instant::now();
+        let mut scheduler = RequestScheduler::new(Duration::from_secs(10));
+        for item in [1, 2, 3, 4] {
+            scheduler.push(item, RequestPriority::Normal, None, now);
+        }
+
+        assert_eq!(scheduler.remove_if(|&item| item % 2 == 0), vec![2, 4]);
+        assert_eq!(scheduler.len(), 2);
+        assert!(scheduler.remove_if(|_| false).is_empty());
+        assert_eq!(scheduler.pop_batch(4, now), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_aging_prevents_starvation() {
+        let start = Instant::now();
+        let mut scheduler = RequestScheduler::new(Duration::from_secs(5));
+        scheduler.push("bulk", RequestPriority::Bulk, None, start);
+
+        // 等待10秒后批量请求提升两级，与新到的交互式请求平级，按提交顺序先调度
+        let now = start + Duration::from_secs(10);
+        scheduler.push("interactive", RequestPriority::Interactive, None, now);
+        scheduler.push("normal", RequestPriority::Normal, None, now);
+        assert_eq!(scheduler.pop_batch(1, now), vec!["bulk"]);
+        assert_eq!(scheduler.len(), 2);
+
+        // 未达到老化间隔时仍按优先级
+        scheduler.push("bulk_new", RequestPriority::Bulk, None, now);
+        assert_eq!(
+            scheduler.pop_batch(3, now + Duration::from_secs(4)),
+            vec!["interactive", "normal", "bulk_new"]
+        );
+    }
+}
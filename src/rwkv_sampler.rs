@@ -228,6 +228,12 @@ pub struct TtsBatchRequest {
     pub args: SamplerArgs,
     /// 音色ID，用于从缓存中快速获取tokens
     pub voice_id: Option<String>,
+    /// 可选的token事件通道，用于流式输出
+    pub token_tx: Option<flume::Sender<crate::batch_types::TtsTokenEvent>>,
+    /// 取消令牌，推理循环每个解码步骤前检查
+    pub cancel: crate::batch_types::CancelToken,
+    /// 调度优先级
+    pub priority: crate::batch_types::RequestPriority,
 }
 
 /// 采样参数
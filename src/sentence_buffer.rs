@@ -0,0 +1,146 @@
+//! 增量文本分句
+//!
+//! 用于LLM逐token输出文本的场景：缓存文本片段，遇到句末标点时切出完整句子送去合成。
+
+/// 句末标点（英文句号需后跟空白才视为句末，见`SentenceBuffer::push`）
+pub const SENTENCE_DELIMITERS: &[char] = &['。', '！', '？', '!', '?', '；', ';', '\n'];
+
+/// 句内停顿标点，单句过长时在此处断开
+pub const CLAUSE_DELIMITERS: &[char] = &['，', ',', '、', '：', ':'];
+
+/// 紧跟句末标点、应归入上一句的闭合符号
+const CLOSING_MARKS: &[char] = &['”', '’', '」', '』', '）', ')', '"', '\''];
+
+/// 增量分句缓冲区
+#[derive(Debug, Clone)]
+pub struct SentenceBuffer {
+    buffer: String,
+    max_chars: usize,
+}
+
+impl SentenceBuffer {
+    /// 创建缓冲区，无句末标点的文本超过`max_chars`个字符时强制切分
+    pub fn new(max_chars: usize) -> Self {
+        Self {
+            buffer: String::new(),
+            max_chars: max_chars.max(1),
+        }
+    }
+
+    /// 追加文本片段，返回已完整的句子
+    pub fn push(&mut self, fragment: &str) -> Vec<String> {
+        self.buffer.push_str(fragment);
+
+        let mut sentences = Vec::new();
+        while let Some(end) = self.find_boundary() {
+            let sentence: String = self.buffer.drain(..end).collect();
+            push_trimmed(&mut sentences, &sentence);
+        }
+
+        // 长时间没有句末标点时，在停顿处或按长度切分，避免缓冲区无限增长
+        while self.buffer.chars().count() > self.max_chars {
+            let end = self.overflow_boundary();
+            let sentence: String = self.buffer.drain(..end).collect();
+            push_trimmed(&mut sentences, &sentence);
+        }
+
+        sentences
+    }
+
+    /// 取出缓冲区中剩余的文本（不论是否以句末标点结尾）
+    pub fn flush(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buffer);
+        let rest = rest.trim();
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+
+    /// 丢弃缓冲区内容
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// 缓冲区是否为空
+    pub fn is_empty(&self) -> bool {
+        self.buffer.trim().is_empty()
+    }
+
+    /// 查找第一个句末位置（字节偏移，包含标点及其后的闭合符号）
+    fn find_boundary(&self) -> Option<usize> {
+        let mut chars = self.buffer.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            let is_boundary = if SENTENCE_DELIMITERS.contains(&c) {
+                true
+            } else if c == '.' {
+                // 英文句号后跟空白才是句末，避免切开小数和缩写；末尾的句号等待后续片段
+                matches!(chars.peek(), Some((_, next)) if next.is_whitespace())
+            } else {
+                false
+            };
+            if !is_boundary {
+                continue;
+            }
+
+            let mut end = index + c.len_utf8();
+            while let Some(&(next_index, next)) = chars.peek() {
+                if !CLOSING_MARKS.contains(&next) {
+                    break;
+                }
+                end = next_index + next.len_utf8();
+                chars.next();
+            }
+            return Some(end);
+        }
+        None
+    }
+
+    /// 缓冲区超长时的切分位置：前`max_chars`个字符内最后一个停顿标点之后，没有则按长度切分
+    fn overflow_boundary(&self) -> usize {
+        let mut hard_end = self.buffer.len();
+        let mut clause_end = None;
+        for (count, (index, c)) in self.buffer.char_indices().enumerate() {
+            if count == self.max_chars {
+                hard_end = index;
+                break;
+            }
+            if CLAUSE_DELIMITERS.contains(&c) {
+                clause_end = Some(index + c.len_utf8());
+            }
+        }
+        clause_end.unwrap_or(hard_end)
+    }
+}
+
+fn push_trimmed(sentences: &mut Vec<String>, sentence: &str) {
+    let sentence = sentence.trim();
+    if !sentence.is_empty() {
+        sentences.push(sentence.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incremental_sentences() {
+        let mut buffer = SentenceBuffer::new(100);
+        assert!(buffer.push("你好").is_empty());
+        assert_eq!(buffer.push("，世界。今天"), vec!["你好，世界。"]);
+        assert_eq!(buffer.push("天气“很好！”明天"), vec!["今天天气“很好！”"]);
+
+        // 英文句号需后跟空白，小数不会被切开
+        assert!(buffer.push(" Pi is 3.14.").is_empty());
+        assert_eq!(buffer.push(" OK"), vec!["明天 Pi is 3.14."]);
+        assert_eq!(buffer.flush(), Some("OK".to_string()));
+        assert!(buffer.flush().is_none());
+    }
+
+    #[test]
+    fn test_overflow_split() {
+        let mut buffer = SentenceBuffer::new(5);
+        assert_eq!(buffer.push("一二，三四五六七"), vec!["一二，"]);
+        assert_eq!(buffer.push("八"), vec!["三四五六七"]);
+        buffer.clear();
+        assert!(buffer.is_empty());
+    }
+}
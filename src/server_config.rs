@@ -0,0 +1,482 @@
+//! 服务器配置文件
+//!
+//! 通过`--config server.toml`加载，所有字段都有默认值，只需写出要修改的项；
+//! 命令行参数优先于配置文件。未知字段会报错，避免拼写错误被静默忽略。
+//!
+//! ```toml
+//! [listen]
+//! host = "0.0.0.0"
+//! port = 3000
+//! # "*"表示允许任意来源，也可以列出具体来源
+//! cors_origins = ["https://app.example.com"]
+//! # 收到SIGTERM/SIGINT后等待进行中请求完成的时间（秒）
+//! shutdown_grace_secs = 30
+//!
+//! [model]
+//! model_path = "assets/model/webrwkv.safetensors"
+//! vocab_path = "assets/model/tokenizer.json"
+//! quant_layers = 24
+//! quant_type = "int8"
+//!
+//! [onnx]
+//! tokenize_path = "assets/model/BiCodecTokenize.onnx"
+//! wav2vec2_path = "assets/model/wav2vec2-large-xlsr-53.onnx"
+//! detokenize_path = "assets/model/BiCodecDetokenize.onnx"
+//! pool_size = 4
+//!
+//! [batch]
+//! max_batch_size = 10
+//! collect_timeout_ms = 20
+//! inference_timeout_ms = 120000
+//! token_chunk_size = 256
+//! # 排队请求上限，队列满时新请求返回503（0表示不限制）
+//! max_queue_depth = 256
+//! # 低优先级请求每等待这么久提升一级优先级（毫秒）
+//! priority_aging_ms = 5000
+//! # 不设置时按max_batch_size自动计算
+//! # max_concurrent_batches = 10
+//!
+//! [storage]
+//! raf_dir = "assets/raf"
+//! jobs_dir = "jobs"
+//!
+//! [jobs]
+//! workers = 1
+//! webhook_secret = "change-me"
+//! public_url = "https://tts.example.com"
+//!
+//! [log]
+//! level = "info"
+//! directives = ["ort=warn", "web_rwkv=warn", "naga=warn", "wgpu=warn"]
+//!
+//! # 格式同--auth-config
+//! [auth]
+//! allow_anonymous_ui = false
+//! [[auth.keys]]
+//! name = "ops"
+//! key = "sk-admin-xxxxxxxxxxxxxxxx"
+//! scope = "admin"
+//!
+//! # 格式同--rate-limit-config
+//! [rate_limit.default]
+//! requests_per_minute = 60
+//! ```
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use tracing_subscriber::filter::{Directive, LevelFilter};
+
+use crate::api_auth::AuthConfig;
+use crate::batch_types::DynamicBatchConfig;
+use crate::rate_limit::RateLimitConfig;
+
+/// 支持的量化类型
+pub const QUANT_TYPES: &[&str] = &["none", "int8", "nf4", "sf4"];
+
+/// 监听配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenConfig {
+    /// 监听地址
+    pub host: IpAddr,
+    /// 监听端口
+    pub port: u16,
+    /// 允许跨域访问的来源，包含"*"时允许任意来源
+    pub cors_origins: Vec<String>,
+    /// 优雅关闭宽限期（秒），超时后未完成的请求以错误返回
+    pub shutdown_grace_secs: u64,
+}
+
+impl Default for ListenConfig {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 3000,
+            cors_origins: vec!["*".to_string()],
+            shutdown_grace_secs: 30,
+        }
+    }
+}
+
+/// RWKV模型与量化配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelConfig {
+    /// 模型文件路径
+    pub model_path: String,
+    /// 词汇表文件路径
+    pub vocab_path: String,
+    /// 量化层数，0表示不量化
+    pub quant_layers: usize,
+    /// 量化类型：none、int8、nf4、sf4
+    pub quant_type: String,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        Self {
+            model_path: "assets/model/webrwkv.safetensors".to_string(),
+            vocab_path: "assets/model/tokenizer.json".to_string(),
+            quant_layers: 0,
+            quant_type: "none".to_string(),
+        }
+    }
+}
+
+/// ONNX模型与会话池配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OnnxConfig {
+    /// BiCodec编码模型
+    pub tokenize_path: String,
+    /// wav2vec2特征提取模型
+    pub wav2vec2_path: String,
+    /// BiCodec解码模型
+    pub detokenize_path: String,
+    /// 每种模型的会话池大小
+    pub pool_size: usize,
+}
+
+impl Default for OnnxConfig {
+    fn default() -> Self {
+        Self {
+            tokenize_path: "assets/model/BiCodecTokenize.onnx".to_string(),
+            wav2vec2_path: "assets/model/wav2vec2-large-xlsr-53.onnx".to_string(),
+            detokenize_path: "assets/model/BiCodecDetokenize.onnx".to_string(),
+            pool_size: 4,
+        }
+    }
+}
+
+impl OnnxConfig {
+    /// 所有ONNX模型路径
+    pub fn model_paths(&self) -> [&str; 3] {
+        [
+            &self.tokenize_path,
+            &self.wav2vec2_path,
+            &self.detokenize_path,
+        ]
+    }
+}
+
+/// 动态批处理配置（对应`DynamicBatchConfig`）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatchConfig {
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    pub collect_timeout_ms: u64,
+    pub inference_timeout_ms: u64,
+    pub token_chunk_size: usize,
+    /// 排队请求上限，0表示不限制
+    pub max_queue_depth: usize,
+    /// 优先级老化间隔（毫秒）
+    pub priority_aging_ms: u64,
+    /// 最大并发批次数，不设置时按max_batch_size自动计算
+    pub max_concurrent_batches: Option<usize>,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            min_batch_size: 1,
+            max_batch_size: 10,
+            collect_timeout_ms: 20,
+            inference_timeout_ms: 120000,
+            token_chunk_size: 256,
+            max_queue_depth: 256,
+            priority_aging_ms: 5000,
+            max_concurrent_batches: None,
+        }
+    }
+}
+
+impl BatchConfig {
+    /// 转换为动态批处理管理器配置，补全自动计算的字段
+    pub fn to_dynamic_batch_config(&self) -> DynamicBatchConfig {
+        let max_concurrent_batches = self.max_concurrent_batches.unwrap_or_else(|| {
+            if self.max_batch_size <= 10 {
+                10
+            } else {
+                std::cmp::max(8, self.max_batch_size / 10)
+            }
+        });
+
+        DynamicBatchConfig {
+            min_batch_size: self.min_batch_size,
+            max_batch_size: self.max_batch_size,
+            collect_timeout_ms: self.collect_timeout_ms,
+            inference_timeout_ms: self.inference_timeout_ms,
+            max_concurrent_batches,
+            token_chunk_size: self.token_chunk_size,
+            max_queue_depth: self.max_queue_depth,
+            priority_aging_ms: self.priority_aging_ms,
+        }
+    }
+}
+
+/// 数据目录配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// 音色特征目录
+    pub raf_dir: String,
+    /// 异步任务状态与输出目录
+    pub jobs_dir: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            raf_dir: "assets/raf".to_string(),
+            jobs_dir: "jobs".to_string(),
+        }
+    }
+}
+
+/// 异步任务配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobsConfig {
+    /// 并行执行的任务数
+    pub workers: usize,
+    /// 任务回调签名密钥，不设置则不支持callback_url
+    pub webhook_secret: Option<String>,
+    /// 服务对外地址，用于生成回调中的下载链接；未设置时回调通知不含下载链接
+    pub public_url: Option<String>,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            workers: 1,
+            webhook_secret: None,
+            public_url: None,
+        }
+    }
+}
+
+/// 日志配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// 默认日志级别：trace、debug、info、warn、error、off
+    pub level: String,
+    /// 额外的过滤指令，如"ort=warn"
+    pub directives: Vec<String>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "debug".to_string(),
+            directives: ["ort=warn", "web_rwkv=warn", "naga=warn", "wgpu=warn"]
+                .iter()
+                .map(|d| d.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// 服务器配置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen: ListenConfig,
+    pub model: ModelConfig,
+    pub onnx: OnnxConfig,
+    pub batch: BatchConfig,
+    pub storage: StorageConfig,
+    pub jobs: JobsConfig,
+    pub log: LogConfig,
+    /// API密钥认证，不设置则不启用认证
+    pub auth: Option<AuthConfig>,
+    /// 限流与配额，不设置则不限流
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+impl ServerConfig {
+    /// 从TOML文件加载并校验配置
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("读取配置文件 {} 失败: {}", path.display(), e))?;
+        Self::from_toml(&content).map_err(|e| anyhow!("配置文件 {} 无效: {}", path.display(), e))
+    }
+
+    /// 从TOML字符串解析并校验配置
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let config: ServerConfig =
+            toml::from_str(content).map_err(|e| anyhow!("解析配置失败: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 校验配置取值（命令行参数覆盖后需再次调用）
+    pub fn validate(&self) -> Result<()> {
+        if self.listen.port == 0 {
+            return Err(anyhow!("listen.port必须大于0"));
+        }
+        for origin in &self.listen.cors_origins {
+            if origin != "*" && !origin.starts_with("http://") && !origin.starts_with("https://") {
+                return Err(anyhow!(
+                    "listen.cors_origins中的 {} 无效，应为\"*\"或以http://、https://开头",
+                    origin
+                ));
+            }
+        }
+
+        require_non_empty("model.model_path", &self.model.model_path)?;
+        require_non_empty("model.vocab_path", &self.model.vocab_path)?;
+        if !QUANT_TYPES.contains(&self.model.quant_type.to_lowercase().as_str()) {
+            return Err(anyhow!(
+                "model.quant_type不支持 {}，可选: {}",
+                self.model.quant_type,
+                QUANT_TYPES.join(", ")
+            ));
+        }
+
+        require_non_empty("onnx.tokenize_path", &self.onnx.tokenize_path)?;
+        require_non_empty("onnx.wav2vec2_path", &self.onnx.wav2vec2_path)?;
+        require_non_empty("onnx.detokenize_path", &self.onnx.detokenize_path)?;
+        require_positive("onnx.pool_size", self.onnx.pool_size as u64)?;
+
+        let batch = &self.batch;
+        require_positive("batch.min_batch_size", batch.min_batch_size as u64)?;
+        require_positive("batch.max_batch_size", batch.max_batch_size as u64)?;
+        if batch.min_batch_size > batch.max_batch_size {
+            return Err(anyhow!(
+                "batch.min_batch_size（{}）不能大于batch.max_batch_size（{}）",
+                batch.min_batch_size,
+                batch.max_batch_size
+            ));
+        }
+        require_positive("batch.collect_timeout_ms", batch.collect_timeout_ms)?;
+        require_positive("batch.inference_timeout_ms", batch.inference_timeout_ms)?;
+        require_positive("batch.token_chunk_size", batch.token_chunk_size as u64)?;
+        require_positive("batch.priority_aging_ms", batch.priority_aging_ms)?;
+        if let Some(batches) = batch.max_concurrent_batches {
+            require_positive("batch.max_concurrent_batches", batches as u64)?;
+        }
+
+        require_non_empty("storage.raf_dir", &self.storage.raf_dir)?;
+        require_non_empty("storage.jobs_dir", &self.storage.jobs_dir)?;
+
+        require_positive("jobs.workers", self.jobs.workers as u64)?;
+        if let Some(secret) = &self.jobs.webhook_secret {
+            require_non_empty("jobs.webhook_secret", secret)?;
+        }
+        if let Some(url) = &self.jobs.public_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(anyhow!("jobs.public_url应以http://或https://开头: {}", url));
+            }
+        }
+
+        self.log
+            .level
+            .parse::<LevelFilter>()
+            .map_err(|_| anyhow!("log.level无效: {}", self.log.level))?;
+        for directive in &self.log.directives {
+            directive
+                .parse::<Directive>()
+                .map_err(|e| anyhow!("log.directives中的 {} 无效: {}", directive, e))?;
+        }
+
+        if let Some(auth) = &self.auth {
+            auth.validate().map_err(|e| anyhow!("auth: {}", e))?;
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit
+                .validate()
+                .map_err(|e| anyhow!("rate_limit.{}", e))?;
+        }
+
+        Ok(())
+    }
+
+    /// 是否允许任意来源跨域访问
+    pub fn allows_any_origin(&self) -> bool {
+        self.listen.cors_origins.iter().any(|origin| origin == "*")
+    }
+}
+
+fn require_non_empty(key: &str, value: &str) -> Result<()> {
+    if value.trim().is_empty() {
+        return Err(anyhow!("{}不能为空", key));
+    }
+    Ok(())
+}
+
+fn require_positive(key: &str, value: u64) -> Result<()> {
+    if value == 0 {
+        return Err(anyhow!("{}必须大于0", key));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_overrides() {
+        let config = ServerConfig::from_toml("").unwrap();
+        assert_eq!(config.listen.port, 3000);
+        assert!(config.allows_any_origin());
+        assert!(config.auth.is_none());
+
+        let config = ServerConfig::from_toml(
+            r#"
+[listen]
+host = "127.0.0.1"
+port = 8080
+cors_origins = ["https://app.example.com"]
+shutdown_grace_secs = 5
+
+[batch]
+max_batch_size = 40
+max_queue_depth = 0
+
+[auth]
+[[auth.keys]]
+name = "ops"
+key = "sk-admin-0123456789abcdef"
+scope = "admin"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.listen.host.to_string(), "127.0.0.1");
+        assert_eq!(config.listen.shutdown_grace_secs, 5);
+        assert!(!config.allows_any_origin());
+        assert_eq!(config.model.quant_type, "none");
+        assert_eq!(config.auth.unwrap().keys.len(), 1);
+
+        let batch = config.batch.to_dynamic_batch_config();
+        assert_eq!(batch.max_batch_size, 40);
+        assert_eq!(batch.max_concurrent_batches, 8);
+        assert_eq!(batch.max_queue_depth, 0);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let error = |content: &str| ServerConfig::from_toml(content).unwrap_err().to_string();
+
+        assert!(error("[listen]\nprot = 80\n").contains("prot"));
+        assert!(error("[listen]\nhost = \"localhost\"\n").contains("host"));
+        assert!(error("[model]\nquant_type = \"int4\"\n").contains("model.quant_type"));
+        assert!(error("[batch]\nmax_batch_size = 0\n").contains("batch.max_batch_size"));
+        assert!(error("[batch]\npriority_aging_ms = 0\n").contains("batch.priority_aging_ms"));
+        // 已移除的配置项按未知字段报错
+        assert!(error("[batch]\nsemaphore_permits = 4\n").contains("semaphore_permits"));
+        assert!(error("[batch]\nmin_batch_size = 4\nmax_batch_size = 2\n")
+            .contains("batch.min_batch_size"));
+        assert!(error("[log]\nlevel = \"verbose\"\n").contains("log.level"));
+        assert!(error("[listen]\ncors_origins = [\"example.com\"]\n").contains("cors_origins"));
+        assert!(error("[auth]\nkeys = []\n").contains("auth"));
+        assert!(
+            error("[rate_limit.default]\nrequest_per_minute = 60\n").contains("request_per_minute")
+        );
+    }
+}
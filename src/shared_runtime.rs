@@ -10,7 +10,7 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock, Semaphore};
+use tokio::sync::{Mutex, RwLock};
 // 删除未使用的导入
 use web_rwkv::runtime::loader::Loader;
 use web_rwkv::runtime::model::{Bundle, State};
@@ -35,8 +35,6 @@ pub struct TtsInferContext {
     pub runtime: Arc<web_rwkv::runtime::TokioRuntime<web_rwkv::runtime::infer::Rnn>>,
     /// 模型状态（独立副本）- 重新添加以确保状态隔离
     pub state: Arc<Mutex<Box<dyn State + Send + Sync>>>,
-    /// Serialize runtime.infer calls for correctness under concurrency
-    pub runtime_semaphore: Arc<Semaphore>,
 }
 
 /// 共享的RWKV Runtime实例
@@ -60,9 +58,8 @@ pub struct SharedRwkvRuntime {
     /// 词汇表路径
     #[allow(dead_code)]
     vocab_path: String,
-    /// A semaphore to control concurrent inference calls
-    /// The number of permits should be configured based on GPU capabilities
-    runtime_semaphore: Arc<Semaphore>,
+    /// 模型状态槽位数（即一次解码可容纳的请求数）
+    num_slots: usize,
 }
 
 impl SharedRwkvRuntime {
@@ -174,11 +171,7 @@ impl SharedRwkvRuntime {
             seed.deserialize(&mut deserializer)
                 .map_err(|e| anyhow::anyhow!("Failed to deserialize prefab model: {}", e))?
         };
-        let model_bundle = Arc::new(v7::Bundle::new(model, config.max_concurrent_batches));
-
-        // 使用配置中的信号量许可数量
-        let semaphore_permits = config.semaphore_permits;
-        // 设置信号量许可数量
+        let model_bundle = Arc::new(v7::Bundle::new(model, config.max_concurrent_batches.max(1)));
 
         // 创建TokioRuntime实例
         let runtime = Arc::new(web_rwkv::runtime::TokioRuntime::new((*model_bundle).clone()).await);
@@ -202,8 +195,7 @@ impl SharedRwkvRuntime {
             active_states: Arc::new(RwLock::new(HashMap::new())),
             model_path,
             vocab_path,
-            // 使用配置中的信号量许可数量
-            runtime_semaphore: Arc::new(Semaphore::new(semaphore_permits)),
+            num_slots: config.max_concurrent_batches.max(1),
         })
     }
 
@@ -245,7 +237,6 @@ impl SharedRwkvRuntime {
             tokenizer: self.tokenizer.clone(),
             runtime: self.runtime.clone(),
             state, // 添加独立状态
-            runtime_semaphore: self.runtime_semaphore.clone(),
         })
     }
 
@@ -274,6 +265,11 @@ impl SharedRwkvRuntime {
         &self.runtime
     }
 
+    /// 模型状态槽位数
+    pub fn num_slots(&self) -> usize {
+        self.num_slots
+    }
+
     /// 获取状态统计信息
     pub async fn stats(&self) -> crate::tts_state_manager::TtsStateStats {
         let active = self.active_states.read().await;
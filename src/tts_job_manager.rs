@@ -0,0 +1,790 @@
+//! 异步TTS任务管理
+//!
+//! 长文本按句切分后在后台逐段合成，绕开单次请求的HTTP超时和semantic token上限。
+//! 任务状态与输出持久化在任务目录下：`<jobs_dir>/<job_id>/job.json` 与
+//! `<jobs_dir>/<job_id>/output.<ext>`。服务重启后，未完成的任务重新排队并从头合成。
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::fs as async_fs;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::audio_encoder::{
+    encode_audio, resample, validate_output_sample_rate, AudioEncodeOptions, AudioFormat,
+    MODEL_SAMPLE_RATE,
+};
+use crate::batch_types::{CancelToken, QueueFull};
+use crate::job_webhook::WebhookNotifier;
+use crate::lightweight_tts_pipeline::{LightweightTtsPipeline, LightweightTtsPipelineArgs};
+use crate::sentence_buffer::{CLAUSE_DELIMITERS, SENTENCE_DELIMITERS};
+
+/// 每段文本的最大字符数（远低于单次推理的semantic token上限）
+pub const DEFAULT_SEGMENT_MAX_CHARS: usize = 150;
+
+/// 段落之间插入的静音时长（秒）
+const SEGMENT_SILENCE_SECS: f32 = 0.2;
+
+/// 任务状态文件名
+const JOB_FILE_NAME: &str = "job.json";
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// 等待执行
+    Queued,
+    /// 合成中
+    Running,
+    /// 已完成，可下载
+    Completed,
+    /// 合成失败
+    Failed,
+    /// 已取消
+    Cancelled,
+}
+
+impl JobStatus {
+    /// 是否为终止状态
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// 任务输出设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobOutput {
+    /// 输出格式名称，见`AudioFormat::from_name`
+    pub format: String,
+    /// 输出采样率
+    pub sample_rate: u32,
+    /// 有损格式的比特率（kbps）
+    pub bitrate_kbps: Option<u32>,
+}
+
+impl JobOutput {
+    /// 解析输出格式
+    pub fn audio_format(&self) -> Result<AudioFormat> {
+        AudioFormat::from_name(&self.format)
+            .ok_or_else(|| anyhow!("不支持的输出格式: {}", self.format))
+    }
+}
+
+/// 异步合成任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TtsJob {
+    pub id: String,
+    pub status: JobStatus,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// 文本切分后的段数
+    pub segments_total: usize,
+    /// 已合成的段数
+    pub segments_done: usize,
+    /// 文本字符数
+    pub text_chars: usize,
+    pub output: JobOutput,
+    /// 输出文件名（位于任务目录内）
+    pub output_file: Option<String>,
+    /// 输出音频时长（秒）
+    pub audio_duration_secs: Option<f64>,
+    /// 合成耗时与音频时长之比
+    pub rtf: Option<f64>,
+    pub error: Option<String>,
+    /// 任务结束后接收通知的地址
+    #[serde(default)]
+    pub callback_url: Option<String>,
+    /// 回调投递结果，None表示尚未投递
+    #[serde(default)]
+    pub callback_delivered: Option<bool>,
+    /// 提交任务的API密钥名称，None表示未启用鉴权时提交
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// 合成参数（已解析音色特征）
+    pub args: LightweightTtsPipelineArgs,
+}
+
+impl TtsJob {
+    /// 合成进度（0.0-1.0）
+    pub fn progress(&self) -> f64 {
+        if self.status == JobStatus::Completed {
+            1.0
+        } else if self.segments_total == 0 {
+            0.0
+        } else {
+            self.segments_done as f64 / self.segments_total as f64
+        }
+    }
+
+    /// 是否允许访问该任务，`owner`为None时（admin密钥或未启用鉴权）可访问任意任务
+    pub fn is_visible_to(&self, owner: Option<&str>) -> bool {
+        owner.is_none() || self.owner.as_deref() == owner
+    }
+}
+
+/// 异步TTS任务管理器
+pub struct TtsJobManager {
+    /// 任务根目录
+    jobs_dir: PathBuf,
+    /// 所有任务（内存副本，每次变更后写回job.json）
+    jobs: Mutex<HashMap<String, TtsJob>>,
+    /// 运行中任务的取消令牌
+    cancel_tokens: Mutex<HashMap<String, CancelToken>>,
+    /// 待执行任务队列
+    queue_tx: flume::Sender<String>,
+    queue_rx: flume::Receiver<String>,
+    pipeline: Arc<LightweightTtsPipeline>,
+    /// 回调投递器，未配置签名密钥时为None
+    notifier: Option<Arc<WebhookNotifier>>,
+    segment_max_chars: usize,
+    /// 服务器关闭中，工作线程不再领取新任务
+    shutting_down: AtomicBool,
+}
+
+impl TtsJobManager {
+    /// 创建任务管理器：加载已有任务，将未完成的任务重新排队，并启动`workers`个后台工作线程
+    pub async fn new<P: AsRef<Path>>(
+        jobs_dir: P,
+        pipeline: Arc<LightweightTtsPipeline>,
+        workers: usize,
+        notifier: Option<Arc<WebhookNotifier>>,
+    ) -> Result<Arc<Self>> {
+        let jobs_dir = jobs_dir.as_ref().to_path_buf();
+        async_fs::create_dir_all(&jobs_dir).await?;
+
+        let (queue_tx, queue_rx) = flume::unbounded();
+        let manager = Arc::new(Self {
+            jobs_dir,
+            jobs: Mutex::new(HashMap::new()),
+            cancel_tokens: Mutex::new(HashMap::new()),
+            queue_tx,
+            queue_rx,
+            pipeline,
+            notifier,
+            segment_max_chars: DEFAULT_SEGMENT_MAX_CHARS,
+            shutting_down: AtomicBool::new(false),
+        });
+
+        manager.restore_jobs().await?;
+
+        for worker_id in 0..workers {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                manager.run_worker(worker_id).await;
+            });
+        }
+
+        Ok(manager)
+    }
+
+    /// 生成新的任务ID
+    fn generate_job_id() -> String {
+        let now = Utc::now();
+        let timestamp = now.format("%Y%m%d_%H%M%S");
+        let uuid_short = Uuid::new_v4().to_string()[..8].to_string();
+        format!("job_{}_{}", timestamp, uuid_short)
+    }
+
+    /// 校验任务参数（输出格式、比特率、采样率、回调地址和文本），不提交任务
+    pub async fn validate(
+        &self,
+        args: &LightweightTtsPipelineArgs,
+        output: &JobOutput,
+        callback_url: Option<&str>,
+    ) -> Result<()> {
+        let format = output.audio_format()?;
+        format.validate_bitrate(output.bitrate_kbps)?;
+        validate_output_sample_rate(output.sample_rate)?;
+        if let Some(required) = format.required_sample_rate() {
+            if output.sample_rate != required {
+                return Err(anyhow!("{}格式只支持{}Hz采样率", output.format, required));
+            }
+        }
+        if !format.supports_sample_rate(output.sample_rate) {
+            return Err(anyhow!(
+                "{}格式不支持{}Hz采样率",
+                output.format,
+                output.sample_rate
+            ));
+        }
+
+        if let Some(url) = callback_url {
+            let Some(notifier) = &self.notifier else {
+                return Err(anyhow!("服务器未配置webhook签名密钥，不支持callback_url"));
+            };
+            notifier.validate_url(url).await?;
+        }
+
+        if split_text_segments(&args.text, self.segment_max_chars).is_empty() {
+            return Err(anyhow!("文本不能为空"));
+        }
+        Ok(())
+    }
+
+    /// 提交任务，返回排队中的任务
+    pub async fn submit(
+        &self,
+        args: LightweightTtsPipelineArgs,
+        output: JobOutput,
+        callback_url: Option<String>,
+        owner: Option<String>,
+    ) -> Result<TtsJob> {
+        self.validate(&args, &output, callback_url.as_deref())
+            .await?;
+        let segments = split_text_segments(&args.text, self.segment_max_chars);
+
+        let job = TtsJob {
+            id: Self::generate_job_id(),
+            status: JobStatus::Queued,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            segments_total: segments.len(),
+            segments_done: 0,
+            text_chars: args.text.chars().count(),
+            output,
+            output_file: None,
+            audio_duration_secs: None,
+            rtf: None,
+            error: None,
+            callback_url,
+            callback_delivered: None,
+            owner,
+            args,
+        };
+
+        self.persist(&job).await?;
+        self.jobs
+            .lock()
+            .unwrap()
+            .insert(job.id.clone(), job.clone());
+        self.queue_tx
+            .send(job.id.clone())
+            .map_err(|e| anyhow!("任务入队失败: {}", e))?;
+
+        info!(
+            "📥 任务 {} 已提交: {} 字符，{} 段",
+            job.id, job.text_chars, job.segments_total
+        );
+        Ok(job)
+    }
+
+    /// 获取任务
+    pub fn get(&self, job_id: &str) -> Option<TtsJob> {
+        self.jobs.lock().unwrap().get(job_id).cloned()
+    }
+
+    /// 列出所有任务（按提交时间倒序）
+    pub fn list(&self) -> Vec<TtsJob> {
+        let mut jobs: Vec<TtsJob> = self.jobs.lock().unwrap().values().cloned().collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs
+    }
+
+    /// 取消任务：排队中的任务立即取消，运行中的任务中止当前段的推理后停止
+    pub async fn cancel(self: &Arc<Self>, job_id: &str) -> Result<TtsJob> {
+        let job = self
+            .get(job_id)
+            .ok_or_else(|| anyhow!("任务不存在: {}", job_id))?;
+
+        match job.status {
+            JobStatus::Queued => {
+                let job = self
+                    .update_job(job_id, |job| {
+                        job.status = JobStatus::Cancelled;
+                        job.finished_at = Some(Utc::now());
+                    })
+                    .await?;
+                info!("🛑 任务 {} 已取消（排队中）", job_id);
+                self.notify(&job);
+                Ok(job)
+            }
+            JobStatus::Running => {
+                if let Some(cancel) = self.cancel_tokens.lock().unwrap().get(job_id) {
+                    cancel.cancel();
+                }
+                info!("🛑 任务 {} 请求取消，正在中止推理", job_id);
+                Ok(job)
+            }
+            status => Err(anyhow!("任务已结束（{:?}），无法取消", status)),
+        }
+    }
+
+    /// 获取已完成任务的输出文件路径
+    pub fn output_path(&self, job: &TtsJob) -> Option<PathBuf> {
+        job.output_file
+            .as_ref()
+            .map(|file| self.job_dir(&job.id).join(file))
+    }
+
+    fn job_dir(&self, job_id: &str) -> PathBuf {
+        self.jobs_dir.join(job_id)
+    }
+
+    /// 将任务写入job.json（先写临时文件再重命名，避免中途崩溃留下半个文件）
+    async fn persist(&self, job: &TtsJob) -> Result<()> {
+        let job_dir = self.job_dir(&job.id);
+        async_fs::create_dir_all(&job_dir).await?;
+
+        let data = serde_json::to_vec_pretty(job)?;
+        let temp_path = job_dir.join(format!("{}.tmp", JOB_FILE_NAME));
+        async_fs::write(&temp_path, &data).await?;
+        async_fs::rename(&temp_path, job_dir.join(JOB_FILE_NAME)).await?;
+        Ok(())
+    }
+
+    /// 修改内存中的任务并持久化，返回修改后的副本
+    async fn update_job<F>(&self, job_id: &str, update: F) -> Result<TtsJob>
+    where
+        F: FnOnce(&mut TtsJob),
+    {
+        let job = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = jobs
+                .get_mut(job_id)
+                .ok_or_else(|| anyhow!("任务不存在: {}", job_id))?;
+            update(job);
+            job.clone()
+        };
+        self.persist(&job).await?;
+        Ok(job)
+    }
+
+    /// 从任务目录恢复任务，未完成的任务重新排队，未投递的回调重新投递
+    async fn restore_jobs(self: &Arc<Self>) -> Result<()> {
+        let mut restored = Vec::new();
+        let mut entries = async_fs::read_dir(&self.jobs_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let job_file = entry.path().join(JOB_FILE_NAME);
+            if !job_file.exists() {
+                continue;
+            }
+            let content = match async_fs::read(&job_file).await {
+                Ok(content) => content,
+                Err(e) => {
+                    warn!("读取任务文件 {} 失败: {}", job_file.display(), e);
+                    continue;
+                }
+            };
+            match serde_json::from_slice::<TtsJob>(&content) {
+                Ok(job) => restored.push(job),
+                Err(e) => warn!("解析任务文件 {} 失败: {}", job_file.display(), e),
+            }
+        }
+
+        restored.sort_by_key(|job| job.created_at);
+        let mut requeued = 0;
+        for mut job in restored {
+            if !job.status.is_finished() {
+                // 分段音频未持久化，中断的任务从头合成
+                job.status = JobStatus::Queued;
+                job.started_at = None;
+                job.segments_done = 0;
+                self.persist(&job).await?;
+                self.queue_tx
+                    .send(job.id.clone())
+                    .map_err(|e| anyhow!("任务入队失败: {}", e))?;
+                requeued += 1;
+            } else if job.callback_url.is_some() && job.callback_delivered.is_none() {
+                self.notify(&job);
+            }
+            self.jobs.lock().unwrap().insert(job.id.clone(), job);
+        }
+
+        let total = self.jobs.lock().unwrap().len();
+        if total > 0 {
+            info!("恢复 {} 个任务，其中 {} 个重新排队", total, requeued);
+        }
+        Ok(())
+    }
+
+    /// 开始关闭：工作线程不再领取新任务，排队中的任务保留到下次启动时执行
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        let running = self.cancel_tokens.lock().unwrap().len();
+        info!("任务管理器停止领取新任务，{} 个任务正在执行", running);
+    }
+
+    /// 后台工作线程：依次执行队列中的任务
+    async fn run_worker(self: Arc<Self>, worker_id: usize) {
+        while let Ok(job_id) = self.queue_rx.recv_async().await {
+            if self.shutting_down.load(Ordering::SeqCst) {
+                // 任务仍为排队状态，重启后由restore_jobs重新入队
+                break;
+            }
+
+            // 排队期间可能已被取消
+            if self.get(&job_id).map(|job| job.status) != Some(JobStatus::Queued) {
+                continue;
+            }
+
+            let cancel = CancelToken::new();
+            self.cancel_tokens
+                .lock()
+                .unwrap()
+                .insert(job_id.clone(), cancel.clone());
+
+            info!("🚀 工作线程 {} 开始执行任务 {}", worker_id, job_id);
+            let result = self.run_job(&job_id, &cancel).await;
+            self.cancel_tokens.lock().unwrap().remove(&job_id);
+
+            if result.is_err() && self.shutting_down.load(Ordering::SeqCst) {
+                // 关闭导致的中断不算失败，恢复为排队状态以便重启后从头合成
+                warn!("任务 {} 因服务器关闭中断，将在重启后重新执行", job_id);
+                if let Err(e) = self
+                    .update_job(&job_id, |job| {
+                        job.status = JobStatus::Queued;
+                        job.started_at = None;
+                        job.segments_done = 0;
+                    })
+                    .await
+                {
+                    error!("更新任务 {} 状态失败: {}", job_id, e);
+                }
+                break;
+            }
+
+            let finished = match result {
+                Ok(Some(job)) => Ok(job),
+                Ok(None) => {
+                    self.update_job(&job_id, |job| {
+                        job.status = JobStatus::Cancelled;
+                        job.finished_at = Some(Utc::now());
+                    })
+                    .await
+                }
+                Err(e) => {
+                    error!("任务 {} 失败: {}", job_id, e);
+                    self.update_job(&job_id, |job| {
+                        job.status = JobStatus::Failed;
+                        job.finished_at = Some(Utc::now());
+                        job.error = Some(e.to_string());
+                    })
+                    .await
+                }
+            };
+
+            match finished {
+                Ok(job) => {
+                    info!("任务 {} 结束: {:?}", job.id, job.status);
+                    self.notify(&job);
+                }
+                Err(e) => error!("更新任务 {} 状态失败: {}", job_id, e),
+            }
+        }
+    }
+
+    /// 在后台投递任务结束通知，并记录投递结果
+    fn notify(self: &Arc<Self>, job: &TtsJob) {
+        let (Some(notifier), Some(url)) = (self.notifier.clone(), job.callback_url.clone()) else {
+            return;
+        };
+        let notification = notifier.notification(job);
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let delivered = notifier.deliver(&url, &notification).await;
+            if let Err(e) = manager
+                .update_job(&notification.job_id, |job| {
+                    job.callback_delivered = Some(delivered)
+                })
+                .await
+            {
+                warn!("记录任务 {} 回调结果失败: {}", notification.job_id, e);
+            }
+        });
+    }
+
+    /// 执行任务，被取消时返回`Ok(None)`
+    async fn run_job(&self, job_id: &str, cancel: &CancelToken) -> Result<Option<TtsJob>> {
+        let job = self
+            .update_job(job_id, |job| {
+                job.status = JobStatus::Running;
+                job.started_at = Some(Utc::now());
+                job.segments_done = 0;
+                job.error = None;
+            })
+            .await?;
+
+        let job_start = Instant::now();
+        let segments = split_text_segments(&job.args.text, self.segment_max_chars);
+        let mut args = job.args.clone();
+        args.cancel = cancel.clone();
+        // 未指定音色时，以首段生成的tokens作为后续段落的zero-shot参考，避免段间音色漂移
+        let inherit_voice =
+            args.voice_global_tokens.is_none() || args.voice_semantic_tokens.is_none();
+        let silence = vec![0.0f32; (MODEL_SAMPLE_RATE as f32 * SEGMENT_SILENCE_SECS) as usize];
+        let mut audio: Vec<f32> = Vec::new();
+
+        for (index, segment) in segments.iter().enumerate() {
+            if cancel.is_cancelled() {
+                return Ok(None);
+            }
+
+            args.text = segment.clone();
+            let result = loop {
+                match self.pipeline.generate_speech_with_tokens(&args).await {
+                    // 后台任务不因请求队列暂时已满而失败，等待队列空出后重试
+                    Err(e) if !cancel.is_cancelled() => match e.downcast_ref::<QueueFull>() {
+                        Some(full) => {
+                            warn!("任务 {} 等待请求队列空位: {}", job_id, full);
+                            tokio::time::sleep(Duration::from_secs(full.retry_after_secs())).await;
+                        }
+                        None => break Err(e),
+                    },
+                    result => break result,
+                }
+            };
+            if cancel.is_cancelled() {
+                return Ok(None);
+            }
+            let (segment_audio, global_tokens, semantic_tokens) = result?;
+
+            if index == 0 && inherit_voice && !semantic_tokens.is_empty() {
+                args.zero_shot = true;
+                args.prompt_text = segment.clone();
+                args.voice_global_tokens = Some(global_tokens);
+                args.voice_semantic_tokens = Some(semantic_tokens);
+            }
+
+            if !audio.is_empty() {
+                audio.extend_from_slice(&silence);
+            }
+            audio.extend_from_slice(&segment_audio);
+
+            self.update_job(job_id, |job| {
+                job.segments_total = segments.len();
+                job.segments_done = index + 1;
+            })
+            .await?;
+        }
+
+        if cancel.is_cancelled() {
+            return Ok(None);
+        }
+
+        // 重采样与编码在阻塞线程中执行
+        let format = job.output.audio_format()?;
+        let options = AudioEncodeOptions::new(format, job.output.sample_rate)
+            .with_bitrate(job.output.bitrate_kbps);
+        let sample_rate = job.output.sample_rate;
+        let (encoded, sample_count) = tokio::task::spawn_blocking(move || {
+            let samples = resample(&audio, MODEL_SAMPLE_RATE, sample_rate);
+            encode_audio(&samples, &options).map(|bytes| (bytes, samples.len()))
+        })
+        .await
+        .map_err(|e| anyhow!("音频编码任务异常: {}", e))??;
+
+        let output_file = format!("output.{}", format.extension());
+        async_fs::write(self.job_dir(job_id).join(&output_file), &encoded).await?;
+
+        let audio_duration = sample_count as f64 / sample_rate as f64;
+        let elapsed = job_start.elapsed().as_secs_f64();
+        let job = self
+            .update_job(job_id, |job| {
+                job.status = JobStatus::Completed;
+                job.finished_at = Some(Utc::now());
+                job.output_file = Some(output_file);
+                job.audio_duration_secs = Some(audio_duration);
+                job.rtf = (audio_duration > 0.0).then(|| elapsed / audio_duration);
+            })
+            .await?;
+
+        info!(
+            "✅ 任务 {} 完成: 音频时长 {:.2}s, 耗时 {:.2}s",
+            job_id, audio_duration, elapsed
+        );
+        Ok(Some(job))
+    }
+}
+
+/// 将长文本切分为不超过`max_chars`个字符的段落
+///
+/// 优先在句末标点（。！？!?；;和换行）处断开，单句过长时退而在逗号等停顿处断开，
+/// 仍然过长则按字符数硬切分。相邻短句会合并到同一段中。
+pub fn split_text_segments(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut pieces = Vec::new();
+    for sentence in split_keep_delimiter(text, SENTENCE_DELIMITERS) {
+        if sentence.chars().count() <= max_chars {
+            pieces.push(sentence);
+            continue;
+        }
+        for clause in split_keep_delimiter(&sentence, CLAUSE_DELIMITERS) {
+            let chars: Vec<char> = clause.chars().collect();
+            for chunk in chars.chunks(max_chars) {
+                pieces.push(chunk.iter().collect());
+            }
+        }
+    }
+
+    // 合并相邻短句
+    let mut segments: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for piece in pieces {
+        let piece = piece.trim();
+        if piece.is_empty() {
+            continue;
+        }
+        if !current.is_empty() && current.chars().count() + piece.chars().count() > max_chars {
+            segments.push(std::mem::take(&mut current));
+        }
+        current.push_str(piece);
+    }
+    if !current.is_empty() {
+        segments.push(current);
+    }
+    segments
+}
+
+/// 按分隔符切分字符串，分隔符保留在前一段末尾
+fn split_keep_delimiter(text: &str, delimiters: &[char]) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        current.push(c);
+        if delimiters.contains(&c) {
+            parts.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_split_text_segments() {
+        let text = "第一句话。第二句话！\n第三句话？";
+        assert_eq!(
+            split_text_segments(text, 100),
+            vec!["第一句话。第二句话！第三句话？".to_string()]
+        );
+        assert_eq!(
+            split_text_segments(text, 6),
+            vec!["第一句话。", "第二句话！", "第三句话？"]
+        );
+
+        // 单句过长时在逗号处断开，仍过长则硬切分
+        let segments = split_text_segments("一二三，四五六七八九十。", 4);
+        assert_eq!(segments, vec!["一二三，", "四五六七", "八九十。"]);
+
+        assert!(split_text_segments("  \n ", 10).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_jobs_persist_and_requeue() {
+        let temp_dir = TempDir::new().unwrap();
+        let pipeline = Arc::new(LightweightTtsPipeline::new());
+
+        // 不启动工作线程，任务保持排队状态
+        let manager = TtsJobManager::new(temp_dir.path(), pipeline.clone(), 0, None)
+            .await
+            .unwrap();
+        let output = JobOutput {
+            format: "wav".to_string(),
+            sample_rate: 16000,
+            bitrate_kbps: None,
+        };
+        let args = LightweightTtsPipelineArgs {
+            text: "你好。世界。".to_string(),
+            ..Default::default()
+        };
+        let job = manager
+            .submit(
+                args.clone(),
+                output.clone(),
+                None,
+                Some("alice".to_string()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(job.segments_total, 1);
+        assert!(job.is_visible_to(Some("alice")));
+        assert!(!job.is_visible_to(Some("bob")));
+        assert!(job.is_visible_to(None));
+
+        let cancelled = manager
+            .submit(args, output.clone(), None, None)
+            .await
+            .unwrap();
+        let cancelled = manager.cancel(&cancelled.id).await.unwrap();
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert!(manager.cancel(&cancelled.id).await.is_err());
+
+        // G.711要求8kHz，空文本不可提交，未配置签名密钥时不接受回调地址
+        let invalid = JobOutput {
+            format: "ulaw".to_string(),
+            ..output.clone()
+        };
+        assert!(manager
+            .submit(job.args.clone(), invalid, None, None)
+            .await
+            .is_err());
+        let empty = LightweightTtsPipelineArgs::default();
+        assert!(manager
+            .submit(empty, output.clone(), None, None)
+            .await
+            .is_err());
+        let callback = Some("https://example.com/hook".to_string());
+        assert!(manager
+            .submit(job.args.clone(), output, callback, None)
+            .await
+            .is_err());
+
+        // 重新加载后保留已结束任务，未完成任务重新排队
+        let reloaded = TtsJobManager::new(temp_dir.path(), pipeline, 0, None)
+            .await
+            .unwrap();
+        assert_eq!(reloaded.list().len(), 2);
+        let reloaded_job = reloaded.get(&job.id).unwrap();
+        assert_eq!(reloaded_job.status, JobStatus::Queued);
+        assert_eq!(reloaded_job.owner.as_deref(), Some("alice"));
+        assert_eq!(
+            reloaded.get(&cancelled.id).unwrap().status,
+            JobStatus::Cancelled
+        );
+        assert_eq!(reloaded.queue_tx.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_keeps_queued_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let pipeline = Arc::new(LightweightTtsPipeline::new());
+        let manager = TtsJobManager::new(temp_dir.path(), pipeline, 1, None)
+            .await
+            .unwrap();
+        manager.begin_shutdown();
+
+        let args = LightweightTtsPipelineArgs {
+            text: "你好。".to_string(),
+            ..Default::default()
+        };
+        let output = JobOutput {
+            format: "wav".to_string(),
+            sample_rate: 16000,
+            bitrate_kbps: None,
+        };
+        let job = manager.submit(args, output, None, None).await.unwrap();
+
+        // 工作线程不再领取任务，任务保持排队状态等待重启
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(manager.get(&job.id).unwrap().status, JobStatus::Queued);
+        assert!(manager.cancel_tokens.lock().unwrap().is_empty());
+    }
+}
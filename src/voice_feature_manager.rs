@@ -2,6 +2,7 @@
 //! 实现音色特征的提取、保存、加载和管理功能
 
 use anyhow::{anyhow, Result};
+use base64::Engine;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -21,6 +22,12 @@ pub struct VoiceFeature {
     pub name: String,
     /// 原始提示词
     pub prompt_text: String,
+    /// 音色描述（为空时不写入文件，保持旧文件的校验和不变）
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// 标签
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     /// 创建时间
     pub created_at: DateTime<Utc>,
     /// 全局令牌
@@ -41,6 +48,10 @@ pub struct VoiceMetadata {
     pub id: String,
     pub name: String,
     pub prompt_text: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub file_path: String,
     pub file_size: u64,
@@ -53,8 +64,61 @@ pub struct VoicesMetadata {
     pub voices: Vec<VoiceMetadata>,
 }
 
+/// 音色信息修改项，None表示保持不变
+#[derive(Debug, Clone, Default)]
+pub struct VoiceUpdate {
+    pub name: Option<String>,
+    pub prompt_text: Option<String>,
+    pub description: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// 音色包格式标识
+pub const VOICE_PACKAGE_FORMAT: &str = "rwkv-tts-voice-package";
+
+/// 当前音色包格式版本
+pub const VOICE_PACKAGE_VERSION: u32 = 1;
+
+/// 可随音色保存的参考音频格式
+const REFERENCE_AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3"];
+
+/// 原始参考音频
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceAudio {
+    /// 文件格式（wav或mp3）
+    pub format: String,
+    /// Base64编码的文件内容
+    pub data_base64: String,
+}
+
+/// 音色导出包，用于在不同服务器之间迁移音色
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoicePackage {
+    /// 固定为`rwkv-tts-voice-package`
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub voice: VoiceFeature,
+    pub metadata: VoiceMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_audio: Option<ReferenceAudio>,
+    /// 整个包的校验和（本字段置空后序列化计算）
+    pub checksum: String,
+}
+
+/// 音色导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct VoiceImportResult {
+    /// 导入后的音色ID
+    pub voice_id: String,
+    /// 包中的原始音色ID，与已有音色冲突时会分配新ID
+    pub original_id: String,
+    /// 已存在内容相同的音色，未重复导入
+    pub already_exists: bool,
+}
+
 /// 缓存统计信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CacheStats {
     pub total_voices: usize,
     pub cache_hits: u64,
@@ -165,11 +229,41 @@ impl VoiceFeatureManager {
         format!("{:x}", hasher.finalize())
     }
 
+    /// 音色特征文件路径
+    fn raf_file_path(&self, voice_id: &str) -> PathBuf {
+        self.raf_dir.join(format!("{}.raf.json", voice_id))
+    }
+
+    /// 计算音色特征的校验和（校验和字段置空后序列化）
+    fn feature_checksum(voice_feature: &VoiceFeature) -> Result<String> {
+        let mut temp_feature = voice_feature.clone();
+        temp_feature.checksum = String::new();
+        let temp_data = serde_json::to_vec_pretty(&temp_feature)?;
+        Ok(Self::calculate_checksum(&temp_data))
+    }
+
+    /// 更新校验和并写入RAF文件，返回文件大小
+    async fn write_voice_file(&self, voice_feature: &mut VoiceFeature) -> Result<u64> {
+        voice_feature.checksum = Self::feature_checksum(voice_feature)?;
+        let final_data = serde_json::to_vec_pretty(voice_feature)?;
+        let raf_file_path = self.raf_file_path(&voice_feature.id);
+
+        // 确保父目录存在
+        if let Some(parent_dir) = raf_file_path.parent() {
+            async_fs::create_dir_all(parent_dir).await?;
+        }
+
+        async_fs::write(&raf_file_path, &final_data).await?;
+        Ok(final_data.len() as u64)
+    }
+
     /// 保存音色特征到文件（只支持JSON文本格式）
+    #[allow(clippy::too_many_arguments)]
     pub async fn save_voice_feature(
         &self,
         name: String,
         prompt_text: String,
+        description: String,
         global_tokens: Vec<i32>,
         semantic_tokens: Vec<i32>,
         audio_duration: f32,
@@ -178,47 +272,35 @@ impl VoiceFeatureManager {
         let voice_id = Self::generate_voice_id();
         let created_at = Utc::now();
 
-        let voice_feature = VoiceFeature {
+        let mut voice_feature = VoiceFeature {
             id: voice_id.clone(),
             name: name.clone(),
             prompt_text: prompt_text.clone(),
+            description: description.clone(),
+            tags: Vec::new(),
             created_at,
             global_tokens,
             semantic_tokens,
             audio_duration,
             sample_rate,
-            checksum: String::new(), // 将在序列化后计算
+            checksum: String::new(), // 将在写入文件时计算
         };
 
-        // 使用JSON文本格式序列化
-        let serialized_data = serde_json::to_vec_pretty(&voice_feature)?;
-        let checksum = Self::calculate_checksum(&serialized_data);
-
-        // 更新校验和
-        let mut voice_feature = voice_feature;
-        voice_feature.checksum = checksum.clone();
-
-        // 重新序列化包含校验和的数据
-        let final_data = serde_json::to_vec_pretty(&voice_feature)?;
-
         // 保存到RAF文件（只使用JSON格式）
-        let raf_file_path = self.raf_dir.join(format!("{}.raf.json", voice_id));
-
-        // 确保父目录存在
-        if let Some(parent_dir) = raf_file_path.parent() {
-            async_fs::create_dir_all(parent_dir).await?;
-        }
-
-        async_fs::write(&raf_file_path, &final_data).await?;
+        let file_size = self.write_voice_file(&mut voice_feature).await?;
+        let raf_file_path = self.raf_file_path(&voice_id);
+        let checksum = voice_feature.checksum.clone();
 
         // 更新元数据
         let metadata = VoiceMetadata {
             id: voice_id.clone(),
             name,
             prompt_text,
+            description,
+            tags: Vec::new(),
             created_at,
             file_path: raf_file_path.to_string_lossy().to_string(),
-            file_size: final_data.len() as u64,
+            file_size,
             checksum,
         };
 
@@ -263,7 +345,7 @@ impl VoiceFeatureManager {
         }
 
         // 从文件加载（只使用JSON格式）
-        let raf_file_path = self.raf_dir.join(format!("{}.raf.json", voice_id));
+        let raf_file_path = self.raf_file_path(voice_id);
         if !raf_file_path.exists() {
             return Err(anyhow!("音色特征文件不存在: {}", voice_id));
         }
@@ -272,12 +354,7 @@ impl VoiceFeatureManager {
         let voice_feature: VoiceFeature = serde_json::from_slice(&data)?;
 
         // 验证校验和
-        let mut temp_feature = voice_feature.clone();
-        temp_feature.checksum = String::new();
-        let temp_data = serde_json::to_vec_pretty(&temp_feature)?;
-        let calculated_checksum = Self::calculate_checksum(&temp_data);
-
-        if calculated_checksum != voice_feature.checksum {
+        if Self::feature_checksum(&voice_feature)? != voice_feature.checksum {
             return Err(anyhow!("音色特征文件校验和不匹配: {}", voice_id));
         }
 
@@ -315,11 +392,15 @@ impl VoiceFeatureManager {
     /// 删除音色特征
     pub async fn delete_voice(&self, voice_id: &str) -> Result<()> {
         // 删除RAF文件
-        let raf_file_path = self.raf_dir.join(format!("{}.raf.json", voice_id));
+        let raf_file_path = self.raf_file_path(voice_id);
         if raf_file_path.exists() {
             async_fs::remove_file(&raf_file_path).await?;
         }
 
+        if let Some(reference_audio_path) = self.reference_audio_path(voice_id) {
+            async_fs::remove_file(&reference_audio_path).await?;
+        }
+
         // 从元数据中移除
         self.remove_voice_metadata(voice_id).await?;
 
@@ -334,40 +415,237 @@ impl VoiceFeatureManager {
 
     /// 重命名音色
     pub async fn rename_voice(&self, voice_id: &str, new_name: String) -> Result<()> {
+        self.update_voice(
+            voice_id,
+            VoiceUpdate {
+                name: Some(new_name),
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// 修改音色名称、提示词、描述和标签，返回修改后的音色特征
+    pub async fn update_voice(&self, voice_id: &str, update: VoiceUpdate) -> Result<VoiceFeature> {
         // 先加载当前特征文件
         let mut voice_feature = self.load_voice_feature(voice_id).await?;
-        voice_feature.name = new_name.clone();
 
-        // 重新序列化并保存
-        let mut temp_feature = voice_feature.clone();
-        temp_feature.checksum = String::new();
-        let serialized_data = serde_json::to_vec_pretty(&temp_feature)?;
-        let checksum = Self::calculate_checksum(&serialized_data);
-        voice_feature.checksum = checksum;
-
-        let final_data = serde_json::to_vec_pretty(&voice_feature)?;
-        let raf_file_path = self.raf_dir.join(format!("{}.raf.json", voice_id));
-
-        // 确保父目录存在
-        if let Some(parent_dir) = raf_file_path.parent() {
-            async_fs::create_dir_all(parent_dir).await?;
+        if let Some(name) = update.name {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(anyhow!("音色名称不能为空"));
+            }
+            voice_feature.name = name.to_string();
+        }
+        if let Some(prompt_text) = update.prompt_text {
+            if prompt_text.trim().is_empty() {
+                return Err(anyhow!("提示词不能为空"));
+            }
+            voice_feature.prompt_text = prompt_text;
+        }
+        if let Some(description) = update.description {
+            voice_feature.description = description.trim().to_string();
+        }
+        if let Some(tags) = update.tags {
+            voice_feature.tags = normalize_tags(tags);
         }
 
-        async_fs::write(&raf_file_path, &final_data).await?;
+        // 重新计算校验和并保存
+        let file_size = self.write_voice_file(&mut voice_feature).await?;
 
         // 更新元数据
-        self.update_voice_metadata_name(voice_id, new_name.clone())
-            .await?;
+        self.update_voice_metadata(voice_id, |voice_meta| {
+            voice_meta.name = voice_feature.name.clone();
+            voice_meta.prompt_text = voice_feature.prompt_text.clone();
+            voice_meta.description = voice_feature.description.clone();
+            voice_meta.tags = voice_feature.tags.clone();
+            voice_meta.file_size = file_size;
+            voice_meta.checksum = voice_feature.checksum.clone();
+        })
+        .await?;
 
         // 更新缓存中的条目
         {
             let mut cache = self.voice_cache.lock().unwrap();
-            cache.insert(voice_id.to_string(), Arc::new(voice_feature));
+            cache.insert(voice_id.to_string(), Arc::new(voice_feature.clone()));
         }
 
+        Ok(voice_feature)
+    }
+
+    /// 已保存的原始参考音频路径
+    fn reference_audio_path(&self, voice_id: &str) -> Option<PathBuf> {
+        REFERENCE_AUDIO_EXTENSIONS
+            .iter()
+            .map(|ext| self.raf_dir.join(format!("{}.ref.{}", voice_id, ext)))
+            .find(|path| path.exists())
+    }
+
+    /// 是否保存了原始参考音频
+    pub fn has_reference_audio(&self, voice_id: &str) -> bool {
+        self.reference_audio_path(voice_id).is_some()
+    }
+
+    /// 保存音色的原始参考音频，导出音色包时可一并带上
+    pub async fn save_reference_audio(&self, voice_id: &str, source: &Path) -> Result<()> {
+        let format = source
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .filter(|ext| REFERENCE_AUDIO_EXTENSIONS.contains(&ext.as_str()))
+            .ok_or_else(|| anyhow!("不支持保存该格式的参考音频: {}", source.display()))?;
+
+        let target = self.raf_dir.join(format!("{}.ref.{}", voice_id, format));
+        async_fs::copy(source, &target).await?;
         Ok(())
     }
 
+    /// 计算音色包的校验和（校验和字段置空后序列化）
+    fn package_checksum(package: &VoicePackage) -> Result<String> {
+        let mut temp_package = package.clone();
+        temp_package.checksum = String::new();
+        let temp_data = serde_json::to_vec_pretty(&temp_package)?;
+        Ok(Self::calculate_checksum(&temp_data))
+    }
+
+    /// 导出音色包，`include_reference_audio`为true且保存了参考音频时一并导出
+    pub async fn export_voice(
+        &self,
+        voice_id: &str,
+        include_reference_audio: bool,
+    ) -> Result<VoicePackage> {
+        let voice = self.load_voice_feature(voice_id).await?;
+        let metadata = self
+            .list_voices()
+            .await?
+            .into_iter()
+            .find(|v| v.id == voice_id)
+            .ok_or_else(|| anyhow!("音色元数据不存在: {}", voice_id))?;
+
+        let reference_audio = match self.reference_audio_path(voice_id) {
+            Some(path) if include_reference_audio => {
+                let format = path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or_default()
+                    .to_string();
+                let data = async_fs::read(&path).await?;
+                Some(ReferenceAudio {
+                    format,
+                    data_base64: base64::engine::general_purpose::STANDARD.encode(data),
+                })
+            }
+            _ => None,
+        };
+
+        let mut package = VoicePackage {
+            format: VOICE_PACKAGE_FORMAT.to_string(),
+            version: VOICE_PACKAGE_VERSION,
+            exported_at: Utc::now(),
+            voice,
+            metadata,
+            reference_audio,
+            checksum: String::new(),
+        };
+        package.checksum = Self::package_checksum(&package)?;
+        Ok(package)
+    }
+
+    /// 导入音色包
+    ///
+    /// 校验包和音色特征的校验和；本地已有同ID且内容相同的音色时直接返回，
+    /// ID被其他音色占用时分配新ID。
+    pub async fn import_voice(&self, package: VoicePackage) -> Result<VoiceImportResult> {
+        if package.format != VOICE_PACKAGE_FORMAT {
+            return Err(anyhow!("不是音色包: {}", package.format));
+        }
+        if package.version == 0 || package.version > VOICE_PACKAGE_VERSION {
+            return Err(anyhow!("不支持的音色包版本: {}", package.version));
+        }
+        if Self::package_checksum(&package)? != package.checksum {
+            return Err(anyhow!("音色包校验和不匹配"));
+        }
+
+        let mut voice = package.voice;
+        if Self::feature_checksum(&voice)? != voice.checksum {
+            return Err(anyhow!("音色特征校验和不匹配: {}", voice.id));
+        }
+        if package.metadata.id != voice.id {
+            return Err(anyhow!("音色包元数据与音色特征不一致"));
+        }
+        if !is_valid_voice_id(&voice.id) {
+            return Err(anyhow!("无效的音色ID: {}", voice.id));
+        }
+        if voice.name.trim().is_empty() {
+            return Err(anyhow!("音色名称不能为空"));
+        }
+        if voice.global_tokens.is_empty() || voice.semantic_tokens.is_empty() {
+            return Err(anyhow!("音色特征缺少令牌"));
+        }
+
+        let reference_audio = match package.reference_audio {
+            Some(audio) => {
+                let format = audio.format.to_ascii_lowercase();
+                if !REFERENCE_AUDIO_EXTENSIONS.contains(&format.as_str()) {
+                    return Err(anyhow!("不支持的参考音频格式: {}", audio.format));
+                }
+                let data = base64::engine::general_purpose::STANDARD
+                    .decode(audio.data_base64.as_bytes())
+                    .map_err(|e| anyhow!("参考音频Base64解码失败: {}", e))?;
+                Some((format, data))
+            }
+            None => None,
+        };
+
+        let original_id = voice.id.clone();
+        let existing = self.list_voices().await?;
+        if let Some(existing_meta) = existing.iter().find(|v| v.id == original_id) {
+            if existing_meta.checksum == voice.checksum {
+                return Ok(VoiceImportResult {
+                    voice_id: original_id.clone(),
+                    original_id,
+                    already_exists: true,
+                });
+            }
+        }
+        if existing.iter().any(|v| v.id == original_id) || self.raf_file_path(&original_id).exists()
+        {
+            voice.id = Self::generate_voice_id();
+        }
+
+        let file_size = self.write_voice_file(&mut voice).await?;
+        if let Some((format, data)) = reference_audio {
+            let target = self.raf_dir.join(format!("{}.ref.{}", voice.id, format));
+            async_fs::write(&target, data).await?;
+        }
+
+        let metadata = VoiceMetadata {
+            id: voice.id.clone(),
+            name: voice.name.clone(),
+            prompt_text: voice.prompt_text.clone(),
+            description: voice.description.clone(),
+            tags: voice.tags.clone(),
+            created_at: voice.created_at,
+            file_path: self.raf_file_path(&voice.id).to_string_lossy().to_string(),
+            file_size,
+            checksum: voice.checksum.clone(),
+        };
+        self.add_voice_metadata(metadata).await?;
+
+        let voice_id = voice.id.clone();
+        {
+            let mut cache = self.voice_cache.lock().unwrap();
+            cache.insert(voice_id.clone(), Arc::new(voice));
+        }
+
+        Ok(VoiceImportResult {
+            voice_id,
+            original_id,
+            already_exists: false,
+        })
+    }
+
     /// 添加音色元数据
     async fn add_voice_metadata(&self, metadata: VoiceMetadata) -> Result<()> {
         let mut voices_metadata = if self.metadata_file.exists() {
@@ -414,8 +692,11 @@ impl VoiceFeatureManager {
         Ok(())
     }
 
-    /// 更新音色元数据名称
-    async fn update_voice_metadata_name(&self, voice_id: &str, new_name: String) -> Result<()> {
+    /// 更新音色元数据
+    async fn update_voice_metadata<F>(&self, voice_id: &str, update: F) -> Result<()>
+    where
+        F: FnOnce(&mut VoiceMetadata),
+    {
         if !self.metadata_file.exists() {
             return Err(anyhow!("元数据文件不存在"));
         }
@@ -424,7 +705,7 @@ impl VoiceFeatureManager {
         let mut voices_metadata: VoicesMetadata = serde_json::from_str(&content)?;
 
         if let Some(voice_meta) = voices_metadata.voices.iter_mut().find(|v| v.id == voice_id) {
-            voice_meta.name = new_name;
+            update(voice_meta);
         }
 
         let content = serde_json::to_string_pretty(&voices_metadata)?;
@@ -491,6 +772,50 @@ impl VoiceFeatureManager {
     pub fn get_raf_dir(&self) -> &Path {
         &self.raf_dir
     }
+
+    /// 获取上传音频临时目录路径
+    pub fn upload_temp_dir(&self) -> PathBuf {
+        self.raf_dir.join("temp").join("upload_temp_files")
+    }
+
+    /// 清理上传临时目录中残留的文件，返回删除的文件数
+    pub fn clean_upload_temp_files(&self) -> Result<usize> {
+        let upload_temp_dir = self.upload_temp_dir();
+        if !upload_temp_dir.exists() {
+            return Ok(0);
+        }
+
+        let mut removed = 0;
+        for entry in fs::read_dir(&upload_temp_dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// 音色ID只允许字母、数字、下划线和连字符（会用作文件名）
+fn is_valid_voice_id(voice_id: &str) -> bool {
+    !voice_id.is_empty()
+        && voice_id.len() <= 128
+        && voice_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// 去除标签首尾空白，丢弃空标签和重复标签（保持原有顺序）
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
 }
 
 #[cfg(test)]
@@ -508,6 +833,7 @@ mod tests {
             .save_voice_feature(
                 "测试音色".to_string(),
                 "这是一个测试音色".to_string(),
+                String::new(),
                 vec![1, 2, 3, 4, 5],
                 vec![6, 7, 8, 9, 10],
                 5.0,
@@ -539,9 +865,127 @@ mod tests {
         assert_eq!(voices_after_rename.len(), 1);
         assert_eq!(voices_after_rename[0].name, "新名称");
 
+        // 测试修改描述和标签
+        let updated = manager
+            .update_voice(
+                &voice_id,
+                VoiceUpdate {
+                    description: Some(" 温柔女声 ".to_string()),
+                    tags: Some(vec![
+                        "女声".to_string(),
+                        " ".to_string(),
+                        "女声".to_string(),
+                    ]),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.description, "温柔女声");
+        assert_eq!(updated.tags, vec!["女声"]);
+        manager.clear_cache();
+        let reloaded = manager.load_voice_feature(&voice_id).await.unwrap();
+        assert_eq!(reloaded.name, "新名称");
+        assert_eq!(reloaded.tags, vec!["女声"]);
+        assert_eq!(
+            manager.list_voices().await.unwrap()[0].description,
+            "温柔女声"
+        );
+        assert!(manager
+            .update_voice(
+                &voice_id,
+                VoiceUpdate {
+                    name: Some("  ".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .is_err());
+
         // 测试删除
         manager.delete_voice(&voice_id).await.unwrap();
         let voices_after_delete = manager.list_voices().await.unwrap();
         assert_eq!(voices_after_delete.len(), 0);
     }
+
+    #[tokio::test]
+    async fn test_voice_package_export_import() {
+        let source_dir = TempDir::new().unwrap();
+        let source = VoiceFeatureManager::new(source_dir.path()).unwrap();
+        let voice_id = source
+            .save_voice_feature(
+                "导出音色".to_string(),
+                "参考文本".to_string(),
+                "描述".to_string(),
+                vec![1, 2, 3],
+                vec![4, 5, 6],
+                3.0,
+                16000,
+            )
+            .await
+            .unwrap();
+        let audio_path = source_dir.path().join("upload.WAV");
+        fs::write(&audio_path, b"RIFF-test-audio").unwrap();
+        source
+            .save_reference_audio(&voice_id, &audio_path)
+            .await
+            .unwrap();
+
+        let package = source.export_voice(&voice_id, true).await.unwrap();
+        assert_eq!(package.reference_audio.as_ref().unwrap().format, "wav");
+        assert!(source
+            .export_voice(&voice_id, false)
+            .await
+            .unwrap()
+            .reference_audio
+            .is_none());
+
+        // 导入到另一台服务器，保留原ID和参考音频
+        let target_dir = TempDir::new().unwrap();
+        let target = VoiceFeatureManager::new(target_dir.path()).unwrap();
+        let result = target.import_voice(package.clone()).await.unwrap();
+        assert_eq!(result.voice_id, voice_id);
+        assert!(!result.already_exists);
+        assert!(target.has_reference_audio(&voice_id));
+        target.clear_cache();
+        let imported = target.load_voice_feature(&voice_id).await.unwrap();
+        assert_eq!(imported.semantic_tokens, vec![4, 5, 6]);
+
+        // 重复导入相同内容不会产生新音色
+        let again = target.import_voice(package.clone()).await.unwrap();
+        assert!(again.already_exists);
+        assert_eq!(target.list_voices().await.unwrap().len(), 1);
+
+        // 同ID但内容不同时分配新ID
+        target
+            .rename_voice(&voice_id, "本地修改".to_string())
+            .await
+            .unwrap();
+        let renamed = target.import_voice(package.clone()).await.unwrap();
+        assert_ne!(renamed.voice_id, voice_id);
+        assert_eq!(renamed.original_id, voice_id);
+        assert_eq!(
+            target
+                .load_voice_feature(&renamed.voice_id)
+                .await
+                .unwrap()
+                .name,
+            "导出音色"
+        );
+        assert_eq!(target.list_voices().await.unwrap().len(), 2);
+
+        // 篡改内容后校验失败
+        let mut tampered = package.clone();
+        tampered.voice.global_tokens.push(7);
+        assert!(target.import_voice(tampered).await.is_err());
+        let mut forged = package;
+        forged.voice.id = "../escape".to_string();
+        forged.metadata.id = forged.voice.id.clone();
+        forged.voice.checksum = VoiceFeatureManager::feature_checksum(&forged.voice).unwrap();
+        forged.checksum = VoiceFeatureManager::package_checksum(&forged).unwrap();
+        assert!(target.import_voice(forged).await.is_err());
+
+        source.delete_voice(&voice_id).await.unwrap();
+        assert!(!source.has_reference_audio(&voice_id));
+    }
 }